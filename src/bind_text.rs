@@ -0,0 +1,317 @@
+//! Two-way binding between a [`String`] on a component and an editor's text
+//!
+//! See [`BindText`]
+
+use std::marker::PhantomData;
+
+use cosmic_text::{Action, Motion, Selection};
+
+use crate::{
+    cosmic_edit::DefaultAttrs,
+    input::{CosmicTextChanged, InputSet},
+    placeholder::Placeholder,
+    prelude::*,
+};
+
+/// Adds support for [`BindText<C>`].
+///
+/// Add one for each component type you bind to.
+pub struct BindTextPlugin<C>(PhantomData<C>);
+
+impl<C> Default for BindTextPlugin<C> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<C: Component> Plugin for BindTextPlugin<C> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (write_bound_text::<C>, read_bound_text::<C>)
+                .chain()
+                .after(InputSet),
+        );
+    }
+}
+
+/// When the text is written back to the bound component
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BindWriteBack {
+    /// Whenever the text changes
+    #[default]
+    OnEdit,
+    /// When the editor loses focus
+    OnBlur,
+}
+
+/// Mirrors a [`String`] field of the component `C` on the `source` entity.
+///
+/// The buffer is updated whenever the field changes, and the field is updated when the
+/// text is edited, or when unfocussed with [`BindText::on_blur`]. Requires a
+/// [`BindTextPlugin<C>`].
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_cosmic_edit::prelude::*;
+/// use bevy_cosmic_edit::bind_text::{BindText, BindTextPlugin};
+///
+/// #[derive(Component)]
+/// struct Player {
+///     name: String,
+/// }
+///
+/// # fn setup(mut commands: Commands) {
+/// let player = commands.spawn(Player { name: "Ferris".into() }).id();
+/// commands.spawn((
+///     TextEdit,
+///     BindText::new(player, |p: &Player| &p.name, |p, name| p.name = name),
+/// ));
+/// # }
+/// # fn main() {
+/// #     App::new()
+/// #         .add_plugins(MinimalPlugins)
+/// #         .add_plugins(CosmicEditPlugin::default())
+/// #         .add_plugins(BindTextPlugin::<Player>::default())
+/// #         .add_systems(Startup, setup);
+/// # }
+/// ```
+#[derive(Component)]
+pub struct BindText<C: Component> {
+    pub source: Entity,
+    pub write_back: BindWriteBack,
+    get: fn(&C) -> &str,
+    set: fn(&mut C, String),
+    /// Text both sides agreed on the last time they were synced
+    last_synced: Option<String>,
+}
+
+impl<C: Component> BindText<C> {
+    pub fn new(source: Entity, get: fn(&C) -> &str, set: fn(&mut C, String)) -> Self {
+        Self {
+            source,
+            write_back: BindWriteBack::OnEdit,
+            get,
+            set,
+            last_synced: None,
+        }
+    }
+
+    /// Only writes the text back to the component when unfocussed
+    pub fn on_blur(mut self) -> Self {
+        self.write_back = BindWriteBack::OnBlur;
+        self
+    }
+
+    fn write(&mut self, source: &mut Mut<C>, text: String) {
+        if self.last_synced.as_ref() == Some(&text) {
+            return;
+        }
+        if (self.get)(source) != text {
+            (self.set)(source, text.clone());
+        }
+        self.last_synced = Some(text);
+    }
+}
+
+/// Updates bound components from the editors
+fn write_bound_text<C: Component>(
+    mut bindings: Query<(&mut BindText<C>, EditorBuffer, Option<&Placeholder>)>,
+    mut sources: Query<&mut C>,
+    mut evr_changed: EventReader<CosmicTextChanged>,
+    focused: Res<FocusedWidget>,
+    mut last_focused: Local<Option<Entity>>,
+) {
+    for CosmicTextChanged((entity, text)) in evr_changed.read() {
+        let Ok((mut binding, ..)) = bindings.get_mut(*entity) else {
+            continue;
+        };
+        if binding.write_back != BindWriteBack::OnEdit {
+            continue;
+        }
+        if let Ok(mut source) = sources.get_mut(binding.source) {
+            binding.write(&mut source, text.clone());
+        }
+    }
+
+    let blurred = last_focused.filter(|entity| focused.0 != Some(*entity));
+    *last_focused = focused.0;
+    let Some(Ok((mut binding, buffer, placeholder))) = blurred.map(|e| bindings.get_mut(e)) else {
+        return;
+    };
+    if binding.write_back != BindWriteBack::OnBlur {
+        return;
+    }
+    let text = match placeholder {
+        Some(placeholder) if placeholder.is_active() => String::new(),
+        _ => buffer.get_text(),
+    };
+    if let Ok(mut source) = sources.get_mut(binding.source) {
+        binding.write(&mut source, text);
+    }
+}
+
+/// Updates editors from the bound components
+fn read_bound_text<C: Component>(
+    mut bindings: Query<(&mut BindText<C>, EditorBuffer, &DefaultAttrs)>,
+    sources: Query<Ref<C>>,
+    mut font_system: ResMut<CosmicFontSystem>,
+) {
+    for (mut binding, mut buffer, attrs) in bindings.iter_mut() {
+        let Ok(source) = sources.get(binding.source) else {
+            continue;
+        };
+        if !source.is_changed() && binding.last_synced.is_some() {
+            continue;
+        }
+        let text = (binding.get)(&source);
+        if binding.last_synced.as_deref() == Some(text) {
+            continue;
+        }
+        binding.last_synced = Some(text.to_owned());
+        buffer.set_text(&mut font_system, text, attrs.as_attrs());
+        if let Some(editor) = buffer.editor() {
+            editor.set_selection(Selection::None);
+            editor.action(&mut font_system.0, Action::Motion(Motion::BufferEnd));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::{
+        keyboard::{Key, KeyboardInput},
+        ButtonState,
+    };
+
+    use super::*;
+    use crate::input::{delta::send_programmatic_edits, keyboard::kb_input_text};
+    use crate::input_filter::CosmicInputRejected;
+
+    #[derive(Component)]
+    struct Player {
+        name: String,
+    }
+
+    struct Harness {
+        world: World,
+        frame: Schedule,
+        player: Entity,
+        editor: Entity,
+    }
+
+    impl Harness {
+        fn new(write_back: BindWriteBack) -> Self {
+            let mut world = World::new();
+            world.insert_resource(CosmicFontSystem(test_font_system()));
+            world.init_resource::<Assets<Image>>();
+            world.init_resource::<ButtonInput<KeyCode>>();
+            world.init_resource::<Events<KeyboardInput>>();
+            world.init_resource::<Events<CosmicTextChanged>>();
+            world.init_resource::<Events<CosmicInputRejected>>();
+            let player = world
+                .spawn(Player {
+                    name: "Ferris".into(),
+                })
+                .id();
+            let mut binding = BindText::new(player, |p: &Player| &p.name, |p, name| p.name = name);
+            binding.write_back = write_back;
+            let buffer = test_buffer(&mut world.resource_mut::<CosmicFontSystem>().0, "");
+            let editor = CosmicEditor::clone_from_buffer(&buffer);
+            let editor = world.spawn((buffer, editor, binding)).id();
+            world.insert_resource(FocusedWidget(Some(editor)));
+
+            let mut frame = Schedule::default();
+            frame.add_systems(
+                (
+                    kb_input_text,
+                    write_bound_text::<Player>,
+                    read_bound_text::<Player>,
+                    send_programmatic_edits,
+                )
+                    .chain(),
+            );
+            Self {
+                world,
+                frame,
+                player,
+                editor,
+            }
+        }
+
+        fn update(&mut self) {
+            self.frame.run(&mut self.world);
+            self.world.resource_mut::<Events<KeyboardInput>>().update();
+            self.world
+                .resource_mut::<Events<CosmicTextChanged>>()
+                .update();
+        }
+
+        fn type_text(&mut self, text: &str) {
+            for c in text.chars() {
+                self.world.send_event(KeyboardInput {
+                    key_code: KeyCode::KeyA,
+                    logical_key: Key::Character(c.to_string().into()),
+                    state: ButtonState::Pressed,
+                    repeat: false,
+                    window: Entity::PLACEHOLDER,
+                });
+            }
+            self.update();
+        }
+
+        fn text(&self) -> String {
+            self.world
+                .get::<CosmicEditor>(self.editor)
+                .unwrap()
+                .get_text()
+        }
+
+        fn name(&self) -> &str {
+            &self.world.get::<Player>(self.player).unwrap().name
+        }
+
+        fn set_name(&mut self, name: &str) {
+            self.world.get_mut::<Player>(self.player).unwrap().name = name.into();
+            self.update();
+        }
+    }
+
+    #[test]
+    fn writes_back_on_edit() {
+        let mut harness = Harness::new(BindWriteBack::OnEdit);
+        harness.update();
+        assert_eq!(harness.text(), "Ferris");
+        assert_eq!(harness.name(), "Ferris");
+
+        harness.type_text("!");
+        assert_eq!(harness.text(), "Ferris!");
+        assert_eq!(harness.name(), "Ferris!");
+
+        harness.set_name("Crab");
+        assert_eq!(harness.text(), "Crab");
+        harness.update();
+        assert_eq!(harness.name(), "Crab");
+
+        harness.type_text("s");
+        assert_eq!(harness.name(), "Crabs");
+    }
+
+    #[test]
+    fn writes_back_on_blur() {
+        let mut harness = Harness::new(BindWriteBack::OnBlur);
+        harness.update();
+        assert_eq!(harness.text(), "Ferris");
+
+        harness.type_text("!");
+        assert_eq!(harness.text(), "Ferris!");
+        assert_eq!(harness.name(), "Ferris");
+
+        harness.world.resource_mut::<FocusedWidget>().0 = None;
+        harness.update();
+        assert_eq!(harness.name(), "Ferris!");
+
+        harness.set_name("Crab");
+        assert_eq!(harness.text(), "Crab");
+    }
+}
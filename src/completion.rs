@@ -0,0 +1,508 @@
+//! Autocompletion popups, see [`Autocomplete`]
+//!
+//! While the popup is open, \[Up\] and \[Down\] choose an item, \[Tab\] or \[Enter\] insert it
+//! and \[Esc\] closes the popup, instead of being handled by the editor
+
+use std::sync::Arc;
+
+use cosmic_text::{Cursor, Edit};
+
+use crate::{
+    input::{
+        delta::{CosmicTextEdited, EditCause},
+        InputSet,
+    },
+    prelude::*,
+    render::caret_position,
+    render_implementations::RelativeQuery,
+    BufferMutExtras,
+};
+
+pub(crate) struct CompletionPlugin;
+
+impl Plugin for CompletionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CompletionStyle>()
+            .add_observer(query_completions)
+            .add_systems(Update, completion_keys.before(InputSet))
+            .add_systems(Update, close_moved_completions.after(InputSet))
+            .add_systems(
+                PostUpdate,
+                update_completion_popups.after(crate::render::RenderSet),
+            );
+    }
+}
+
+/// A single completion
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionItem {
+    /// Shown in the popup
+    pub label: String,
+    /// Replaces the token being completed
+    pub text: String,
+}
+
+impl CompletionItem {
+    pub fn new(text: impl Into<String>) -> Self {
+        let text = text.into();
+        Self {
+            label: text.clone(),
+            text,
+        }
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self
+    }
+}
+
+/// Completions for the token before the cursor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completions {
+    /// Length in bytes of the token before the cursor that an item replaces, e.g. `@pl`
+    pub token_len: usize,
+    pub items: Vec<CompletionItem>,
+}
+
+/// Suggests completions while typing
+pub trait CompletionProvider: Send + Sync + 'static {
+    /// Completions for the end of `before_cursor`, the text on the cursor's line before it,
+    /// or `None` if there is nothing to complete
+    fn complete(&self, before_cursor: &str) -> Option<Completions>;
+}
+
+/// Completes words from a list, e.g. identifiers, or player names after `@`
+///
+/// ```
+/// use bevy_cosmic_edit::completion::{CompletionProvider, WordCompletions};
+///
+/// let players = WordCompletions::new(["Alice", "Bob"]).with_trigger('@');
+/// let completions = players.complete("hi @al").unwrap();
+/// assert_eq!(completions.token_len, 3);
+/// assert_eq!(completions.items[0].text, "@Alice");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct WordCompletions {
+    pub words: Vec<String>,
+    /// Only complete words typed after this character, which is part of the token
+    pub trigger: Option<char>,
+}
+
+impl WordCompletions {
+    pub fn new(words: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            words: words.into_iter().map(Into::into).collect(),
+            trigger: None,
+        }
+    }
+
+    pub fn with_trigger(mut self, trigger: char) -> Self {
+        self.trigger = Some(trigger);
+        self
+    }
+}
+
+impl CompletionProvider for WordCompletions {
+    fn complete(&self, before_cursor: &str) -> Option<Completions> {
+        let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+        let mut token_start = before_cursor
+            .char_indices()
+            .rev()
+            .find(|(_, c)| !is_word_char(*c))
+            .map_or(0, |(index, c)| index + c.len_utf8());
+        let word = &before_cursor[token_start..];
+        let trigger = match self.trigger {
+            Some(trigger) => {
+                let before = before_cursor[..token_start].strip_suffix(trigger)?;
+                if before.ends_with(|c: char| !c.is_whitespace()) {
+                    return None;
+                }
+                token_start = before.len();
+                trigger.to_string()
+            }
+            None if word.is_empty() => return None,
+            None => String::new(),
+        };
+
+        let prefix = word.to_lowercase();
+        let items = self
+            .words
+            .iter()
+            .filter(|candidate| candidate.as_str() != word)
+            .filter(|candidate| candidate.to_lowercase().starts_with(&prefix))
+            .map(|candidate| CompletionItem::new(format!("{trigger}{candidate}")))
+            .collect::<Vec<_>>();
+        (!items.is_empty()).then(|| Completions {
+            token_len: before_cursor.len() - token_start,
+            items,
+        })
+    }
+}
+
+/// Shows a popup of completions near the cursor while typing.
+///
+/// Providers are asked in order, and the first with completions is used.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_cosmic_edit::prelude::*;
+/// use bevy_cosmic_edit::completion::{Autocomplete, WordCompletions};
+///
+/// # fn setup(mut commands: Commands) {
+/// commands.spawn((
+///     TextEdit,
+///     Autocomplete::new(WordCompletions::new(["Alice", "Bob"]).with_trigger('@'))
+///         .with_provider(WordCompletions::new(["help", "whisper"]).with_trigger('/')),
+/// ));
+/// # }
+/// ```
+#[derive(Component, Clone)]
+pub struct Autocomplete {
+    providers: Vec<Arc<dyn CompletionProvider>>,
+    /// Most items shown at once
+    pub max_items: usize,
+    active: Option<ActiveCompletion>,
+    /// Root node of the open popup
+    popup: Option<Entity>,
+}
+
+#[derive(Debug, Clone)]
+struct ActiveCompletion {
+    token_start: Cursor,
+    /// Where the cursor was when completing, the popup closes when it moves
+    cursor: Cursor,
+    items: Vec<CompletionItem>,
+    selected: usize,
+}
+
+impl Autocomplete {
+    pub fn new(provider: impl CompletionProvider) -> Self {
+        Self {
+            providers: vec![Arc::new(provider)],
+            max_items: 8,
+            active: None,
+            popup: None,
+        }
+    }
+
+    pub fn with_provider(mut self, provider: impl CompletionProvider) -> Self {
+        self.providers.push(Arc::new(provider));
+        self
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// The completions shown in the popup
+    pub fn items(&self) -> &[CompletionItem] {
+        self.active
+            .as_ref()
+            .map_or(&[], |active| active.items.as_slice())
+    }
+
+    /// The item inserted by \[Tab\] or \[Enter\]
+    pub fn selected(&self) -> Option<&CompletionItem> {
+        self.active
+            .as_ref()
+            .and_then(|active| active.items.get(active.selected))
+    }
+
+    pub fn close(&mut self) {
+        self.active = None;
+    }
+
+    fn complete(&self, line: &str, cursor: Cursor) -> Option<ActiveCompletion> {
+        let before_cursor = line.get(..cursor.index)?;
+        let completions = self
+            .providers
+            .iter()
+            .filter_map(|provider| provider.complete(before_cursor))
+            .find(|completions| !completions.items.is_empty())?;
+        let token_len = completions.token_len.min(cursor.index);
+        let items = completions
+            .items
+            .into_iter()
+            .take(self.max_items)
+            .collect::<Vec<_>>();
+        (!items.is_empty()).then(|| ActiveCompletion {
+            token_start: Cursor::new(cursor.line, cursor.index - token_len),
+            cursor,
+            items,
+            selected: 0,
+        })
+    }
+}
+
+/// How completion popups look
+#[derive(Resource, Clone)]
+pub struct CompletionStyle {
+    pub text_font: TextFont,
+    pub text_color: Color,
+    pub background_color: Color,
+    pub selected_color: Color,
+}
+
+impl Default for CompletionStyle {
+    fn default() -> Self {
+        Self {
+            text_font: TextFont::from_font_size(14.),
+            text_color: Color::BLACK,
+            background_color: Color::WHITE,
+            selected_color: bevy::color::palettes::css::LIGHT_GRAY.into(),
+        }
+    }
+}
+
+fn query_completions(
+    trigger: Trigger<CosmicTextEdited>,
+    mut editors: Query<(&mut Autocomplete, &CosmicEditor)>,
+) {
+    let Ok((mut autocomplete, editor)) = editors.get_mut(trigger.entity()) else {
+        return;
+    };
+    if trigger.event().cause != EditCause::Typed {
+        autocomplete.close();
+        return;
+    }
+    let cursor = editor.cursor();
+    autocomplete.active = editor.with_buffer(|buffer| {
+        let line = buffer.lines.get(cursor.line)?;
+        autocomplete.complete(line.text(), cursor)
+    });
+}
+
+/// Handles keys for the focused popup before the editor does
+fn completion_keys(
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    focused: Res<FocusedWidget>,
+    mut editors: Query<(&mut Autocomplete, EditorBuffer)>,
+) {
+    let Some(Ok((mut autocomplete, mut buffer))) = focused.0.map(|entity| editors.get_mut(entity))
+    else {
+        return;
+    };
+    // changing the completion rebuilds the popup, so only touch it when a key is pressed
+    let handled = [
+        KeyCode::ArrowDown,
+        KeyCode::ArrowUp,
+        KeyCode::Escape,
+        KeyCode::Tab,
+        KeyCode::Enter,
+    ];
+    if !autocomplete.is_open() || !keys.any_just_pressed(handled) {
+        return;
+    }
+    let Some(active) = autocomplete.active.as_mut() else {
+        return;
+    };
+
+    let len = active.items.len();
+    if keys.clear_just_pressed(KeyCode::ArrowDown) {
+        active.selected = (active.selected + 1) % len;
+    }
+    if keys.clear_just_pressed(KeyCode::ArrowUp) {
+        active.selected = (active.selected + len - 1) % len;
+    }
+    if keys.clear_just_pressed(KeyCode::Escape) {
+        autocomplete.close();
+        return;
+    }
+    let accept = keys.clear_just_pressed(KeyCode::Tab) | keys.clear_just_pressed(KeyCode::Enter);
+    if accept {
+        let (start, cursor) = (active.token_start, active.cursor);
+        let text = active.items[active.selected].text.clone();
+        autocomplete.close();
+        buffer.replace_range(start, cursor, &text);
+    }
+}
+
+/// Closes popups when the cursor moves or the editor loses focus
+fn close_moved_completions(mut editors: Query<(&mut Autocomplete, Option<&CosmicEditor>)>) {
+    for (mut autocomplete, editor) in editors.iter_mut() {
+        let moved = |active: &ActiveCompletion| {
+            editor.is_none_or(|editor| {
+                let cursor = editor.cursor();
+                (cursor.line, cursor.index) != (active.cursor.line, active.cursor.index)
+            })
+        };
+        if autocomplete.active.as_ref().is_some_and(moved) {
+            autocomplete.close();
+        }
+    }
+}
+
+fn update_completion_popups(
+    mut commands: Commands,
+    style: Res<CompletionStyle>,
+    mut editors: Query<(&mut Autocomplete, Option<&mut CosmicEditor>, RelativeQuery)>,
+    mut popups: Query<&mut Node>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut font_system: ResMut<CosmicFontSystem>,
+) {
+    for (mut autocomplete, editor, buffer_relative) in editors.iter_mut() {
+        let rebuild = autocomplete.is_changed();
+        // the popup entity isn't a change to react to
+        let autocomplete = autocomplete.bypass_change_detection();
+        let (Some(active), Some(mut editor)) = (&autocomplete.active, editor) else {
+            if let Some(popup) = autocomplete.popup.take() {
+                commands.entity(popup).despawn_recursive();
+            }
+            continue;
+        };
+
+        // measuring the editor doesn't change it
+        let mut editor = editor
+            .bypass_change_detection()
+            .borrow_with(&mut font_system.0);
+        let buffer_size = editor.expected_size();
+        let Some(position) = editor
+            .with_buffer(|buffer| caret_position(buffer, active.token_start))
+            .and_then(|(x, y, height)| {
+                let below_caret = Vec2::new(x as f32, (y + height) as f32);
+                buffer_relative
+                    .buffer_to_viewport(below_caret, buffer_size, &cameras)
+                    .ok()
+            })
+        else {
+            continue;
+        };
+
+        if let Some(mut node) = autocomplete
+            .popup
+            .and_then(|popup| popups.get_mut(popup).ok())
+        {
+            node.left = Val::Px(position.x);
+            node.top = Val::Px(position.y);
+            if !rebuild {
+                continue;
+            }
+        }
+        if let Some(popup) = autocomplete.popup.take() {
+            commands.entity(popup).despawn_recursive();
+        }
+
+        let popup = commands
+            .spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(position.x),
+                    top: Val::Px(position.y),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(2.)),
+                    ..default()
+                },
+                BackgroundColor(style.background_color),
+                GlobalZIndex(i32::MAX),
+            ))
+            .with_children(|parent| {
+                for (i, item) in active.items.iter().enumerate() {
+                    let background = match i == active.selected {
+                        true => style.selected_color,
+                        false => style.background_color,
+                    };
+                    parent
+                        .spawn((
+                            Node {
+                                padding: UiRect::axes(Val::Px(8.), Val::Px(2.)),
+                                ..default()
+                            },
+                            BackgroundColor(background),
+                        ))
+                        .with_child((
+                            Text::new(item.label.clone()),
+                            style.text_font.clone(),
+                            TextColor(style.text_color),
+                        ));
+                }
+            })
+            .id();
+        autocomplete.popup = Some(popup);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completes_current_token() {
+        let autocomplete =
+            Autocomplete::new(WordCompletions::new(["Alice", "Albert", "Bob"]).with_trigger('@'))
+                .with_provider(WordCompletions::new(["println", "print", "panic"]));
+
+        let complete = |line: &str| {
+            let cursor = Cursor::new(0, line.len());
+            autocomplete.complete(line, cursor).map(|active| {
+                let texts = active.items.into_iter().map(|item| item.text);
+                (active.token_start.index, texts.collect::<Vec<_>>())
+            })
+        };
+
+        assert_eq!(
+            complete("hey @al"),
+            Some((4, vec!["@Alice".to_owned(), "@Albert".to_owned()]))
+        );
+        assert_eq!(complete("email@al"), None);
+        assert_eq!(
+            complete("    prin"),
+            Some((4, vec!["println".to_owned(), "print".to_owned()]))
+        );
+        // already complete
+        assert_eq!(complete("panic"), None);
+        assert_eq!(complete("x = "), None);
+    }
+
+    #[test]
+    fn enter_only_accepts_the_completion() {
+        use bevy::{
+            ecs::system::RunSystemOnce,
+            input::{
+                keyboard::{Key, KeyboardInput},
+                ButtonState,
+            },
+        };
+
+        use crate::{
+            input::{keyboard::kb_input_text, CosmicTextChanged},
+            input_filter::CosmicInputRejected,
+        };
+
+        let mut font_system = test_font_system();
+        let buffer = test_buffer(&mut font_system, "prin");
+        let mut editor = CosmicEditor::clone_from_buffer(&buffer);
+        let cursor = Cursor::new(0, 4);
+        editor.set_cursor(cursor);
+        let mut autocomplete = Autocomplete::new(WordCompletions::new(["println", "print"]));
+        autocomplete.active = autocomplete.complete("prin", cursor);
+        assert!(autocomplete.is_open());
+
+        let mut world = World::new();
+        world.insert_resource(CosmicFontSystem(font_system));
+        world.init_resource::<Assets<Image>>();
+        world.init_resource::<Events<KeyboardInput>>();
+        world.init_resource::<Events<CosmicTextChanged>>();
+        world.init_resource::<Events<CosmicInputRejected>>();
+        let entity = world.spawn((buffer, editor, autocomplete)).id();
+        world.insert_resource(FocusedWidget(Some(entity)));
+
+        let mut keys = ButtonInput::<KeyCode>::default();
+        keys.press(KeyCode::Enter);
+        world.insert_resource(keys);
+        world.send_event(KeyboardInput {
+            key_code: KeyCode::Enter,
+            logical_key: Key::Enter,
+            state: ButtonState::Pressed,
+            repeat: false,
+            window: Entity::PLACEHOLDER,
+        });
+        world.run_system_once(completion_keys).unwrap();
+        world.run_system_once(kb_input_text).unwrap();
+
+        let editor = world.get::<CosmicEditor>(entity).unwrap();
+        assert_eq!(editor.get_text(), "println");
+        // the completion is reported as a programmatic edit later on
+        assert!(world.resource::<Events<CosmicTextChanged>>().is_empty());
+    }
+}
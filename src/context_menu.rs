@@ -0,0 +1,510 @@
+//! Optional right-click context menu for [`CosmicEditBuffer`]s, built with `bevy_ui`
+
+use bevy::ecs::{component::ComponentId, world::DeferredWorld};
+use cosmic_text::{Action, Motion, Selection};
+
+use crate::{
+    cosmic_edit::DefaultAttrs,
+    input::{
+        clipboard::{
+            apply_clipboard_op, ClipboardOp, ClipboardOutcome, CosmicClipboard, PasteFilter,
+        },
+        delta::EditCause,
+        undo::{apply_undo_op, UndoHistory, UndoOp},
+        CosmicTextChanged, InputSet,
+    },
+    input_filter::{CosmicInputRejected, InputFilter},
+    password::Password,
+    placeholder::Placeholder,
+    prelude::*,
+    MaxChars, MaxLines,
+};
+
+pub(crate) struct ContextMenuPlugin;
+
+impl Plugin for ContextMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ContextMenuStyle>()
+            .init_resource::<OpenContextMenu>()
+            .add_systems(
+                Update,
+                (close_context_menu, highlight_hovered_items).after(InputSet),
+            )
+            .add_observer(handle_builtin_actions)
+            .register_type::<ContextMenuAction>()
+            .register_type::<ContextMenuItemChosen>();
+    }
+}
+
+/// Add to an entity with a [`CosmicEditBuffer`] to open a context menu on right click
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_cosmic_edit::prelude::*;
+/// use bevy_cosmic_edit::context_menu::{ContextMenu, ContextMenuAction, ContextMenuItemChosen};
+///
+/// # fn setup(mut commands: Commands) {
+/// commands
+///     .spawn((
+///         TextEdit,
+///         ContextMenu::default().with_item("Shout", "shout"),
+///     ))
+///     .observe(|trigger: Trigger<ContextMenuItemChosen>| {
+///         if trigger.event().0 == ContextMenuAction::Custom("shout".into()) {
+///             info!("Shouting from {:?}", trigger.entity());
+///         }
+///     });
+/// # }
+/// ```
+#[derive(Component, Default, Debug, Clone)]
+#[component(on_add = add_context_menu_observer)]
+pub struct ContextMenu {
+    /// App specific entries, shown after the built-in ones
+    pub custom_items: Vec<ContextMenuItem>,
+}
+
+impl ContextMenu {
+    /// Adds a [`ContextMenuAction::Custom`] entry
+    pub fn with_item(mut self, label: impl Into<String>, id: impl Into<String>) -> Self {
+        self.custom_items.push(ContextMenuItem {
+            label: label.into(),
+            action: ContextMenuAction::Custom(id.into()),
+        });
+        self
+    }
+}
+
+/// A single entry of a [`ContextMenu`]
+#[derive(Debug, Clone)]
+pub struct ContextMenuItem {
+    pub label: String,
+    pub action: ContextMenuAction,
+}
+
+/// What a [`ContextMenuItem`] does when chosen.
+///
+/// Every variant except [`ContextMenuAction::Custom`] is handled by bevy_cosmic_edit
+#[derive(Reflect, Debug, Clone, PartialEq, Eq)]
+pub enum ContextMenuAction {
+    Undo,
+    Redo,
+    Cut,
+    Copy,
+    Paste,
+    SelectAll,
+    /// App defined action, identified by the given id
+    Custom(String),
+}
+
+impl ContextMenuAction {
+    fn label(&self) -> &str {
+        match self {
+            ContextMenuAction::Undo => "Undo",
+            ContextMenuAction::Redo => "Redo",
+            ContextMenuAction::Cut => "Cut",
+            ContextMenuAction::Copy => "Copy",
+            ContextMenuAction::Paste => "Paste",
+            ContextMenuAction::SelectAll => "Select All",
+            ContextMenuAction::Custom(id) => id,
+        }
+    }
+}
+
+/// Triggered on the [`CosmicEditBuffer`] entity whenever an item of its [`ContextMenu`]
+/// is chosen
+#[derive(Event, Reflect, Debug, Clone)]
+pub struct ContextMenuItemChosen(pub ContextMenuAction);
+
+/// How spawned context menus look
+#[derive(Resource, Clone)]
+pub struct ContextMenuStyle {
+    pub text_font: TextFont,
+    pub text_color: Color,
+    pub background_color: Color,
+    pub hover_color: Color,
+}
+
+impl Default for ContextMenuStyle {
+    fn default() -> Self {
+        Self {
+            text_font: TextFont::from_font_size(14.),
+            text_color: Color::BLACK,
+            background_color: Color::WHITE,
+            hover_color: bevy::color::palettes::css::LIGHT_GRAY.into(),
+        }
+    }
+}
+
+/// The currently open context menu root node, if any
+#[derive(Resource, Default)]
+struct OpenContextMenu(Option<Entity>);
+
+/// Placed on every item node of a spawned context menu
+#[derive(Component)]
+struct ContextMenuEntry {
+    editor: Entity,
+    action: ContextMenuAction,
+}
+
+fn add_context_menu_observer(mut world: DeferredWorld, target: Entity, _: ComponentId) {
+    let mut observer = Observer::new(open_context_menu);
+    observer.watch_entity(target);
+    world.commands().spawn(observer);
+}
+
+fn open_context_menu(
+    trigger: Trigger<Pointer<Click>>,
+    mut commands: Commands,
+    mut focused: ResMut<FocusedWidget>,
+    mut open_menu: ResMut<OpenContextMenu>,
+    style: Res<ContextMenuStyle>,
+    menus: Query<(
+        &ContextMenu,
+        Option<&CosmicEditor>,
+        Option<&UndoHistory>,
+        Has<ReadOnly>,
+        Has<Password>,
+    )>,
+) {
+    if trigger.event().button != PointerButton::Secondary {
+        return;
+    }
+    let target = trigger.entity();
+    let Ok((menu, editor, history, readonly, password)) = menus.get(target) else {
+        return;
+    };
+
+    if let Some(previous) = open_menu.0.take() {
+        commands.entity(previous).despawn_recursive();
+    }
+    focused.0 = Some(target);
+
+    let items = builtin_actions(editor, history, readonly, password)
+        .into_iter()
+        .map(|action| ContextMenuItem {
+            label: action.label().to_owned(),
+            action,
+        })
+        .chain(menu.custom_items.iter().cloned());
+
+    let position = trigger.pointer_location.position;
+    let root = commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(position.x),
+                top: Val::Px(position.y),
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(2.)),
+                ..default()
+            },
+            BackgroundColor(style.background_color),
+            GlobalZIndex(i32::MAX),
+        ))
+        .with_children(|parent| {
+            for item in items {
+                parent
+                    .spawn((
+                        Button,
+                        Node {
+                            padding: UiRect::axes(Val::Px(8.), Val::Px(4.)),
+                            ..default()
+                        },
+                        BackgroundColor(style.background_color),
+                        ContextMenuEntry {
+                            editor: target,
+                            action: item.action,
+                        },
+                    ))
+                    .with_child((
+                        Text::new(item.label),
+                        style.text_font.clone(),
+                        TextColor(style.text_color),
+                    ))
+                    .observe(choose_item);
+            }
+        })
+        .id();
+    open_menu.0 = Some(root);
+}
+
+/// The built-in actions available for an editor, leaving out those that would do nothing
+fn builtin_actions(
+    editor: Option<&CosmicEditor>,
+    history: Option<&UndoHistory>,
+    readonly: bool,
+    password: bool,
+) -> Vec<ContextMenuAction> {
+    let has_selection = editor.is_some_and(|editor| editor.selection_bounds().is_some());
+    let mut actions = Vec::new();
+    if !readonly && history.is_some_and(UndoHistory::can_undo) {
+        actions.push(ContextMenuAction::Undo);
+    }
+    if !readonly && history.is_some_and(UndoHistory::can_redo) {
+        actions.push(ContextMenuAction::Redo);
+    }
+    // a password can be replaced, but not read
+    if has_selection && !readonly && !password {
+        actions.push(ContextMenuAction::Cut);
+    }
+    if has_selection && !password {
+        actions.push(ContextMenuAction::Copy);
+    }
+    if !readonly {
+        actions.push(ContextMenuAction::Paste);
+    }
+    actions.push(ContextMenuAction::SelectAll);
+    actions
+}
+
+fn choose_item(
+    trigger: Trigger<Pointer<Click>>,
+    mut commands: Commands,
+    mut open_menu: ResMut<OpenContextMenu>,
+    entries: Query<&ContextMenuEntry>,
+) {
+    if trigger.event().button != PointerButton::Primary {
+        return;
+    }
+    let Ok(entry) = entries.get(trigger.entity()) else {
+        return;
+    };
+
+    commands.trigger_targets(ContextMenuItemChosen(entry.action.clone()), entry.editor);
+    if let Some(menu) = open_menu.0.take() {
+        commands.entity(menu).despawn_recursive();
+    }
+}
+
+/// Closes the open menu when clicking anywhere outside it, or pressing \[Esc\]
+fn close_context_menu(
+    mut commands: Commands,
+    mut open_menu: ResMut<OpenContextMenu>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    entries: Query<&Interaction, With<ContextMenuEntry>>,
+) {
+    let Some(menu) = open_menu.0 else {
+        return;
+    };
+    let over_menu = entries
+        .iter()
+        .any(|interaction| *interaction != Interaction::None);
+    let clicked_outside = mouse.get_just_pressed().len() != 0 && !over_menu;
+    if clicked_outside || keys.just_pressed(KeyCode::Escape) {
+        commands.entity(menu).despawn_recursive();
+        open_menu.0 = None;
+    }
+}
+
+fn highlight_hovered_items(
+    style: Res<ContextMenuStyle>,
+    mut entries: Query<
+        (&Interaction, &mut BackgroundColor),
+        (With<ContextMenuEntry>, Changed<Interaction>),
+    >,
+) {
+    for (interaction, mut background) in entries.iter_mut() {
+        background.0 = match interaction {
+            Interaction::None => style.background_color,
+            Interaction::Hovered | Interaction::Pressed => style.hover_color,
+        };
+    }
+}
+
+fn handle_builtin_actions(
+    trigger: Trigger<ContextMenuItemChosen>,
+    mut editors: Query<(
+        &mut CosmicEditor,
+        &MaxLines,
+        &MaxChars,
+        Has<ReadOnly>,
+        Has<Password>,
+        Option<&PasteFilter>,
+        Option<&InputFilter>,
+        Option<&mut UndoHistory>,
+        Option<&mut Placeholder>,
+        &DefaultAttrs,
+    )>,
+    mut font_system: ResMut<CosmicFontSystem>,
+    mut evw_changed: EventWriter<CosmicTextChanged>,
+    mut evw_rejected: EventWriter<CosmicInputRejected>,
+    mut clipboard: ResMut<CosmicClipboard>,
+    mut commands: Commands,
+) {
+    let entity = trigger.entity();
+    let Ok((
+        mut editor,
+        max_lines,
+        max_chars,
+        readonly,
+        password,
+        paste_filter,
+        input_filter,
+        history,
+        placeholder,
+        attrs,
+    )) = editors.get_mut(entity)
+    else {
+        return;
+    };
+
+    let op = match trigger.event().0 {
+        ContextMenuAction::Undo | ContextMenuAction::Redo => {
+            let (Some(mut history), false) = (history, readonly) else {
+                return;
+            };
+            let op = match trigger.event().0 {
+                ContextMenuAction::Undo => UndoOp::Undo,
+                _ => UndoOp::Redo,
+            };
+            let deltas = apply_undo_op(
+                op,
+                &mut history,
+                &mut editor,
+                placeholder,
+                attrs,
+                &mut font_system.0,
+            );
+            ClipboardOutcome::filtered(EditCause::Undo, Ok(deltas)).send_events(
+                entity,
+                || editor.get_text(),
+                &mut evw_changed,
+                &mut evw_rejected,
+                &mut commands,
+            );
+            return;
+        }
+        ContextMenuAction::Cut | ContextMenuAction::Copy if password => return,
+        ContextMenuAction::Cut => ClipboardOp::Cut,
+        ContextMenuAction::Copy => ClipboardOp::Copy,
+        ContextMenuAction::Paste => ClipboardOp::Paste,
+        ContextMenuAction::SelectAll => {
+            editor.action(&mut font_system.0, Action::Motion(Motion::BufferStart));
+            let cursor = editor.cursor();
+            editor.set_selection(Selection::Normal(cursor));
+            editor.action(&mut font_system.0, Action::Motion(Motion::BufferEnd));
+            return;
+        }
+        ContextMenuAction::Custom(_) => return,
+    };
+
+    apply_clipboard_op(
+        op,
+        &mut clipboard,
+        &mut editor,
+        entity,
+        max_lines,
+        max_chars,
+        readonly,
+        paste_filter,
+        input_filter,
+    )
+    .send_events(
+        entity,
+        || editor.get_text(),
+        &mut evw_changed,
+        &mut evw_rejected,
+        &mut commands,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input::{clipboard::MemoryClipboard, undo::record_undo_steps};
+
+    use super::*;
+
+    use ContextMenuAction::*;
+
+    fn editor(text: &str) -> CosmicEditor {
+        let mut font_system = test_font_system();
+        CosmicEditor::clone_from_buffer(&test_buffer(&mut font_system, text))
+    }
+
+    #[test]
+    fn lists_available_actions() {
+        let mut selected = editor("hello");
+        selected.set_selection(Selection::Normal(cosmic_text::Cursor::new(0, 0)));
+        selected.set_cursor(cosmic_text::Cursor::new(0, 5));
+        let unselected = editor("hello");
+
+        assert_eq!(
+            builtin_actions(Some(&selected), None, false, false),
+            [Cut, Copy, Paste, SelectAll]
+        );
+        assert_eq!(
+            builtin_actions(Some(&unselected), None, false, false),
+            [Paste, SelectAll]
+        );
+        assert_eq!(
+            builtin_actions(None, None, false, false),
+            [Paste, SelectAll]
+        );
+        assert_eq!(
+            builtin_actions(Some(&selected), None, true, false),
+            [Copy, SelectAll]
+        );
+        assert_eq!(
+            builtin_actions(Some(&selected), None, false, true),
+            [Paste, SelectAll]
+        );
+    }
+
+    #[test]
+    fn handles_builtin_actions() {
+        let mut font_system = test_font_system();
+        let buffer = test_buffer(&mut font_system, "hello");
+
+        let mut world = World::new();
+        world.insert_resource(CosmicFontSystem(font_system));
+        world.insert_resource(CosmicClipboard::new(MemoryClipboard::default()));
+        world.init_resource::<Events<CosmicTextChanged>>();
+        world.init_resource::<Events<CosmicInputRejected>>();
+        world.init_resource::<Assets<Image>>();
+        world.add_observer(handle_builtin_actions);
+        world.add_observer(record_undo_steps);
+        let editor = CosmicEditor::clone_from_buffer(&buffer);
+        let entity = world.spawn((buffer, editor)).id();
+
+        let choose = |world: &mut World, action: ContextMenuAction| {
+            world.trigger_targets(ContextMenuItemChosen(action), entity);
+            world.flush();
+            let editor = world.get::<CosmicEditor>(entity).unwrap();
+            let history = world.get::<UndoHistory>(entity).unwrap();
+            (
+                editor.get_text(),
+                builtin_actions(Some(editor), Some(history), false, false),
+            )
+        };
+
+        assert_eq!(
+            choose(&mut world, SelectAll),
+            ("hello".into(), vec![Cut, Copy, Paste, SelectAll])
+        );
+        assert_eq!(
+            choose(&mut world, Cut),
+            ("".into(), vec![Undo, Paste, SelectAll])
+        );
+        assert_eq!(
+            choose(&mut world, Paste),
+            ("hello".into(), vec![Undo, Paste, SelectAll])
+        );
+        assert_eq!(
+            choose(&mut world, Undo),
+            ("".into(), vec![Undo, Redo, Paste, SelectAll])
+        );
+        assert_eq!(
+            choose(&mut world, Redo),
+            ("hello".into(), vec![Undo, Paste, SelectAll])
+        );
+
+        // nothing is copied out of a password
+        world.entity_mut(entity).insert(Password::default());
+        choose(&mut world, SelectAll);
+        choose(&mut world, Cut);
+        assert_eq!(choose(&mut world, Copy).0, "hello");
+        let clipboard = world.resource_mut::<CosmicClipboard>().get_text().unwrap();
+        assert_eq!(clipboard.as_deref(), Some("hello"));
+    }
+}
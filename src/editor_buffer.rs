@@ -4,9 +4,15 @@
 //! of [`CosmicEditor`], which is the primary interface for mutating [`Buffer`].
 
 use bevy::ecs::query::QueryData;
-use cosmic_text::{Attrs, BufferRef, FontSystem, Shaping};
+use cosmic_text::{
+    Attrs, BufferLine, BufferRef, Change, Cursor, Edit, Editor, FontSystem, Selection, Shaping,
+};
 
-use crate::prelude::*;
+use crate::{
+    input::delta::{PendingTextEdits, TextDelta},
+    prelude::*,
+    rich_text::PendingRestyle,
+};
 
 pub(crate) struct EditorBufferPlugin;
 
@@ -35,6 +41,8 @@ pub mod editor;
 pub struct EditorBuffer {
     editor: Option<&'static mut CosmicEditor>,
     buffer: &'static mut CosmicEditBuffer,
+    pending_edits: &'static mut PendingTextEdits,
+    pending_restyle: &'static mut PendingRestyle,
 }
 
 impl std::ops::Deref for EditorBufferItem<'_> {
@@ -56,22 +64,34 @@ impl<'r, 's> EditorBufferItem<'_> {
         self.editor.as_deref_mut()
     }
 
+    /// The cursor of the [`CosmicEditor`], if focussed
+    pub(crate) fn cursor(&self) -> Option<Cursor> {
+        self.editor.as_deref().map(|editor| editor.cursor())
+    }
+
     /// Replace buffer text
+    ///
+    /// Sends [`CosmicTextChanged`](crate::input::CosmicTextChanged) and triggers
+    /// [`CosmicTextEdited`](crate::input::delta::CosmicTextEdited) with
+    /// [`EditCause::Programmatic`](crate::input::delta::EditCause::Programmatic) in [`PostUpdate`]
     pub fn set_text(
         &mut self,
         font_system: &mut FontSystem,
         text: &'s str,
         attrs: Attrs<'r>,
     ) -> &mut Self {
+        let old_text = self.get_text();
         self.get_raw_buffer_mut()
             .set_text(font_system, text, attrs, Shaping::Advanced);
         self.set_redraw(true);
+        self.pending_edits.record_replace(&old_text, text);
         self
     }
 
     /// Replace buffer text with rich text
     ///
-    /// Rich text is an iterable of `(&'s str, Attrs<'r>)`
+    /// Rich text is an iterable of `(&'s str, Attrs<'r>)`.
+    /// Sends change events like [`EditorBufferItem::set_text`]
     pub fn set_rich_text<I>(
         &mut self,
         font_system: &mut FontSystem,
@@ -81,12 +101,101 @@ impl<'r, 's> EditorBufferItem<'_> {
     where
         I: IntoIterator<Item = (&'s str, Attrs<'r>)>,
     {
+        let old_text = self.get_text();
         self.get_raw_buffer_mut()
             .set_rich_text(font_system, spans, attrs, Shaping::Advanced);
         self.set_redraw(true);
+        let new_text = self.get_text();
+        self.pending_edits.record_replace(&old_text, &new_text);
+        self
+    }
+
+    /// Replace buffer lines, keeping their formatting
+    ///
+    /// Sends change events like [`EditorBufferItem::set_text`]
+    pub(crate) fn set_lines(&mut self, lines: Vec<BufferLine>) -> &mut Self {
+        let old_text = self.get_text();
+        self.with_buffer_mut(|buffer| buffer.lines = lines);
+        self.set_redraw(true);
+        let new_text = self.get_text();
+        self.pending_edits.record_replace(&old_text, &new_text);
         self
     }
 
+    /// Replaces the text from `start` to `end` with `text`, leaving the cursor after it.
+    ///
+    /// Returns the edit as a single [`Change`]. It is undone as one step of the
+    /// [`UndoHistory`](crate::input::undo::UndoHistory), together with any other
+    /// programmatic edits made in the same frame.
+    /// Sends change events like [`EditorBufferItem::set_text`]
+    pub fn replace_range(&mut self, start: Cursor, end: Cursor, text: &str) -> Option<Change> {
+        let (_, change) = self.edit(|editor| {
+            editor.delete_range(start, end);
+            let end = editor.insert_at(start, text, None);
+            editor.set_selection(Selection::None);
+            editor.set_cursor(end);
+        });
+        change
+    }
+
+    /// Replace buffer text without sending change events, for display only changes
+    pub(crate) fn set_text_silently(
+        &mut self,
+        font_system: &mut FontSystem,
+        text: &str,
+        attrs: Attrs,
+    ) {
+        self.get_raw_buffer_mut()
+            .set_text(font_system, text, attrs, Shaping::Advanced);
+        self.set_redraw(true);
+    }
+
+    /// Runs `edit` as a single [`Change`] on the [`CosmicEditor`], or on a temporary editor
+    /// without one, returning the change if anything was edited.
+    ///
+    /// Sends change events like [`EditorBufferItem::set_text`]
+    pub(crate) fn edit<T>(
+        &mut self,
+        edit: impl FnOnce(&mut Editor<'static>) -> T,
+    ) -> (T, Option<Change>) {
+        fn as_change<T>(
+            editor: &mut Editor<'static>,
+            edit: impl FnOnce(&mut Editor<'static>) -> T,
+        ) -> (T, Option<Change>) {
+            editor.start_change();
+            let result = edit(editor);
+            let change = editor
+                .finish_change()
+                .filter(|change| !change.items.is_empty());
+            (result, change)
+        }
+
+        let (result, change) = match self.editor.as_deref_mut() {
+            Some(editor) => as_change(&mut editor.editor, edit),
+            None => {
+                let metrics = self.buffer.0.metrics();
+                let buffer = std::mem::replace(&mut self.buffer.0, Buffer::new_empty(metrics));
+                let mut editor = Editor::new(buffer);
+                let result = as_change(&mut editor, edit);
+                if let BufferRef::Owned(buffer) = editor.buffer_ref_mut() {
+                    std::mem::swap(&mut self.buffer.0, buffer);
+                }
+                result
+            }
+        };
+        if let Some(change) = &change {
+            let deltas = self.with_buffer(|buffer| TextDelta::from_change(buffer, change));
+            self.pending_edits.record(deltas);
+            self.set_redraw(true);
+        }
+        (result, change)
+    }
+
+    /// Sends [`CosmicAttrsChanged`](crate::rich_text::CosmicAttrsChanged) in [`PostUpdate`]
+    pub(crate) fn mark_restyled(&mut self) {
+        self.pending_restyle.0 = true;
+    }
+
     pub fn with_buffer_mut<F: FnOnce(&mut Buffer) -> T, T>(&mut self, f: F) -> T {
         match self.editor.as_mut() {
             Some(editor) => editor.with_buffer_mut(f),
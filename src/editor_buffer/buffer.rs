@@ -107,10 +107,19 @@ impl BufferMutExtras for BorrowedWithFontSystem<'_, cosmic_text::Editor<'_>> {
     CosmicWrap,
     CosmicTextAlign,
     crate::input::hover::HoverCursor,
-    crate::input::InputState
+    crate::input::InputState,
+    crate::input::delta::PendingTextEdits,
+    crate::input::undo::UndoHistory,
+    crate::rich_text::PendingRestyle
 )]
 pub struct CosmicEditBuffer(pub(super) Buffer);
 
+impl BufferRefExtras for CosmicEditBuffer {
+    fn get_text(&self) -> String {
+        self.0.get_text()
+    }
+}
+
 impl Default for CosmicEditBuffer {
     fn default() -> Self {
         CosmicEditBuffer(Buffer::new_empty(Metrics::new(20., 20.)))
@@ -139,7 +148,6 @@ impl<'s, 'r> CosmicEditBuffer {
         Self(buffer)
     }
 
-    #[cfg(test)]
     pub(crate) fn inner(&self) -> &Buffer {
         &self.0
     }
@@ -175,6 +183,8 @@ impl<'s, 'r> CosmicEditBuffer {
     }
 
     /// Replace buffer text
+    ///
+    /// Doesn't send change events, use [`EditorBuffer`] for that
     pub fn set_text(
         &mut self,
         font_system: &mut FontSystem,
@@ -188,7 +198,8 @@ impl<'s, 'r> CosmicEditBuffer {
 
     /// Replace buffer text with rich text
     ///
-    /// Rich text is an iterable of `(&'s str, Attrs<'r>)`
+    /// Rich text is an iterable of `(&'s str, Attrs<'r>)`.
+    /// Doesn't send change events, use [`EditorBuffer`] for that
     pub fn set_rich_text<I>(
         &mut self,
         font_system: &mut FontSystem,
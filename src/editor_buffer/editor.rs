@@ -16,6 +16,8 @@ pub struct CosmicEditor {
     pub editor: Editor<'static>,
     pub cursor_visible: bool,
     pub cursor_timer: Timer,
+    /// See [`CosmicEditor::set_typing_attrs`], with the cursor they were set at
+    pub(crate) typing_attrs: Option<(crate::rich_text::AttrsChange, cosmic_text::Cursor)>,
 }
 
 pub(super) fn blink_cursor(mut q: Query<&mut CosmicEditor, Without<ReadOnly>>, time: Res<Time>) {
@@ -51,6 +53,7 @@ impl CosmicEditor {
             editor,
             cursor_visible: false,
             cursor_timer,
+            typing_attrs: None,
         }
     }
 }
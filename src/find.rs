@@ -0,0 +1,506 @@
+//! Finding and replacing text, see [`EditorBufferItem::find`] and [`Search`]
+
+use std::ops::Range;
+
+use cosmic_text::{Buffer, Change, Cursor, Edit, Selection};
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    editor_buffer::EditorBufferItem, input::CosmicTextChanged, password::Password,
+    placeholder::Placeholder, prelude::*,
+};
+
+pub(crate) struct FindPlugin;
+
+impl Plugin for FindPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            update_search_matches
+                .after(crate::input::delta::send_programmatic_edits)
+                .before(crate::password::PasswordSet),
+        )
+        .register_type::<SearchQuery>();
+    }
+}
+
+/// What to search for
+///
+/// ```
+/// use bevy_cosmic_edit::find::SearchQuery;
+///
+/// let query = SearchQuery::new("cosmic").case_insensitive().whole_word();
+/// let regex = SearchQuery::regex(r"fn (\w+)");
+/// ```
+#[derive(Reflect, Debug, Clone, PartialEq, Eq)]
+pub struct SearchQuery {
+    pub pattern: String,
+    /// Whether `pattern` is a [regular expression](regex), rather than plain text
+    pub is_regex: bool,
+    pub case_sensitive: bool,
+    /// Only match whole words, not parts of longer words
+    pub whole_word: bool,
+}
+
+impl SearchQuery {
+    /// Searches for the plain text `pattern`
+    pub fn new(pattern: impl Into<String>) -> Self {
+        Self {
+            pattern: pattern.into(),
+            is_regex: false,
+            case_sensitive: true,
+            whole_word: false,
+        }
+    }
+
+    /// Searches for the regular expression `pattern`.
+    ///
+    /// Replacements can refer to its capture groups, e.g. `$1`
+    pub fn regex(pattern: impl Into<String>) -> Self {
+        Self {
+            is_regex: true,
+            ..Self::new(pattern)
+        }
+    }
+
+    pub fn case_insensitive(mut self) -> Self {
+        self.case_sensitive = false;
+        self
+    }
+
+    pub fn whole_word(mut self) -> Self {
+        self.whole_word = true;
+        self
+    }
+
+    /// The regular expression matching this query, or an error if `pattern` is an invalid regex
+    pub fn compile(&self) -> Result<Regex, regex::Error> {
+        let mut pattern = match self.is_regex {
+            true => self.pattern.clone(),
+            false => regex::escape(&self.pattern),
+        };
+        if self.whole_word {
+            pattern = format!(r"\b(?:{pattern})\b");
+        }
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .multi_line(true)
+            .build()
+    }
+}
+
+/// A match of a [`SearchQuery`], from `start` up to `end`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub start: Cursor,
+    pub end: Cursor,
+}
+
+fn key(cursor: Cursor) -> (usize, usize) {
+    (cursor.line, cursor.index)
+}
+
+/// Byte ranges of the non-empty matches in `text`, with the replacement for each
+fn match_ranges(
+    text: &str,
+    regex: &Regex,
+    replacement: Option<(&str, bool)>,
+) -> Vec<(Range<usize>, String)> {
+    regex
+        .captures_iter(text)
+        .filter_map(|captures| {
+            let range = captures.get(0)?.range();
+            if range.is_empty() {
+                return None;
+            }
+            let replacement = match replacement {
+                Some((replacement, true)) => {
+                    let mut expanded = String::new();
+                    captures.expand(replacement, &mut expanded);
+                    expanded
+                }
+                Some((replacement, false)) => replacement.to_owned(),
+                None => String::new(),
+            };
+            Some((range, replacement))
+        })
+        .collect()
+}
+
+/// Cursors at byte offsets into the text, with lines separated by `\n`
+struct LineStarts(Vec<usize>);
+
+impl LineStarts {
+    fn new(buffer: &Buffer) -> Self {
+        let mut start = 0;
+        Self(
+            buffer
+                .lines
+                .iter()
+                .map(|line| {
+                    let line_start = start;
+                    start += line.text().len() + 1;
+                    line_start
+                })
+                .collect(),
+        )
+    }
+
+    fn cursor(&self, byte: usize) -> Cursor {
+        let line = self
+            .0
+            .partition_point(|start| *start <= byte)
+            .saturating_sub(1);
+        Cursor::new(line, byte - self.0.get(line).copied().unwrap_or(0))
+    }
+}
+
+fn find_in(buffer: &Buffer, regex: &Regex) -> Vec<SearchMatch> {
+    let text = buffer.get_text();
+    let line_starts = LineStarts::new(buffer);
+    match_ranges(&text, regex, None)
+        .into_iter()
+        .map(|(range, _)| SearchMatch {
+            start: line_starts.cursor(range.start),
+            end: line_starts.cursor(range.end),
+        })
+        .collect()
+}
+
+/// Replaces the matches at `ranges` front to back, returning where the last replacement ends
+fn replace_ranges<'b>(
+    editor: &mut impl Edit<'b>,
+    ranges: Vec<(Range<usize>, String)>,
+) -> Option<Cursor> {
+    // bytes inserted minus bytes deleted so far
+    let mut shift = 0isize;
+    let mut end = None;
+    for (range, replacement) in ranges {
+        let (start, old_end) = editor.with_buffer(|buffer| {
+            let line_starts = LineStarts::new(buffer);
+            let at = |byte: usize| line_starts.cursor(byte.saturating_add_signed(shift));
+            (at(range.start), at(range.end))
+        });
+        editor.delete_range(start, old_end);
+        end = Some(editor.insert_at(start, &replacement, None));
+        shift += replacement.len() as isize - range.len() as isize;
+    }
+    end
+}
+
+impl EditorBufferItem<'_> {
+    /// All matches of `query` in the text, in order
+    pub fn find(&self, query: &SearchQuery) -> Result<Vec<SearchMatch>, regex::Error> {
+        let regex = query.compile()?;
+        Ok(self.with_buffer(|buffer| find_in(buffer, &regex)))
+    }
+
+    /// Selects the first match after the cursor or selection, wrapping around to the start.
+    ///
+    /// The editor scrolls to show it. Without a [`CosmicEditor`], nothing is selected
+    pub fn find_next(&mut self, query: &SearchQuery) -> Result<Option<SearchMatch>, regex::Error> {
+        let matches = self.find(query)?;
+        let from = self.editor().map_or(Cursor::new(0, 0), |editor| {
+            editor
+                .selection_bounds()
+                .map_or(editor.cursor(), |(_, end)| end)
+        });
+        let found = matches
+            .iter()
+            .find(|found| key(found.start) >= key(from))
+            .or(matches.first())
+            .copied();
+        self.select_match(found);
+        Ok(found)
+    }
+
+    /// Selects the last match before the cursor or selection, wrapping around to the end.
+    ///
+    /// The editor scrolls to show it. Without a [`CosmicEditor`], nothing is selected
+    pub fn find_previous(
+        &mut self,
+        query: &SearchQuery,
+    ) -> Result<Option<SearchMatch>, regex::Error> {
+        let matches = self.find(query)?;
+        let to = self.editor().map_or(Cursor::new(0, 0), |editor| {
+            editor
+                .selection_bounds()
+                .map_or(editor.cursor(), |(start, _)| start)
+        });
+        let found = matches
+            .iter()
+            .rev()
+            .find(|found| key(found.start) < key(to))
+            .or(matches.last())
+            .copied();
+        self.select_match(found);
+        Ok(found)
+    }
+
+    fn select_match(&mut self, found: Option<SearchMatch>) {
+        if let (Some(found), Some(editor)) = (found, self.editor()) {
+            editor.set_selection(Selection::Normal(found.start));
+            // moving the cursor scrolls it into view
+            editor.set_cursor(found.end);
+        }
+    }
+
+    /// Replaces `ranges` as a single change, leaving the cursor after the last replacement
+    fn replace_ranges(&mut self, ranges: Vec<(Range<usize>, String)>) -> Option<Change> {
+        let (_, change) = self.edit(|editor| {
+            if let Some(end) = replace_ranges(editor, ranges) {
+                editor.set_selection(Selection::None);
+                editor.set_cursor(end);
+            }
+        });
+        change
+    }
+
+    /// Replaces the selected match with `replacement` and selects the next one.
+    /// If no match is selected, only selects the next one.
+    ///
+    /// Returns the edit as a single [`Change`]. It is undone as one step of the
+    /// [`UndoHistory`](crate::input::undo::UndoHistory), together with any other
+    /// programmatic edits made in the same frame
+    pub fn replace(
+        &mut self,
+        query: &SearchQuery,
+        replacement: &str,
+    ) -> Result<Option<Change>, regex::Error> {
+        let regex = query.compile()?;
+        let selected = self
+            .editor()
+            .and_then(|editor| editor.selection_bounds())
+            .map(|(start, end)| (key(start), key(end)));
+        let ranges = self.with_buffer(|buffer| {
+            let line_starts = LineStarts::new(buffer);
+            match_ranges(
+                &buffer.get_text(),
+                &regex,
+                Some((replacement, query.is_regex)),
+            )
+            .into_iter()
+            .filter(|(range, _)| {
+                let start = line_starts.cursor(range.start);
+                let end = line_starts.cursor(range.end);
+                selected == Some((key(start), key(end)))
+            })
+            .collect::<Vec<_>>()
+        });
+
+        let change = match ranges.is_empty() {
+            true => None,
+            false => self.replace_ranges(ranges),
+        };
+        self.find_next(query)?;
+        Ok(change)
+    }
+
+    /// Replaces every match with `replacement`.
+    ///
+    /// Returns the edit as a single [`Change`]. It is undone as one step of the
+    /// [`UndoHistory`](crate::input::undo::UndoHistory), together with any other
+    /// programmatic edits made in the same frame
+    pub fn replace_all(
+        &mut self,
+        query: &SearchQuery,
+        replacement: &str,
+    ) -> Result<Option<Change>, regex::Error> {
+        let regex = query.compile()?;
+        let ranges = self.with_buffer(|buffer| {
+            match_ranges(
+                &buffer.get_text(),
+                &regex,
+                Some((replacement, query.is_regex)),
+            )
+        });
+        let change = self.replace_ranges(ranges);
+        Ok(change)
+    }
+}
+
+/// Highlights every match of a [`SearchQuery`] in the widget, updating as the text changes
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_cosmic_edit::prelude::*;
+/// use bevy_cosmic_edit::find::{Search, SearchQuery};
+///
+/// # fn setup(mut commands: Commands) {
+/// commands.spawn((TextEdit, Search::new(SearchQuery::new("TODO"))));
+/// # }
+/// ```
+#[derive(Component, Debug, Clone)]
+pub struct Search {
+    pub query: SearchQuery,
+    pub color: Color,
+    /// Color of the selected match, e.g. after [`EditorBufferItem::find_next`]
+    pub selected_color: Color,
+    matches: Vec<SearchMatch>,
+    error: Option<regex::Error>,
+}
+
+impl Search {
+    pub fn new(query: SearchQuery) -> Self {
+        Self {
+            query,
+            color: Color::srgba(1., 0.85, 0., 0.35),
+            selected_color: Color::srgba(1., 0.55, 0., 0.6),
+            matches: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// The highlighted matches
+    pub fn matches(&self) -> &[SearchMatch] {
+        &self.matches
+    }
+
+    /// Why nothing is highlighted, if the query is an invalid regex
+    pub fn error(&self) -> Option<&regex::Error> {
+        self.error.as_ref()
+    }
+}
+
+/// Finds the matches of changed searches or text, before [`Password`] hides the text
+fn update_search_matches(
+    mut searches: Query<
+        (
+            Entity,
+            &mut Search,
+            Option<&CosmicEditor>,
+            &CosmicEditBuffer,
+            Option<&Placeholder>,
+        ),
+        Without<Password>,
+    >,
+    changed_searches: Query<Entity, (Changed<Search>, Without<Password>)>,
+    mut evr_changed: EventReader<CosmicTextChanged>,
+) {
+    let changed = evr_changed
+        .read()
+        .map(|CosmicTextChanged((entity, _))| *entity)
+        .chain(changed_searches.iter())
+        .collect::<bevy::utils::HashSet<_>>();
+    for entity in changed {
+        let Ok((_, mut search, editor, buffer, placeholder)) = searches.get_mut(entity) else {
+            continue;
+        };
+        let search = search.bypass_change_detection();
+        search.matches.clear();
+        search.error = None;
+        if search.query.pattern.is_empty() || placeholder.is_some_and(Placeholder::is_active) {
+            continue;
+        }
+        match search.query.compile() {
+            Ok(regex) => {
+                search.matches = match editor {
+                    Some(editor) => editor.with_buffer(|buffer| find_in(buffer, &regex)),
+                    None => find_in(buffer.inner(), &regex),
+                }
+            }
+            Err(error) => search.error = Some(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_and_replaces() {
+        use bevy::ecs::system::RunSystemOnce;
+
+        let mut font_system = test_font_system();
+        let buffer = test_buffer(&mut font_system, "Cat cat\ncatalog cat");
+
+        let mut world = World::new();
+        world.insert_resource(CosmicFontSystem(font_system));
+        world.init_resource::<Assets<Image>>();
+        let entity = world.spawn(buffer).id();
+
+        world
+            .run_system_once(move |mut editor: Query<EditorBuffer>| {
+                let mut buffer = editor.get_mut(entity).unwrap();
+                let query = SearchQuery::new("cat");
+                let found = buffer.find(&query).unwrap();
+                assert_eq!(found.len(), 3);
+                assert_eq!(
+                    found[2],
+                    SearchMatch {
+                        start: Cursor::new(1, 8),
+                        end: Cursor::new(1, 11),
+                    }
+                );
+                assert_eq!(buffer.find(&query.clone().whole_word()).unwrap().len(), 2);
+                assert_eq!(
+                    buffer
+                        .find(&query.case_insensitive().whole_word())
+                        .unwrap()
+                        .len(),
+                    3
+                );
+
+                let query = SearchQuery::regex(r"(?i)\bcat(\w*)");
+                let change = buffer.replace_all(&query, "dog$1").unwrap().unwrap();
+                assert_eq!(buffer.get_text(), "dog dog\ndogalog dog");
+                assert_eq!(change.items.len(), 8);
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn replacing_is_undone_in_one_step() {
+        use crate::{
+            cosmic_edit::DefaultAttrs,
+            input::{
+                delta::send_programmatic_edits,
+                undo::{apply_undo_op, record_undo_steps, UndoHistory, UndoOp},
+                CosmicTextChanged,
+            },
+        };
+        use bevy::ecs::system::RunSystemOnce;
+
+        let mut font_system = test_font_system();
+        let buffer = test_buffer(&mut font_system, "cat cat");
+        let editor = CosmicEditor::clone_from_buffer(&buffer);
+
+        let mut world = World::new();
+        world.insert_resource(CosmicFontSystem(font_system));
+        world.init_resource::<Assets<Image>>();
+        world.init_resource::<Events<CosmicTextChanged>>();
+        world.add_observer(record_undo_steps);
+        world.spawn((buffer, editor));
+
+        world
+            .run_system_once(|mut editor: Query<EditorBuffer>| {
+                editor
+                    .single_mut()
+                    .replace_all(&SearchQuery::new("cat"), "dog")
+                    .unwrap();
+            })
+            .unwrap();
+        world.run_system_once(send_programmatic_edits).unwrap();
+        world.flush();
+
+        let text = world
+            .run_system_once(
+                |mut editors: Query<(&mut CosmicEditor, &mut UndoHistory, &DefaultAttrs)>,
+                 mut font_system: ResMut<CosmicFontSystem>| {
+                    let (mut editor, mut history, attrs) = editors.single_mut();
+                    apply_undo_op(
+                        UndoOp::Undo,
+                        &mut history,
+                        &mut editor,
+                        None,
+                        attrs,
+                        &mut font_system.0,
+                    );
+                    (editor.get_text(), history.can_undo())
+                },
+            )
+            .unwrap();
+        assert_eq!(text, ("cat cat".into(), false));
+    }
+}
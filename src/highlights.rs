@@ -0,0 +1,273 @@
+//! Decorating ranges of text without changing its attributes, see [`TextHighlights`]
+
+use cosmic_text::{Buffer, Cursor};
+
+use crate::{
+    input::delta::{CosmicTextEdited, DeltaKind, TextDelta},
+    prelude::*,
+};
+
+pub(crate) struct HighlightsPlugin;
+
+impl Plugin for HighlightsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(anchor_highlights)
+            .register_type::<UnderlineStyle>();
+    }
+}
+
+#[derive(Reflect, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnderlineStyle {
+    #[default]
+    Solid,
+    Dashed,
+    /// A wavy line, e.g. for spelling errors
+    Squiggly,
+}
+
+/// A range of text from `start` up to `end`, with the decorations to draw over it.
+///
+/// Backgrounds are drawn under the glyphs, everything else over them
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlight {
+    pub start: Cursor,
+    pub end: Cursor,
+    pub background: Option<Color>,
+    pub underline: Option<(UnderlineStyle, Color)>,
+    pub strikethrough: Option<Color>,
+    pub outline: Option<Color>,
+}
+
+impl Highlight {
+    /// A highlight without any decorations yet
+    pub fn new(start: Cursor, end: Cursor) -> Self {
+        Self {
+            start,
+            end,
+            background: None,
+            underline: None,
+            strikethrough: None,
+            outline: None,
+        }
+    }
+
+    pub fn background(mut self, color: Color) -> Self {
+        self.background = Some(color);
+        self
+    }
+
+    pub fn underline(mut self, style: UnderlineStyle, color: Color) -> Self {
+        self.underline = Some((style, color));
+        self
+    }
+
+    pub fn strikethrough(mut self, color: Color) -> Self {
+        self.strikethrough = Some(color);
+        self
+    }
+
+    pub fn outline(mut self, color: Color) -> Self {
+        self.outline = Some(color);
+        self
+    }
+}
+
+/// Ranges of text to decorate, e.g. errors, search hits or other users' selections.
+///
+/// The ranges move along as text is edited before them, and are removed if all of
+/// their text is deleted.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_cosmic_edit::prelude::*;
+/// use bevy_cosmic_edit::cosmic_text::Cursor;
+/// use bevy_cosmic_edit::highlights::{Highlight, TextHighlights, UnderlineStyle};
+///
+/// # fn setup(mut commands: Commands) {
+/// let error = Highlight::new(Cursor::new(0, 4), Cursor::new(0, 9))
+///     .underline(UnderlineStyle::Squiggly, Color::srgb(1., 0., 0.));
+/// commands.spawn((TextEdit, TextHighlights(vec![error])));
+/// # }
+/// ```
+#[derive(Component, Debug, Clone, Default, PartialEq)]
+pub struct TextHighlights(pub Vec<Highlight>);
+
+impl TextHighlights {
+    /// Moves the ranges along with their text after `delta`
+    pub(crate) fn anchor(&mut self, delta: &TextDelta) {
+        self.0.retain_mut(|highlight| {
+            let was_empty = highlight.start == highlight.end;
+            // text typed at either edge goes outside the range
+            highlight.start = shift_cursor(highlight.start, delta, true);
+            highlight.end = match was_empty {
+                true => highlight.start,
+                false => shift_cursor(highlight.end, delta, false),
+            };
+            was_empty || highlight.start != highlight.end
+        });
+    }
+}
+
+/// Where `cursor` ends up after `delta`.
+///
+/// `after_insert` is whether text inserted at the cursor goes before it
+fn shift_cursor(cursor: Cursor, delta: &TextDelta, after_insert: bool) -> Cursor {
+    let at = |line, index| Cursor::new(line, index);
+    let (start, end) = (delta.start, delta.end);
+    let position = (cursor.line, cursor.index);
+    match delta.kind {
+        DeltaKind::Inserted => {
+            let moved = match after_insert {
+                true => position >= (start.line, start.byte),
+                false => position > (start.line, start.byte),
+            };
+            if !moved {
+                cursor
+            } else if cursor.line == start.line {
+                at(end.line, end.byte + cursor.index - start.byte)
+            } else {
+                at(cursor.line + end.line - start.line, cursor.index)
+            }
+        }
+        DeltaKind::Deleted => {
+            if position <= (start.line, start.byte) {
+                cursor
+            } else if position <= (end.line, end.byte) {
+                at(start.line, start.byte)
+            } else if cursor.line == end.line {
+                at(start.line, start.byte + cursor.index - end.byte)
+            } else {
+                at(cursor.line - (end.line - start.line), cursor.index)
+            }
+        }
+    }
+}
+
+fn anchor_highlights(
+    trigger: Trigger<CosmicTextEdited>,
+    mut highlights: Query<&mut TextHighlights>,
+) {
+    let Ok(mut highlights) = highlights.get_mut(trigger.entity()) else {
+        return;
+    };
+    for delta in trigger.event().deltas.iter() {
+        highlights.anchor(delta);
+    }
+}
+
+/// Draws the backgrounds of `highlights`, or with `over_glyphs` their other decorations,
+/// calling `fill` with rectangles in buffer coordinates
+pub(crate) fn draw_highlights<'h>(
+    buffer: &Buffer,
+    highlights: impl IntoIterator<Item = &'h Highlight> + Clone,
+    over_glyphs: bool,
+    mut fill: impl FnMut(i32, i32, u32, u32, cosmic_text::Color),
+) {
+    for run in buffer.layout_runs() {
+        let thickness = (run.line_height / 16.).round().max(1.) as u32;
+        for highlight in highlights.clone() {
+            if run.line_i < highlight.start.line || run.line_i > highlight.end.line {
+                continue;
+            }
+            let Some((x, width)) = run.highlight(highlight.start, highlight.end) else {
+                continue;
+            };
+            let (x, width) = (x as i32, width as u32);
+            let (top, height) = (run.line_top as i32, run.line_height as u32);
+
+            if !over_glyphs {
+                if let Some(color) = highlight.background {
+                    fill(x, top, width, height, color.to_cosmic());
+                }
+                continue;
+            }
+
+            if let Some((style, color)) = highlight.underline {
+                let color = color.to_cosmic();
+                let y = run.line_y as i32 + thickness as i32;
+                match style {
+                    UnderlineStyle::Solid => fill(x, y, width, thickness, color),
+                    UnderlineStyle::Dashed => {
+                        let dash = thickness * 4;
+                        for dash_x in (0..width).step_by((dash + thickness * 2) as usize) {
+                            fill(
+                                x + dash_x as i32,
+                                y,
+                                dash.min(width - dash_x),
+                                thickness,
+                                color,
+                            );
+                        }
+                    }
+                    UnderlineStyle::Squiggly => {
+                        let amplitude = thickness as i32 + 1;
+                        for col in 0..width as i32 {
+                            let phase = col % (amplitude * 4);
+                            let offset = (phase - amplitude * 2).abs() - amplitude;
+                            fill(x + col, y + offset, 1, thickness, color);
+                        }
+                    }
+                }
+            }
+            if let Some(color) = highlight.strikethrough {
+                let y = (run.line_y - run.line_height * 0.25) as i32;
+                fill(x, y, width, thickness, color.to_cosmic());
+            }
+            if let Some(color) = highlight.outline {
+                let color = color.to_cosmic();
+                let bottom = top + height as i32 - thickness as i32;
+                let right = x + width as i32 - thickness as i32;
+                fill(x, top, width, thickness, color);
+                fill(x, bottom, width, thickness, color);
+                fill(x, top, thickness, height, color);
+                fill(right, top, thickness, height, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input::delta::TextPosition;
+
+    use super::*;
+
+    fn delta(kind: DeltaKind, start: (usize, usize), end: (usize, usize)) -> TextDelta {
+        let position = |(line, byte)| TextPosition {
+            line,
+            byte,
+            char: byte,
+        };
+        TextDelta {
+            kind,
+            start: position(start),
+            end: position(end),
+            text: String::new(),
+        }
+    }
+
+    #[test]
+    fn stays_anchored_to_text() {
+        let highlight = |start: (usize, usize), end: (usize, usize)| {
+            Highlight::new(Cursor::new(start.0, start.1), Cursor::new(end.0, end.1))
+        };
+        let mut highlights =
+            TextHighlights(vec![highlight((0, 4), (0, 9)), highlight((1, 0), (1, 3))]);
+        let edit = |highlights: &mut TextHighlights, delta: TextDelta| highlights.anchor(&delta);
+
+        // typing at the start of a highlight doesn't extend it
+        edit(&mut highlights, delta(DeltaKind::Inserted, (0, 4), (0, 6)));
+        assert_eq!(highlights.0[0], highlight((0, 6), (0, 11)));
+        // a new line before moves it down
+        edit(&mut highlights, delta(DeltaKind::Inserted, (0, 0), (1, 0)));
+        assert_eq!(highlights.0[0], highlight((1, 6), (1, 11)));
+        assert_eq!(highlights.0[1], highlight((2, 0), (2, 3)));
+        // deleting a line break joins it to the line before
+        edit(&mut highlights, delta(DeltaKind::Deleted, (1, 20), (2, 0)));
+        assert_eq!(highlights.0[1], highlight((1, 20), (1, 23)));
+        // deleting all of its text removes it
+        edit(&mut highlights, delta(DeltaKind::Deleted, (1, 5), (1, 12)));
+        assert_eq!(highlights.0.len(), 1);
+        assert_eq!(highlights.0[0], highlight((1, 13), (1, 16)));
+    }
+}
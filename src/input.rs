@@ -7,12 +7,18 @@ pub mod click;
 pub mod clipboard;
 pub mod cursor_icon;
 pub mod cursor_visibility;
+pub mod delta;
 pub mod drag;
+pub mod file_drop;
+pub mod gamepad;
 pub mod hover;
 pub mod keyboard;
+pub mod primary_selection;
 pub mod scroll;
+pub mod selection;
+pub mod undo;
 
-/// System set for mouse and keyboard input events. Runs in [`PreUpdate`] and [`Update`]
+/// System set for mouse, keyboard and gamepad input events. Runs in [`PreUpdate`] and [`Update`]
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct InputSet;
 
@@ -21,12 +27,25 @@ pub(crate) struct InputPlugin;
 impl Plugin for InputPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(PreUpdate, scroll::scroll.in_set(InputSet))
+            .add_systems(
+                PostUpdate,
+                (
+                    delta::send_programmatic_edits,
+                    selection::send_cursor_events,
+                )
+                    .before(crate::password::PasswordSet),
+            )
             .add_systems(
                 Update,
                 (
                     keyboard::kb_move_cursor,
                     keyboard::kb_input_text,
                     clipboard::kb_clipboard,
+                    undo::kb_undo,
+                    clipboard::poll_pending_paste,
+                    gamepad::gamepad_input,
+                    file_drop::handle_file_drop,
+                    primary_selection::update_primary_selection,
                     (
                         cursor_icon::update_cursor_icon,
                         cursor_visibility::update_cursor_visibility,
@@ -35,18 +54,29 @@ impl Plugin for InputPlugin {
                     .chain()
                     .in_set(InputSet),
             )
+            .add_observer(undo::record_undo_steps)
             .add_event::<hover::TextHoverIn>()
             .add_event::<hover::TextHoverOut>()
             .add_event::<CosmicTextChanged>()
+            .add_event::<gamepad::VirtualKeyboardRequested>()
+            .init_resource::<gamepad::GamepadMapping>()
+            .init_resource::<primary_selection::PrimarySelection>()
             .register_type::<hover::TextHoverIn>()
             .register_type::<hover::TextHoverOut>()
-            .register_type::<CosmicTextChanged>();
+            .register_type::<CosmicTextChanged>()
+            .register_type::<delta::CosmicTextEdited>()
+            .register_type::<selection::CosmicCursorMoved>()
+            .register_type::<selection::CosmicSelectionChanged>()
+            .register_type::<gamepad::VirtualKeyboardRequested>()
+            .register_type::<file_drop::CosmicFileDropped>()
+            .register_type::<gamepad::GamepadMapping>()
+            .register_type::<primary_selection::PrimarySelection>();
 
-        #[cfg(target_arch = "wasm32")]
+        if !app
+            .world()
+            .contains_resource::<clipboard::CosmicClipboard>()
         {
-            let (tx, rx) = crossbeam_channel::bounded::<clipboard::WasmPaste>(1);
-            app.insert_resource(clipboard::WasmPasteAsyncChannel { tx, rx })
-                .add_systems(Update, clipboard::poll_wasm_paste);
+            app.insert_resource(clipboard::CosmicClipboard::platform());
         }
     }
 }
@@ -54,13 +84,16 @@ impl Plugin for InputPlugin {
 /// Text change events
 ///
 /// Sent when text is changed in a cosmic buffer
-/// Contains the entity on which the text was changed, and the new text as a [`String`]
+/// Contains the entity on which the text was changed, and the new text as a [`String`].
+/// See [`CosmicTextEdited`](delta::CosmicTextEdited), triggered alongside, for what exactly
+/// changed and why. Check its [`EditCause::is_programmatic`](delta::EditCause::is_programmatic)
+/// to avoid feedback loops when syncing the text elsewhere
 #[derive(Event, Reflect, Debug)]
 pub struct CosmicTextChanged(pub (Entity, String));
 
 /// First variant is least important, last is most important
 #[derive(Component, Default, Debug)]
-#[require(ScrollEnabled)]
+#[require(ScrollEnabled, drag::DropCaret, hover::HoverHit, selection::LastCursor)]
 #[component(on_add = add_event_handlers)]
 pub(crate) enum InputState {
     #[default]
@@ -69,6 +102,10 @@ pub(crate) enum InputState {
     Dragging {
         initial_buffer_coord: Vec2,
     },
+    /// Dragging the existing selection to drop it somewhere else
+    MovingSelection {
+        initial_buffer_coord: Vec2,
+    },
 }
 
 fn add_event_handlers(
@@ -78,9 +115,15 @@ fn add_event_handlers(
 ) {
     let mut observers = [
         Observer::new(click::handle_focussed_click.pipe(render_implementations::debug_error)),
+        Observer::new(
+            primary_selection::handle_middle_click.pipe(render_implementations::debug_error),
+        ),
         Observer::new(drag::handle_dragstart.pipe(render_implementations::debug_error)),
         Observer::new(drag::handle_drag_continue),
         Observer::new(drag::handle_dragend),
+        Observer::new(drag::handle_drag_over.pipe(render_implementations::debug_error)),
+        Observer::new(drag::handle_drag_leave),
+        Observer::new(drag::handle_drag_drop),
         Observer::new(hover::handle_hover_start),
         Observer::new(hover::handle_hover_continue),
         Observer::new(hover::handle_hover_end),
@@ -13,7 +13,7 @@ impl InputState {
         trace!("Clicked");
         match self {
             InputState::Idle | InputState::Hovering => {}
-            InputState::Dragging { .. } => {
+            InputState::Dragging { .. } | InputState::MovingSelection { .. } => {
                 // warn!(
                 //     message = "Click event received while dragging",
                 //     state = ?self,
@@ -24,7 +24,10 @@ impl InputState {
 
     /// Should only [`Action::Click`] when not already dragging
     pub fn should_click(&self) -> bool {
-        !matches!(self, InputState::Dragging { .. })
+        !matches!(
+            self,
+            InputState::Dragging { .. } | InputState::MovingSelection { .. }
+        )
     }
 }
 
@@ -91,6 +94,11 @@ pub(super) fn handle_focussed_click(
         return Ok(());
     }
 
+    if buffer_relative.in_gutter(buffer_coord) {
+        crate::line_numbers::select_line_at(&mut *editor, buffer_coord.y);
+        return Ok(());
+    }
+
     match click_state.feed_click() {
         ClickCount::Single => {
             let shift_pressed = buttons.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
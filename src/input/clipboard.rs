@@ -1,8 +1,24 @@
-use crate::{input::CosmicTextChanged, prelude::*, MaxChars, MaxLines};
+//! Copy, cut and paste through a swappable [`ClipboardBackend`]
+//!
+//! By default the platform clipboard is used (`arboard` natively, the browser clipboard
+//! on wasm), falling back to a [`MemoryClipboard`] if it isn't available (e.g. headless CI).
+//! Insert your own [`CosmicClipboard`] resource to replace it.
+
+use std::sync::Arc;
+
+use crate::{
+    input::{
+        delta::{record_edit, CosmicTextEdited, EditCause, TextDelta},
+        CosmicTextChanged,
+    },
+    input_filter::{filtered_edit, CosmicInputRejected, InputFilter},
+    prelude::*,
+    MaxChars, MaxLines,
+};
 
 #[cfg(target_arch = "wasm32")]
 use bevy::tasks::AsyncComputeTaskPool;
-use cosmic_text::{Action, Edit};
+use cosmic_text::{Attrs, AttrsList, AttrsOwned, Edit, Family, Style};
 #[cfg(target_arch = "wasm32")]
 #[allow(unused_imports)]
 use js_sys::Promise;
@@ -11,125 +27,721 @@ use wasm_bindgen::prelude::*;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen_futures::JsFuture;
 
-// TODO: hide this behind #cfg wasm, depends on wasm having own copy/paste fn
-/// Crossbeam channel struct for Wasm clipboard data
-#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
-pub(crate) struct WasmPaste {
-    text: String,
-    entity: Entity,
+/// Errors returned by a [`ClipboardBackend`]
+#[derive(Debug)]
+pub enum ClipboardError {
+    /// The clipboard couldn't be accessed, or was empty
+    Unavailable,
+    /// The backend doesn't support this operation,
+    /// e.g. the primary selection on windows
+    Unsupported,
+    /// Any other backend specific error
+    Backend(String),
 }
 
-/// Async channel for receiving from the clipboard in Wasm
-#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
-#[derive(Resource)]
-pub(crate) struct WasmPasteAsyncChannel {
-    pub tx: crossbeam_channel::Sender<WasmPaste>,
-    pub rx: crossbeam_channel::Receiver<WasmPaste>,
-}
+/// A clipboard implementation used by [`CosmicClipboard`]
+pub trait ClipboardBackend: Send + Sync + 'static {
+    fn set_text(&mut self, text: &str) -> Result<(), ClipboardError>;
 
-pub(crate) fn kb_clipboard(
-    active_editor: Res<FocusedWidget>,
-    keys: Res<ButtonInput<KeyCode>>,
-    mut evw_changed: EventWriter<CosmicTextChanged>,
-    #[allow(unused_variables, unused_mut)] mut font_system: ResMut<CosmicFontSystem>,
-    mut cosmic_edit_query: Query<(
-        &mut CosmicEditor,
-        &MaxLines,
-        &MaxChars,
-        Entity,
-        Option<&ReadOnly>,
-    )>,
-    _channel: Option<Res<WasmPasteAsyncChannel>>,
-) {
-    let Some(active_editor_entity) = active_editor.0 else {
-        return;
-    };
+    /// Reads the clipboard text.
+    ///
+    /// Asynchronous backends should return `Ok(None)` and later
+    /// deliver the text through [`ClipboardBackend::poll_text`]
+    fn get_text(&mut self) -> Result<Option<String>, ClipboardError>;
 
-    #[allow(unused_variables)]
-    if let Ok((mut editor, max_lines, max_chars, entity, readonly_opt)) =
-        cosmic_edit_query.get_mut(active_editor_entity)
-    {
-        let command = crate::input::keyboard::keypress_command(&keys);
+    /// Polled every frame while a paste is pending
+    fn poll_text(&mut self) -> Option<String> {
+        None
+    }
+
+    /// Places HTML on the clipboard for other applications, with `alt_text` as the plain
+    /// text alternative.
+    ///
+    /// Defaults to only setting `alt_text`
+    fn set_html(&mut self, _html: &str, alt_text: &str) -> Result<(), ClipboardError> {
+        self.set_text(alt_text)
+    }
+
+    /// Used by [`PrimarySelection`](crate::input::primary_selection::PrimarySelection)
+    fn set_primary(&mut self, _text: &str) -> Result<(), ClipboardError> {
+        Err(ClipboardError::Unsupported)
+    }
+
+    /// Used by [`PrimarySelection`](crate::input::primary_selection::PrimarySelection)
+    fn get_primary(&mut self) -> Result<String, ClipboardError> {
+        Err(ClipboardError::Unsupported)
+    }
+}
+
+/// Resource holding the [`ClipboardBackend`] used for all copy and paste operations
+///
+/// ```
+/// # use bevy::prelude::*;
+/// use bevy_cosmic_edit::input::clipboard::{CosmicClipboard, MemoryClipboard};
+///
+/// # let mut app = App::new();
+/// app.insert_resource(CosmicClipboard::new(MemoryClipboard::default()));
+/// ```
+#[derive(Resource)]
+pub struct CosmicClipboard {
+    backend: Box<dyn ClipboardBackend>,
+    /// Which editor is waiting for an asynchronous paste
+    pending_paste: Option<Entity>,
+    /// Internal format of the last copy from a bevy_cosmic_edit widget
+    rich_text: Option<RichClipboardText>,
+}
 
-        let readonly = readonly_opt.is_some();
+impl CosmicClipboard {
+    pub fn new(backend: impl ClipboardBackend) -> Self {
+        Self {
+            backend: Box::new(backend),
+            pending_paste: None,
+            rich_text: None,
+        }
+    }
 
-        let mut is_clipboard = false;
+    /// The platform clipboard, or a [`MemoryClipboard`] if it can't be accessed
+    pub fn platform() -> Self {
         #[cfg(not(target_arch = "wasm32"))]
         {
-            if let Ok(mut clipboard) = arboard::Clipboard::new() {
-                if command && keys.just_pressed(KeyCode::KeyC) {
-                    if let Some(text) = editor.copy_selection() {
-                        clipboard.set_text(text).unwrap();
-                        return;
-                    }
-                }
-                if command && keys.just_pressed(KeyCode::KeyX) && !readonly {
-                    if let Some(text) = editor.copy_selection() {
-                        clipboard.set_text(text).unwrap();
-                        editor.delete_selection();
-                    }
-                    is_clipboard = true;
-                }
-                if command && keys.just_pressed(KeyCode::KeyV) && !readonly {
-                    if let Ok(text) = clipboard.get_text() {
-                        for c in text.chars() {
-                            if max_chars.0 == 0 || editor.get_text().len() < max_chars.0 {
-                                if c == 0xA as char {
-                                    if max_lines.0 == 0
-                                        || editor.with_buffer(|b| b.lines.len()) < max_lines.0
-                                    {
-                                        editor.action(&mut font_system.0, Action::Insert(c));
-                                    }
-                                } else {
-                                    editor.action(&mut font_system.0, Action::Insert(c));
-                                }
-                            }
-                        }
-                    }
-                    is_clipboard = true;
+            match ArboardClipboard::new() {
+                Ok(clipboard) => Self::new(clipboard),
+                Err(err) => {
+                    debug!(
+                        message = "Platform clipboard unavailable, falling back to an in-memory clipboard",
+                        ?err
+                    );
+                    Self::new(MemoryClipboard::default())
                 }
             }
         }
 
         #[cfg(target_arch = "wasm32")]
         {
-            if command && keys.just_pressed(KeyCode::KeyC) {
-                if let Some(text) = editor.copy_selection() {
-                    write_clipboard_wasm(text.as_str());
-                    return;
+            Self::new(WasmClipboard::default())
+        }
+    }
+
+    pub fn set_text(&mut self, text: &str) -> Result<(), ClipboardError> {
+        self.rich_text = None;
+        self.backend.set_text(text)
+    }
+
+    pub fn get_text(&mut self) -> Result<Option<String>, ClipboardError> {
+        self.backend.get_text()
+    }
+
+    /// Places both HTML and plain text on the clipboard, and remembers the spans
+    /// so they can be pasted with their formatting into another widget
+    pub fn set_rich_text(&mut self, rich_text: RichClipboardText) -> Result<(), ClipboardError> {
+        self.backend
+            .set_html(&rich_text.to_html(), &rich_text.plain_text())?;
+        self.rich_text = Some(rich_text);
+        Ok(())
+    }
+
+    /// The formatted version of `text`, if it was copied from a bevy_cosmic_edit widget
+    /// and the clipboard hasn't been overwritten since
+    pub fn rich_text_for(&self, text: &str) -> Option<&RichClipboardText> {
+        self.rich_text
+            .as_ref()
+            .filter(|rich_text| rich_text.plain_text() == text)
+    }
+
+    pub fn set_primary(&mut self, text: &str) -> Result<(), ClipboardError> {
+        self.backend.set_primary(text)
+    }
+
+    pub fn get_primary(&mut self) -> Result<String, ClipboardError> {
+        self.backend.get_primary()
+    }
+}
+
+impl Default for CosmicClipboard {
+    fn default() -> Self {
+        Self::platform()
+    }
+}
+
+/// A process local clipboard, useful for tests and platforms without a system clipboard
+#[derive(Default, Debug, Clone)]
+pub struct MemoryClipboard {
+    pub text: Option<String>,
+    pub html: Option<String>,
+    pub primary: Option<String>,
+}
+
+impl ClipboardBackend for MemoryClipboard {
+    fn set_text(&mut self, text: &str) -> Result<(), ClipboardError> {
+        self.text = Some(text.to_owned());
+        self.html = None;
+        Ok(())
+    }
+
+    fn set_html(&mut self, html: &str, alt_text: &str) -> Result<(), ClipboardError> {
+        self.text = Some(alt_text.to_owned());
+        self.html = Some(html.to_owned());
+        Ok(())
+    }
+
+    fn get_text(&mut self) -> Result<Option<String>, ClipboardError> {
+        self.text
+            .clone()
+            .map(Some)
+            .ok_or(ClipboardError::Unavailable)
+    }
+
+    fn set_primary(&mut self, text: &str) -> Result<(), ClipboardError> {
+        self.primary = Some(text.to_owned());
+        Ok(())
+    }
+
+    fn get_primary(&mut self) -> Result<String, ClipboardError> {
+        self.primary.clone().ok_or(ClipboardError::Unavailable)
+    }
+}
+
+/// The native system clipboard, using [`arboard`].
+///
+/// Keeps one handle open for the lifetime of the backend, as on X11 and Wayland
+/// copied text is only served while its handle is alive
+#[cfg(not(target_arch = "wasm32"))]
+pub struct ArboardClipboard(arboard::Clipboard);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ArboardClipboard {
+    /// Fails if the system clipboard can't be accessed
+    pub fn new() -> Result<Self, ClipboardError> {
+        Ok(Self(arboard::Clipboard::new()?))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl std::fmt::Debug for ArboardClipboard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArboardClipboard").finish_non_exhaustive()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<arboard::Error> for ClipboardError {
+    fn from(err: arboard::Error) -> Self {
+        match err {
+            arboard::Error::ContentNotAvailable => ClipboardError::Unavailable,
+            arboard::Error::ClipboardNotSupported => ClipboardError::Unsupported,
+            err => ClipboardError::Backend(err.to_string()),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ClipboardBackend for ArboardClipboard {
+    fn set_text(&mut self, text: &str) -> Result<(), ClipboardError> {
+        Ok(self.0.set_text(text)?)
+    }
+
+    fn get_text(&mut self) -> Result<Option<String>, ClipboardError> {
+        Ok(Some(self.0.get_text()?))
+    }
+
+    fn set_html(&mut self, html: &str, alt_text: &str) -> Result<(), ClipboardError> {
+        Ok(self.0.set_html(html, Some(alt_text))?)
+    }
+
+    #[cfg(all(
+        unix,
+        not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))
+    ))]
+    fn set_primary(&mut self, text: &str) -> Result<(), ClipboardError> {
+        use arboard::{LinuxClipboardKind, SetExtLinux};
+
+        Ok(self
+            .0
+            .set()
+            .clipboard(LinuxClipboardKind::Primary)
+            .text(text)?)
+    }
+
+    #[cfg(all(
+        unix,
+        not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))
+    ))]
+    fn get_primary(&mut self) -> Result<String, ClipboardError> {
+        use arboard::{GetExtLinux, LinuxClipboardKind};
+
+        Ok(self.0.get().clipboard(LinuxClipboardKind::Primary).text()?)
+    }
+}
+
+/// The browser clipboard, which can only be read asynchronously
+#[cfg(target_arch = "wasm32")]
+pub struct WasmClipboard {
+    tx: crossbeam_channel::Sender<String>,
+    rx: crossbeam_channel::Receiver<String>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Default for WasmClipboard {
+    fn default() -> Self {
+        let (tx, rx) = crossbeam_channel::bounded(1);
+        Self { tx, rx }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl ClipboardBackend for WasmClipboard {
+    fn set_text(&mut self, text: &str) -> Result<(), ClipboardError> {
+        write_clipboard_wasm(text);
+        Ok(())
+    }
+
+    fn get_text(&mut self) -> Result<Option<String>, ClipboardError> {
+        let tx = self.tx.clone();
+        let _task = AsyncComputeTaskPool::get().spawn(async move {
+            let promise = read_clipboard_wasm();
+
+            let result = JsFuture::from(promise).await;
+
+            if let Ok(js_text) = result {
+                if let Some(text) = js_text.as_string() {
+                    let _ = tx.try_send(text);
                 }
             }
+        });
 
-            if command && keys.just_pressed(KeyCode::KeyX) && !readonly {
-                if let Some(text) = editor.copy_selection() {
-                    write_clipboard_wasm(text.as_str());
-                    editor.delete_selection();
+        Ok(None)
+    }
+
+    fn poll_text(&mut self) -> Option<String> {
+        self.rx.try_recv().ok()
+    }
+}
+
+/// Transforms or rejects clipboard text before it is pasted into this entity.
+///
+/// The filter receives the target entity and the clipboard string, and returns the text to
+/// insert, or `None` to reject the paste. It runs for keyboard shortcuts, the context menu,
+/// middle-click pastes of the primary selection and asynchronous (wasm) pastes alike,
+/// as well as for [dropped files](crate::input::file_drop) and text dragged in from
+/// another editor. [`MaxLines`] and [`MaxChars`] are applied afterwards.
+///
+/// Formatted text that the filter changes is inserted without its formatting.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_cosmic_edit::prelude::*;
+/// use bevy_cosmic_edit::{input::clipboard::PasteFilter, MaxLines};
+///
+/// # fn setup(mut commands: Commands) {
+/// commands.spawn((
+///     TextEdit,
+///     MaxLines(1),
+///     PasteFilter::new(|_entity, text| {
+///         let digits: String = text.chars().filter(char::is_ascii_digit).collect();
+///         (!digits.is_empty()).then_some(digits)
+///     }),
+/// ));
+/// # }
+/// ```
+#[derive(Component, Clone)]
+pub struct PasteFilter(Arc<dyn Fn(Entity, &str) -> Option<String> + Send + Sync>);
+
+impl PasteFilter {
+    pub fn new(filter: impl Fn(Entity, &str) -> Option<String> + Send + Sync + 'static) -> Self {
+        Self(Arc::new(filter))
+    }
+
+    /// Joins lines with a space instead of dropping the newlines, for single line fields
+    pub fn single_line() -> Self {
+        Self::new(|_, text| Some(text.lines().collect::<Vec<_>>().join(" ")))
+    }
+
+    /// Removes control characters other than tabs and newlines, and trims surrounding whitespace
+    pub fn sanitize() -> Self {
+        Self::new(|_, text| {
+            Some(
+                text.trim()
+                    .chars()
+                    .filter(|c| !c.is_control() || matches!(c, '\t' | '\n'))
+                    .collect(),
+            )
+        })
+    }
+
+    /// Runs the filter, `None` means the paste was rejected
+    pub fn apply(&self, entity: Entity, text: &str) -> Option<String> {
+        (self.0)(entity, text)
+    }
+}
+
+impl std::fmt::Debug for PasteFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("PasteFilter").finish_non_exhaustive()
+    }
+}
+
+/// Text copied from a bevy_cosmic_edit widget, keeping the attributes of each span.
+///
+/// Newlines are kept inside the span texts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RichClipboardText(pub Vec<(String, AttrsOwned)>);
+
+impl RichClipboardText {
+    /// Collects the spans of the editor's current selection
+    pub fn from_selection(editor: &CosmicEditor) -> Option<Self> {
+        let (start, end) = editor.selection_bounds()?;
+        let mut spans: Vec<(String, AttrsOwned)> = Vec::new();
+        let mut push = |text: &str, attrs: AttrsOwned| match spans.last_mut() {
+            Some((last_text, last_attrs)) if *last_attrs == attrs => last_text.push_str(text),
+            _ => spans.push((text.to_owned(), attrs)),
+        };
+
+        editor.with_buffer(|buffer| {
+            for line_i in start.line..=end.line {
+                let line = &buffer.lines[line_i];
+                let text = line.text();
+                let from = if line_i == start.line { start.index } else { 0 };
+                let to = if line_i == end.line {
+                    end.index
+                } else {
+                    text.len()
+                };
+                let attrs_list = line.attrs_list();
+                for (i, c) in text[from..to].char_indices() {
+                    let index = from + i;
+                    push(
+                        &text[index..index + c.len_utf8()],
+                        AttrsOwned::new(attrs_list.get_span(index)),
+                    );
+                }
+                if line_i != end.line {
+                    push(
+                        "\n",
+                        AttrsOwned::new(attrs_list.get_span(to.saturating_sub(1))),
+                    );
                 }
-                is_clipboard = true;
             }
-            if command && keys.just_pressed(KeyCode::KeyV) && !readonly {
-                let tx = _channel.unwrap().tx.clone();
-                let _task = AsyncComputeTaskPool::get().spawn(async move {
-                    let promise = read_clipboard_wasm();
-
-                    let result = JsFuture::from(promise).await;
-
-                    if let Ok(js_text) = result {
-                        if let Some(text) = js_text.as_string() {
-                            let _ = tx.try_send(WasmPaste { text, entity });
-                        }
-                    }
+        });
+
+        Some(Self(spans))
+    }
+
+    pub fn plain_text(&self) -> String {
+        self.0.iter().map(|(text, _)| text.as_str()).collect()
+    }
+
+    /// Renders the spans as inline styled HTML
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        for (text, attrs) in &self.0 {
+            let mut style = String::new();
+            if let Some(color) = attrs.color_opt {
+                style.push_str(&format!(
+                    "color:rgba({},{},{},{:.3});",
+                    color.r(),
+                    color.g(),
+                    color.b(),
+                    color.a() as f32 / 255.
+                ));
+            }
+            let family = match attrs.family_owned.as_family() {
+                Family::Name(name) => format!("'{}'", escape_html(name)),
+                Family::Serif => "serif".to_owned(),
+                Family::SansSerif => "sans-serif".to_owned(),
+                Family::Cursive => "cursive".to_owned(),
+                Family::Fantasy => "fantasy".to_owned(),
+                Family::Monospace => "monospace".to_owned(),
+            };
+            style.push_str(&format!("font-family:{family};"));
+            style.push_str(&format!("font-weight:{};", attrs.weight.0));
+            match attrs.style {
+                Style::Normal => {}
+                Style::Italic => style.push_str("font-style:italic;"),
+                Style::Oblique => style.push_str("font-style:oblique;"),
+            }
+
+            let text = escape_html(text).replace('\n', "<br>");
+            html.push_str(&format!("<span style=\"{style}\">{text}</span>"));
+        }
+        html
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Clipboard operations shared by keyboard shortcuts and the
+/// [`ContextMenu`](crate::context_menu::ContextMenu)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ClipboardOp {
+    Copy,
+    Cut,
+    Paste,
+}
+
+/// What happened to the editor's text after a [`ClipboardOp`], or after text was dropped
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ClipboardOutcome {
+    Unchanged,
+    Changed {
+        cause: EditCause,
+        deltas: Vec<TextDelta>,
+    },
+    /// The paste was undone because the [`InputFilter`] didn't accept the resulting text
+    Rejected(String),
+}
+
+impl ClipboardOutcome {
+    /// The outcome of a [`filtered_edit`]
+    pub(crate) fn filtered(cause: EditCause, result: Result<Vec<TextDelta>, String>) -> Self {
+        match result {
+            Ok(deltas) if deltas.is_empty() => ClipboardOutcome::Unchanged,
+            Ok(deltas) => ClipboardOutcome::Changed { cause, deltas },
+            Err(rejected_text) => ClipboardOutcome::Rejected(rejected_text),
+        }
+    }
+
+    /// Sends [`CosmicTextChanged`] with the new `text` and [`CosmicTextEdited`],
+    /// or [`CosmicInputRejected`] as appropriate
+    pub(crate) fn send_events(
+        self,
+        entity: Entity,
+        text: impl FnOnce() -> String,
+        evw_changed: &mut EventWriter<CosmicTextChanged>,
+        evw_rejected: &mut EventWriter<CosmicInputRejected>,
+        commands: &mut Commands,
+    ) {
+        match self {
+            ClipboardOutcome::Unchanged => {}
+            ClipboardOutcome::Changed { cause, deltas } => {
+                evw_changed.send(CosmicTextChanged((entity, text())));
+                CosmicTextEdited::trigger(cause, deltas, entity, commands);
+            }
+            ClipboardOutcome::Rejected(rejected_text) => {
+                evw_rejected.send(CosmicInputRejected {
+                    entity,
+                    rejected_text,
                 });
+            }
+        }
+    }
+}
+
+/// Applies a [`ClipboardOp`] to an editor
+pub(crate) fn apply_clipboard_op(
+    op: ClipboardOp,
+    clipboard: &mut CosmicClipboard,
+    editor: &mut CosmicEditor,
+    entity: Entity,
+    max_lines: &MaxLines,
+    max_chars: &MaxChars,
+    readonly: bool,
+    paste_filter: Option<&PasteFilter>,
+    input_filter: Option<&InputFilter>,
+) -> ClipboardOutcome {
+    if readonly && op != ClipboardOp::Copy {
+        return ClipboardOutcome::Unchanged;
+    }
 
-                return;
+    match op {
+        ClipboardOp::Copy => {
+            if let Some(rich_text) = RichClipboardText::from_selection(editor) {
+                if let Err(err) = clipboard.set_rich_text(rich_text) {
+                    warn!(message = "Failed to copy to the clipboard", ?err);
+                }
             }
+            ClipboardOutcome::Unchanged
         }
+        ClipboardOp::Cut => {
+            if let Some(rich_text) = RichClipboardText::from_selection(editor) {
+                if let Err(err) = clipboard.set_rich_text(rich_text) {
+                    warn!(message = "Failed to cut to the clipboard", ?err);
+                    return ClipboardOutcome::Unchanged;
+                }
+            }
+            ClipboardOutcome::Changed {
+                cause: EditCause::Cut,
+                deltas: record_edit(&mut **editor, |editor| {
+                    editor.delete_selection();
+                }),
+            }
+        }
+        ClipboardOp::Paste => match clipboard.get_text() {
+            Ok(Some(text)) => paste_text(
+                clipboard,
+                editor,
+                entity,
+                &text,
+                max_lines,
+                max_chars,
+                paste_filter,
+                input_filter,
+            ),
+            Ok(None) => {
+                // text is inserted later by `poll_pending_paste`
+                clipboard.pending_paste = Some(entity);
+                ClipboardOutcome::Unchanged
+            }
+            Err(err) => {
+                debug!(message = "Failed to paste from the clipboard", ?err);
+                ClipboardOutcome::Unchanged
+            }
+        },
+    }
+}
+
+/// Inserts clipboard text, with formatting if it was copied from a bevy_cosmic_edit widget
+fn paste_text(
+    clipboard: &CosmicClipboard,
+    editor: &mut CosmicEditor,
+    entity: Entity,
+    text: &str,
+    max_lines: &MaxLines,
+    max_chars: &MaxChars,
+    paste_filter: Option<&PasteFilter>,
+    input_filter: Option<&InputFilter>,
+) -> ClipboardOutcome {
+    let filtered;
+    let text = match paste_filter {
+        Some(filter) => match filter.apply(entity, text) {
+            Some(text) => {
+                filtered = text;
+                filtered.as_str()
+            }
+            None => return ClipboardOutcome::Unchanged,
+        },
+        None => text,
+    };
+    let result = filtered_edit(&mut **editor, input_filter, |editor| {
+        match clipboard.rich_text_for(text) {
+            Some(rich_text) => insert_pasted_spans(
+                editor,
+                rich_text
+                    .0
+                    .iter()
+                    .map(|(text, attrs)| (text.as_str(), Some(attrs.as_attrs()))),
+                max_lines,
+                max_chars,
+            ),
+            None => insert_pasted_text(editor, text, max_lines, max_chars),
+        }
+    });
+    ClipboardOutcome::filtered(EditCause::Paste, result)
+}
+
+/// Inserts text at the cursor, respecting [`MaxLines`] and [`MaxChars`]
+pub(crate) fn insert_pasted_text<'b>(
+    editor: &mut impl Edit<'b>,
+    text: &str,
+    max_lines: &MaxLines,
+    max_chars: &MaxChars,
+) {
+    insert_pasted_spans(editor, [(text, None)], max_lines, max_chars);
+}
+
+/// Inserts spans of text at the cursor, respecting [`MaxLines`] and [`MaxChars`].
+///
+/// Spans without [`Attrs`] take on the attributes at the cursor
+pub(crate) fn insert_pasted_spans<'a, 'b>(
+    editor: &mut impl Edit<'b>,
+    spans: impl IntoIterator<Item = (&'a str, Option<Attrs<'a>>)>,
+    max_lines: &MaxLines,
+    max_chars: &MaxChars,
+) {
+    editor.delete_selection();
+    let mut len = editor.with_buffer(|b| b.get_text().len());
+    let mut lines = editor.with_buffer(|b| b.lines.len());
+
+    for (text, attrs) in spans {
+        let mut allowed = String::with_capacity(text.len());
+        for c in text.chars() {
+            if max_chars.0 != 0 && len >= max_chars.0 {
+                break;
+            }
+            if c == '\n' {
+                if max_lines.0 != 0 && lines >= max_lines.0 {
+                    continue;
+                }
+                lines += 1;
+            } else if c.is_control() && c != '\t' {
+                continue;
+            }
+            allowed.push(c);
+            len += c.len_utf8();
+        }
+        editor.insert_string(&allowed, attrs.map(AttrsList::new));
+    }
+    editor.set_redraw(true);
+}
+
+pub(crate) fn kb_clipboard(
+    active_editor: Res<FocusedWidget>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut evw_changed: EventWriter<CosmicTextChanged>,
+    mut evw_rejected: EventWriter<CosmicInputRejected>,
+    mut commands: Commands,
+    mut clipboard: ResMut<CosmicClipboard>,
+    mut cosmic_edit_query: Query<(
+        &mut CosmicEditor,
+        &MaxLines,
+        &MaxChars,
+        Entity,
+        Option<&ReadOnly>,
+        Option<&PasteFilter>,
+        Option<&InputFilter>,
+    )>,
+) {
+    let Some(active_editor_entity) = active_editor.0 else {
+        return;
+    };
 
-        if !is_clipboard {
+    if let Ok((
+        mut editor,
+        max_lines,
+        max_chars,
+        entity,
+        readonly_opt,
+        paste_filter,
+        input_filter,
+    )) = cosmic_edit_query.get_mut(active_editor_entity)
+    {
+        let command = crate::input::keyboard::keypress_command(&keys);
+        if !command {
             return;
         }
 
-        evw_changed.send(CosmicTextChanged((entity, editor.get_text())));
+        let op = if keys.just_pressed(KeyCode::KeyC) {
+            ClipboardOp::Copy
+        } else if keys.just_pressed(KeyCode::KeyX) {
+            ClipboardOp::Cut
+        } else if keys.just_pressed(KeyCode::KeyV) {
+            ClipboardOp::Paste
+        } else {
+            return;
+        };
+
+        apply_clipboard_op(
+            op,
+            &mut clipboard,
+            &mut editor,
+            entity,
+            max_lines,
+            max_chars,
+            readonly_opt.is_some(),
+            paste_filter,
+            input_filter,
+        )
+        .send_events(
+            entity,
+            || editor.get_text(),
+            &mut evw_changed,
+            &mut evw_rejected,
+            &mut commands,
+        );
     }
 }
 
@@ -147,36 +759,241 @@ pub fn read_clipboard_wasm() -> Promise {
     clipboard.read_text()
 }
 
-#[cfg(target_arch = "wasm32")]
-pub(crate) fn poll_wasm_paste(
-    channel: Res<WasmPasteAsyncChannel>,
-    mut editor_q: Query<(&mut CosmicEditor, &MaxChars, &MaxChars), Without<ReadOnly>>,
+/// Inserts text from asynchronous [`ClipboardBackend`]s once it arrives
+pub(crate) fn poll_pending_paste(
+    mut clipboard: ResMut<CosmicClipboard>,
+    mut editor_q: Query<
+        (
+            &mut CosmicEditor,
+            &MaxLines,
+            &MaxChars,
+            Option<&PasteFilter>,
+            Option<&InputFilter>,
+        ),
+        Without<ReadOnly>,
+    >,
     mut evw_changed: EventWriter<CosmicTextChanged>,
-    mut font_system: ResMut<CosmicFontSystem>,
+    mut evw_rejected: EventWriter<CosmicInputRejected>,
+    mut commands: Commands,
 ) {
-    let inlet = channel.rx.try_recv();
-    match inlet {
-        Ok(inlet) => {
-            let entity = inlet.entity;
-            if let Ok((mut editor, max_chars, max_lines)) = editor_q.get_mut(entity) {
-                let text = inlet.text;
-                for c in text.chars() {
-                    if max_chars.0 == 0 || editor.get_text().len() < max_chars.0 {
-                        if c == 0xA as char {
-                            if max_lines.0 == 0
-                                || editor.with_buffer(|b| b.lines.len()) < max_lines.0
-                            {
-                                editor.action(&mut font_system.0, Action::Insert(c));
-                            }
-                        } else {
-                            editor.action(&mut font_system.0, Action::Insert(c));
-                        }
-                    }
-                }
+    let Some(entity) = clipboard.pending_paste else {
+        return;
+    };
+    let Some(text) = clipboard.backend.poll_text() else {
+        return;
+    };
+    clipboard.pending_paste = None;
 
-                evw_changed.send(CosmicTextChanged((entity, editor.get_text())));
-            }
+    if let Ok((mut editor, max_lines, max_chars, paste_filter, input_filter)) =
+        editor_q.get_mut(entity)
+    {
+        paste_text(
+            &clipboard,
+            &mut editor,
+            entity,
+            &text,
+            max_lines,
+            max_chars,
+            paste_filter,
+            input_filter,
+        )
+        .send_events(
+            entity,
+            || editor.get_text(),
+            &mut evw_changed,
+            &mut evw_rejected,
+            &mut commands,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command_key() -> KeyCode {
+        if cfg!(target_os = "macos") {
+            KeyCode::SuperLeft
+        } else {
+            KeyCode::ControlLeft
         }
-        Err(_) => {}
+    }
+
+    fn test_app(text: &str) -> (App, Entity) {
+        let mut font_system = test_font_system();
+
+        let buffer = test_buffer(&mut font_system, text);
+        let editor = CosmicEditor::clone_from_buffer(&buffer);
+
+        let mut app = App::new();
+        app.insert_resource(CosmicFontSystem(font_system))
+            .insert_resource(CosmicClipboard::new(MemoryClipboard::default()))
+            .init_resource::<ButtonInput<KeyCode>>()
+            .add_event::<CosmicTextChanged>()
+            .add_event::<CosmicInputRejected>()
+            .add_systems(Update, (kb_clipboard, poll_pending_paste).chain());
+        let entity = app
+            .world_mut()
+            .spawn((editor, MaxLines(0), MaxChars(0)))
+            .id();
+        app.insert_resource(FocusedWidget(Some(entity)));
+
+        (app, entity)
+    }
+
+    fn press_shortcut(app: &mut App, key: KeyCode) {
+        let mut keys = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+        keys.reset_all();
+        keys.press(command_key());
+        keys.press(key);
+        app.update();
+    }
+
+    fn select_all(app: &mut App, entity: Entity) {
+        let mut editor = app.world_mut().get_mut::<CosmicEditor>(entity).unwrap();
+        editor.set_selection(cosmic_text::Selection::Normal(cosmic_text::Cursor::new(
+            0, 0,
+        )));
+        let end = editor.with_buffer(|b| b.lines[0].text().len());
+        editor.set_cursor(cosmic_text::Cursor::new(0, end));
+    }
+
+    fn editor_text(app: &App, entity: Entity) -> String {
+        app.world().get::<CosmicEditor>(entity).unwrap().get_text()
+    }
+
+    fn clipboard_text(app: &mut App) -> Option<String> {
+        app.world_mut()
+            .resource_mut::<CosmicClipboard>()
+            .get_text()
+            .ok()
+            .flatten()
+    }
+
+    #[test]
+    fn copy_leaves_text() {
+        let (mut app, entity) = test_app("Hello");
+        select_all(&mut app, entity);
+        press_shortcut(&mut app, KeyCode::KeyC);
+
+        assert_eq!(clipboard_text(&mut app).as_deref(), Some("Hello"));
+        assert_eq!(editor_text(&app, entity), "Hello");
+    }
+
+    #[test]
+    fn cut_then_paste() {
+        let (mut app, entity) = test_app("Hello");
+        select_all(&mut app, entity);
+        press_shortcut(&mut app, KeyCode::KeyX);
+
+        assert_eq!(clipboard_text(&mut app).as_deref(), Some("Hello"));
+        assert_eq!(editor_text(&app, entity), "");
+
+        press_shortcut(&mut app, KeyCode::KeyV);
+        press_shortcut(&mut app, KeyCode::KeyV);
+        assert_eq!(editor_text(&app, entity), "HelloHello");
+    }
+
+    #[test]
+    fn paste_respects_max_chars() {
+        let (mut app, entity) = test_app("");
+        app.world_mut().entity_mut(entity).insert(MaxChars(3));
+        app.world_mut()
+            .resource_mut::<CosmicClipboard>()
+            .set_text("Hello")
+            .unwrap();
+        press_shortcut(&mut app, KeyCode::KeyV);
+
+        assert_eq!(editor_text(&app, entity), "Hel");
+    }
+
+    #[test]
+    fn paste_keeps_formatting() {
+        let red = cosmic_text::Color::rgb(255, 0, 0);
+        let (mut app, entity) = test_app("");
+        {
+            let world = app.world_mut();
+            let mut font_system = world.remove_resource::<CosmicFontSystem>().unwrap();
+            let mut editor = world.get_mut::<CosmicEditor>(entity).unwrap();
+            editor.with_buffer_mut(|b| {
+                b.set_rich_text(
+                    &mut font_system.0,
+                    [("Hi", Attrs::new()), ("Red", Attrs::new().color(red))],
+                    Attrs::new(),
+                    cosmic_text::Shaping::Advanced,
+                )
+            });
+            world.insert_resource(font_system);
+        }
+        select_all(&mut app, entity);
+        press_shortcut(&mut app, KeyCode::KeyC);
+
+        let rich_text = app.world().resource::<CosmicClipboard>().rich_text.clone();
+        assert_eq!(
+            rich_text
+                .unwrap()
+                .0
+                .iter()
+                .map(|(t, _)| t.as_str())
+                .collect::<Vec<_>>(),
+            ["Hi", "Red"]
+        );
+
+        press_shortcut(&mut app, KeyCode::KeyV);
+        press_shortcut(&mut app, KeyCode::KeyV);
+        assert_eq!(editor_text(&app, entity), "HiRedHiRed");
+        let editor = app.world().get::<CosmicEditor>(entity).unwrap();
+        editor.with_buffer(|b| {
+            let attrs = b.lines[0].attrs_list();
+            assert_eq!(attrs.get_span(6).color_opt, None);
+            assert_eq!(attrs.get_span(8).color_opt, Some(red));
+        });
+    }
+
+    #[test]
+    fn html_escapes_text() {
+        let rich_text = RichClipboardText(vec![(
+            "a<b\nc".into(),
+            AttrsOwned::new(Attrs::new().family(Family::Monospace)),
+        )]);
+        assert_eq!(
+            rich_text.to_html(),
+            "<span style=\"font-family:monospace;font-weight:400;\">a&lt;b<br>c</span>"
+        );
+    }
+
+    #[test]
+    fn paste_filter_transforms_and_rejects() {
+        let (mut app, entity) = test_app("");
+        app.world_mut()
+            .entity_mut(entity)
+            .insert(PasteFilter::new(|_, text| {
+                (!text.contains('!')).then(|| text.replace('\n', " "))
+            }));
+        let mut clipboard = app.world_mut().resource_mut::<CosmicClipboard>();
+        clipboard.set_text("one\ntwo").unwrap();
+        press_shortcut(&mut app, KeyCode::KeyV);
+        assert_eq!(editor_text(&app, entity), "one two");
+
+        let mut clipboard = app.world_mut().resource_mut::<CosmicClipboard>();
+        clipboard.set_text("no!").unwrap();
+        press_shortcut(&mut app, KeyCode::KeyV);
+        assert_eq!(editor_text(&app, entity), "one two");
+    }
+
+    #[test]
+    fn input_filter_rejects_paste() {
+        let (mut app, entity) = test_app("1");
+        app.world_mut()
+            .entity_mut(entity)
+            .insert(InputFilter::Digits);
+        let mut clipboard = app.world_mut().resource_mut::<CosmicClipboard>();
+        clipboard.set_text("2a").unwrap();
+        press_shortcut(&mut app, KeyCode::KeyV);
+        assert_eq!(editor_text(&app, entity), "1");
+
+        let rejected = app.world().resource::<Events<CosmicInputRejected>>();
+        let rejected = rejected.iter_current_update_events().next().unwrap();
+        assert_eq!(rejected.rejected_text, "2a1");
     }
 }
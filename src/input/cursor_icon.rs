@@ -79,7 +79,7 @@ pub(super) fn update_cursor_icon(
                 cursor_state.account_for_hovered_buffer(hover_cursor.0.clone());
             }
             InputState::Idle => {}
-            InputState::Dragging { .. } => {
+            InputState::Dragging { .. } | InputState::MovingSelection { .. } => {
                 if is_editor && focused_widget.0 == Some(buffer_entity) {
                     cursor_state.account_for_dragging_focussed_editor();
                 }
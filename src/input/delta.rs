@@ -0,0 +1,338 @@
+//! Change events describing the actual edit, see [`CosmicTextEdited`]
+
+use cosmic_text::{Buffer, Change, ChangeItem, Cursor, Edit};
+
+use crate::{input::CosmicTextChanged, placeholder::Placeholder, prelude::*};
+
+/// Why the text changed
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditCause {
+    /// Typed on the keyboard or entered with a gamepad
+    Typed,
+    Paste,
+    Cut,
+    /// A selection or file dropped onto the editor, or a selection dragged away from it
+    Drop,
+    /// Changed through [`EditorBuffer`] rather than by the user, including by this crate,
+    /// e.g. an [`InputFilter`](crate::input_filter::InputFilter) reverting text
+    Programmatic,
+    /// Undone or redone with the [`UndoHistory`](crate::input::undo::UndoHistory)
+    Undo,
+}
+
+impl EditCause {
+    pub fn is_programmatic(self) -> bool {
+        self == EditCause::Programmatic
+    }
+}
+
+/// A position in a buffer
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextPosition {
+    pub line: usize,
+    /// Byte offset within the line
+    pub byte: usize,
+    /// Char offset within the line
+    pub char: usize,
+}
+
+impl TextPosition {
+    pub const START: Self = Self {
+        line: 0,
+        byte: 0,
+        char: 0,
+    };
+
+    pub(crate) fn from_cursor(buffer: &Buffer, cursor: Cursor) -> Self {
+        let char = buffer.lines.get(cursor.line).map_or(0, |line| {
+            let text = line.text();
+            text.get(..cursor.index.min(text.len()))
+                .map_or(cursor.index, |prefix| prefix.chars().count())
+        });
+        Self {
+            line: cursor.line,
+            byte: cursor.index,
+            char,
+        }
+    }
+
+    /// The position after `text` if it starts here
+    fn after(self, text: &str) -> Self {
+        match text.rsplit_once('\n') {
+            Some((before, last_line)) => Self {
+                line: self.line + before.matches('\n').count() + 1,
+                byte: last_line.len(),
+                char: last_line.chars().count(),
+            },
+            None => Self {
+                line: self.line,
+                byte: self.byte + text.len(),
+                char: self.char + text.chars().count(),
+            },
+        }
+    }
+}
+
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeltaKind {
+    Inserted,
+    Deleted,
+}
+
+/// A single insertion or deletion.
+///
+/// For insertions, `start..end` is the range of the new text. For deletions, it is the range
+/// the text occupied before it was removed.
+#[derive(Reflect, Debug, Clone, PartialEq, Eq)]
+pub struct TextDelta {
+    pub kind: DeltaKind,
+    pub start: TextPosition,
+    pub end: TextPosition,
+    pub text: String,
+}
+
+impl TextDelta {
+    fn from_change_item(buffer: &Buffer, item: &ChangeItem) -> Self {
+        let start = TextPosition::from_cursor(buffer, item.start);
+        Self {
+            kind: if item.insert {
+                DeltaKind::Inserted
+            } else {
+                DeltaKind::Deleted
+            },
+            start,
+            end: start.after(&item.text),
+            text: item.text.clone(),
+        }
+    }
+
+    /// Deltas for the items of `change`, resolved against `buffer` after it was made
+    pub(crate) fn from_change(buffer: &Buffer, change: &Change) -> Vec<Self> {
+        change
+            .items
+            .iter()
+            .filter(|item| !item.text.is_empty())
+            .map(|item| Self::from_change_item(buffer, item))
+            .collect()
+    }
+
+    /// Deltas replacing all of `old` with `new`
+    pub fn replace_all(old: &str, new: &str) -> Vec<Self> {
+        [(DeltaKind::Deleted, old), (DeltaKind::Inserted, new)]
+            .into_iter()
+            .filter(|(_, text)| !text.is_empty())
+            .map(|(kind, text)| Self {
+                kind,
+                start: TextPosition::START,
+                end: TextPosition::START.after(text),
+                text: text.to_owned(),
+            })
+            .collect()
+    }
+}
+
+/// Triggered on the edited entity whenever its text changes, describing the edit.
+///
+/// Unlike [`CosmicTextChanged`](crate::input::CosmicTextChanged) this doesn't contain the
+/// whole text, so it is cheap for large buffers and can be used to keep other copies in sync.
+/// Deltas are in order, each relative to the text after the previous one.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_cosmic_edit::prelude::*;
+/// use bevy_cosmic_edit::input::delta::{CosmicTextEdited, DeltaKind};
+///
+/// # fn setup(mut commands: Commands) {
+/// commands.spawn(TextEdit).observe(|trigger: Trigger<CosmicTextEdited>| {
+///     for delta in &trigger.event().deltas {
+///         if delta.kind == DeltaKind::Inserted {
+///             info!("{:?} inserted {:?} at line {}", trigger.event().cause, delta.text, delta.start.line);
+///         }
+///     }
+/// });
+/// # }
+/// ```
+#[derive(Event, Reflect, Debug, Clone)]
+pub struct CosmicTextEdited {
+    pub cause: EditCause,
+    pub deltas: Vec<TextDelta>,
+}
+
+impl CosmicTextEdited {
+    /// Triggers the event on `entity`, unless nothing changed
+    pub(crate) fn trigger(
+        cause: EditCause,
+        deltas: Vec<TextDelta>,
+        entity: Entity,
+        commands: &mut Commands,
+    ) {
+        if !deltas.is_empty() {
+            commands.trigger_targets(Self { cause, deltas }, entity);
+        }
+    }
+}
+
+/// Edits made through [`EditorBuffer`] that haven't been sent as events yet
+#[derive(Component, Default, Debug)]
+pub(crate) struct PendingTextEdits(Vec<TextDelta>);
+
+impl PendingTextEdits {
+    /// Records replacing `old` with `new`, if they differ
+    pub(crate) fn record_replace(&mut self, old: &str, new: &str) {
+        if old != new {
+            self.0.extend(TextDelta::replace_all(old, new));
+        }
+    }
+
+    pub(crate) fn record(&mut self, deltas: Vec<TextDelta>) {
+        self.0.extend(deltas);
+    }
+}
+
+/// Sends [`CosmicTextChanged`] and [`CosmicTextEdited`] for programmatic edits,
+/// before [`Password`](crate::password::Password) hides the text
+pub(crate) fn send_programmatic_edits(
+    mut editors: Query<
+        (
+            Entity,
+            &mut PendingTextEdits,
+            Option<&CosmicEditor>,
+            &CosmicEditBuffer,
+            Option<&Placeholder>,
+        ),
+        Changed<PendingTextEdits>,
+    >,
+    mut evw_changed: EventWriter<CosmicTextChanged>,
+    mut commands: Commands,
+) {
+    for (entity, mut pending, editor, buffer, placeholder) in editors.iter_mut() {
+        if pending.0.is_empty() {
+            continue;
+        }
+        let deltas = std::mem::take(&mut pending.bypass_change_detection().0);
+
+        let mut text = match editor {
+            Some(editor) => editor.get_text(),
+            None => buffer.get_text(),
+        };
+        if placeholder
+            .is_some_and(|placeholder| placeholder.is_active() && text == placeholder.text)
+        {
+            text.clear();
+        }
+        evw_changed.send(CosmicTextChanged((entity, text)));
+        CosmicTextEdited::trigger(EditCause::Programmatic, deltas, entity, &mut commands);
+    }
+}
+
+/// Runs `edit`, returning the changes it made to the editor.
+///
+/// Positions are resolved against the text after `edit`, so keep each call to a single
+/// action or a sequence of edits moving forwards.
+pub(crate) fn record_edit<'b, E: Edit<'b>>(
+    editor: &mut E,
+    edit: impl FnOnce(&mut E),
+) -> Vec<TextDelta> {
+    editor.start_change();
+    edit(editor);
+    let Some(change) = editor.finish_change() else {
+        return Vec::new();
+    };
+    editor.with_buffer(|buffer| TextDelta::from_change(buffer, &change))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_typing_over_selection() {
+        let mut font_system = test_font_system();
+        let mut buffer = Buffer::new(&mut font_system, cosmic_text::Metrics::new(20., 20.));
+        buffer.set_text(
+            &mut font_system,
+            "héllo\nworld",
+            cosmic_text::Attrs::new(),
+            cosmic_text::Shaping::Advanced,
+        );
+        let mut editor = cosmic_text::Editor::new(&mut buffer);
+        editor.set_selection(cosmic_text::Selection::Normal(Cursor::new(0, 3)));
+        editor.set_cursor(Cursor::new(1, 1));
+
+        let deltas = record_edit(&mut editor, |editor| {
+            editor.action(&mut font_system, cosmic_text::Action::Insert('ü'))
+        });
+        assert_eq!(
+            deltas,
+            [
+                TextDelta {
+                    kind: DeltaKind::Deleted,
+                    start: TextPosition {
+                        line: 0,
+                        byte: 3,
+                        char: 2
+                    },
+                    end: TextPosition {
+                        line: 1,
+                        byte: 1,
+                        char: 1
+                    },
+                    text: "llo\nw".into(),
+                },
+                TextDelta {
+                    kind: DeltaKind::Inserted,
+                    start: TextPosition {
+                        line: 0,
+                        byte: 3,
+                        char: 2
+                    },
+                    end: TextPosition {
+                        line: 0,
+                        byte: 5,
+                        char: 3
+                    },
+                    text: "ü".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn set_text_is_reported_as_programmatic() {
+        use bevy::ecs::system::RunSystemOnce;
+
+        let mut font_system = test_font_system();
+        let buffer = test_buffer(&mut font_system, "old");
+
+        let mut world = World::new();
+        world.insert_resource(CosmicFontSystem(font_system));
+        world.init_resource::<Events<CosmicTextChanged>>();
+        world.init_resource::<Assets<Image>>();
+        let entity = world.spawn(buffer).id();
+        #[derive(Resource, Default)]
+        struct Causes(Vec<EditCause>);
+        world.init_resource::<Causes>();
+        world.add_observer(
+            |trigger: Trigger<CosmicTextEdited>, mut causes: ResMut<Causes>| {
+                causes.0.push(trigger.event().cause);
+            },
+        );
+
+        let set_text = |text: &'static str| {
+            move |mut buffers: Query<EditorBuffer>, mut font_system: ResMut<CosmicFontSystem>| {
+                for mut buffer in buffers.iter_mut() {
+                    buffer.set_text(&mut font_system, text, cosmic_text::Attrs::new());
+                }
+            }
+        };
+        world.run_system_once(set_text("new")).unwrap();
+        world.run_system_once(set_text("new")).unwrap();
+        world.run_system_once(send_programmatic_edits).unwrap();
+
+        let events = world.resource::<Events<CosmicTextChanged>>();
+        let events = events.iter_current_update_events().collect::<Vec<_>>();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].0, (entity, "new".to_owned()));
+        assert_eq!(world.resource::<Causes>().0, [EditCause::Programmatic]);
+    }
+}
@@ -1,14 +1,47 @@
-use crate::prelude::*;
+use crate::{
+    input::{
+        clipboard::{insert_pasted_spans, ClipboardOutcome, PasteFilter, RichClipboardText},
+        delta::{record_edit, EditCause},
+        CosmicTextChanged,
+    },
+    input_filter::{filtered_edit, CosmicInputRejected, EditSnapshot, InputFilter},
+    numeric_input::NumericField,
+    prelude::*,
+    MaxChars, MaxLines,
+};
 
 use super::{warn_no_editor_on_picking_event, InputState};
-use cosmic_text::Action;
+use cosmic_text::{Action, Cursor, Edit, Selection};
 use render_implementations::RelativeQuery;
 
+/// Where the selection being dragged would be dropped in this buffer, if anywhere.
+///
+/// Drawn as a caret by the renderer
+#[derive(Component, Default, Debug)]
+pub(crate) struct DropCaret(pub Option<Cursor>);
+
 impl InputState {
     pub fn is_dragging(&self) -> bool {
         matches!(self, InputState::Dragging { .. })
     }
 
+    pub fn is_moving_selection(&self) -> bool {
+        matches!(self, InputState::MovingSelection { .. })
+    }
+
+    /// Handler for [`DragStart`] event pressed inside the current selection
+    pub fn start_moving_selection(&mut self, initial_buffer_coord: Vec2) {
+        trace!("Starting to move the selection");
+        match self {
+            InputState::Idle | InputState::Hovering => {
+                *self = InputState::MovingSelection {
+                    initial_buffer_coord,
+                };
+            }
+            InputState::Dragging { .. } | InputState::MovingSelection { .. } => {}
+        }
+    }
+
     /// Handler for [`DragStart`] event
     pub fn start_dragging(&mut self, initial_buffer_coord: Vec2) {
         trace!("Starting a drag");
@@ -18,7 +51,7 @@ impl InputState {
                     initial_buffer_coord,
                 };
             }
-            InputState::Dragging { .. } => {
+            InputState::Dragging { .. } | InputState::MovingSelection { .. } => {
                 // warn!(
                 //     message = "Somehow, a `DragStart` event was received before a previous `DragStart` event was ended with a `DragEnd`",
                 //     note = "Ignoring",
@@ -30,7 +63,7 @@ impl InputState {
     /// Handler for [`Move`]
     pub fn continue_dragging(&self) {
         match self {
-            InputState::Dragging { .. } => {}
+            InputState::Dragging { .. } | InputState::MovingSelection { .. } => {}
             InputState::Idle | InputState::Hovering => {
                 // warn!(
                 //     message = "Somehow, a `Move` event was received before a previous `DragStart` event was received",
@@ -44,7 +77,7 @@ impl InputState {
     pub fn end_dragging(&mut self) {
         trace!("Ending drag");
         match self {
-            InputState::Dragging { .. } => {
+            InputState::Dragging { .. } | InputState::MovingSelection { .. } => {
                 *self = InputState::Idle;
             }
             InputState::Idle | InputState::Hovering => {
@@ -59,12 +92,22 @@ impl InputState {
 
 pub(super) fn handle_dragstart(
     trigger: Trigger<Pointer<DragStart>>,
-    mut editor: Query<(&mut InputState, &mut CosmicEditor, RelativeQuery), With<CosmicEditBuffer>>,
+    mut editor: Query<
+        (
+            &mut InputState,
+            &mut CosmicEditor,
+            RelativeQuery,
+            Has<NumericField>,
+        ),
+        With<CosmicEditBuffer>,
+    >,
     mut font_system: ResMut<CosmicFontSystem>,
 ) -> render_implementations::Result<()> {
     let font_system = &mut font_system.0;
     let event = trigger.event();
-    let Ok((mut input_state, mut editor, sprite_relative)) = editor.get_mut(trigger.target) else {
+    let Ok((mut input_state, mut editor, sprite_relative, numeric)) =
+        editor.get_mut(trigger.target)
+    else {
         warn_no_editor_on_picking_event("handling cursor `DragStart` event");
         return Ok(());
     };
@@ -76,6 +119,17 @@ pub(super) fn handle_dragstart(
         return Ok(());
     }
 
+    let pressed = editor.with_buffer(|b| b.hit(buffer_coord.x, buffer_coord.y));
+    // dragging a number field's selection would clash with scrubbing it
+    if let (Some(pressed), Some((start, end)), false) =
+        (pressed, editor.selection_bounds(), numeric)
+    {
+        if is_within(pressed, start, end) {
+            input_state.start_moving_selection(buffer_coord);
+            return Ok(());
+        }
+    }
+
     input_state.start_dragging(buffer_coord);
 
     if input_state.is_dragging() {
@@ -94,7 +148,7 @@ pub(super) fn handle_dragstart(
 
 pub(super) fn handle_drag_continue(
     trigger: Trigger<Pointer<Drag>>,
-    mut editor: Query<(&InputState, &mut CosmicEditor)>,
+    mut editor: Query<(&InputState, &mut CosmicEditor, &mut DropCaret)>,
     mut font_system: ResMut<CosmicFontSystem>,
 ) {
     let font_system = &mut font_system.0;
@@ -105,31 +159,64 @@ pub(super) fn handle_drag_continue(
         return;
     }
 
-    let Ok((input_state, mut editor)) = editor.get_mut(entity) else {
+    let Ok((input_state, mut editor, mut drop_caret)) = editor.get_mut(entity) else {
         warn_no_editor_on_picking_event("handling cursor `Drag` event");
         return;
     };
 
     input_state.continue_dragging();
 
-    if let InputState::Dragging {
-        initial_buffer_coord,
-    } = *input_state
-    {
-        let new_buffer_coord = initial_buffer_coord + event.distance;
-        editor.action(
-            font_system,
-            Action::Drag {
-                x: new_buffer_coord.x as i32,
-                y: new_buffer_coord.y as i32,
-            },
-        );
+    match *input_state {
+        InputState::Dragging {
+            initial_buffer_coord,
+        } => {
+            let new_buffer_coord = initial_buffer_coord + event.distance;
+            editor.action(
+                font_system,
+                Action::Drag {
+                    x: new_buffer_coord.x as i32,
+                    y: new_buffer_coord.y as i32,
+                },
+            );
+        }
+        InputState::MovingSelection {
+            initial_buffer_coord,
+        } => {
+            let new_buffer_coord = initial_buffer_coord + event.distance;
+            drop_caret.0 = editor.with_buffer(|b| {
+                let (width, height) = b.size();
+                let inside = new_buffer_coord.x >= 0.
+                    && new_buffer_coord.y >= 0.
+                    && new_buffer_coord.x <= width.unwrap_or(f32::MAX)
+                    && new_buffer_coord.y <= height.unwrap_or(f32::MAX);
+                inside
+                    .then(|| b.hit(new_buffer_coord.x, new_buffer_coord.y))
+                    .flatten()
+            });
+            editor.set_redraw(true);
+        }
+        InputState::Idle | InputState::Hovering => {}
     }
 }
 
 pub(super) fn handle_dragend(
     trigger: Trigger<Pointer<DragEnd>>,
-    mut editor: Query<&mut InputState, With<CosmicEditBuffer>>,
+    mut editor: Query<
+        (
+            &mut InputState,
+            &mut DropCaret,
+            Option<&mut CosmicEditor>,
+            &MaxLines,
+            &MaxChars,
+            Has<ReadOnly>,
+            Option<&InputFilter>,
+        ),
+        With<CosmicEditBuffer>,
+    >,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut evw_changed: EventWriter<CosmicTextChanged>,
+    mut evw_rejected: EventWriter<CosmicInputRejected>,
+    mut commands: Commands,
 ) {
     let event = &trigger.event;
     let entity = trigger.target;
@@ -138,11 +225,352 @@ pub(super) fn handle_dragend(
         return;
     }
 
-    let Ok(entity_mut) = editor.get_mut(entity) else {
+    let Ok((mut input_state, mut drop_caret, editor, max_lines, max_chars, readonly, filter)) =
+        editor.get_mut(entity)
+    else {
         warn_no_editor_on_picking_event("handling cursor `DragEnd` event");
         return;
     };
-    let input_state = entity_mut.into_inner();
+
+    // dropped inside the same buffer
+    if let (true, Some(drop), Some(mut editor)) = (
+        input_state.is_moving_selection(),
+        drop_caret.0.take(),
+        editor,
+    ) {
+        let copy = readonly || copy_modifier_pressed(&keys);
+        move_selection_within(&mut editor, drop, copy, max_lines, max_chars, filter).send_events(
+            entity,
+            || editor.get_text(),
+            &mut evw_changed,
+            &mut evw_rejected,
+            &mut commands,
+        );
+    }
 
     input_state.end_dragging();
 }
+
+/// Shows where the selection dragged from another editor would be dropped
+pub(super) fn handle_drag_over(
+    trigger: Trigger<Pointer<DragOver>>,
+    sources: Query<&InputState>,
+    mut targets: Query<(EditorBuffer, &mut DropCaret, RelativeQuery), Without<ReadOnly>>,
+    mut font_system: ResMut<CosmicFontSystem>,
+) -> render_implementations::Result<()> {
+    let event = trigger.event();
+    if event.button != PointerButton::Primary
+        || !sources
+            .get(event.dragged)
+            .is_ok_and(InputState::is_moving_selection)
+    {
+        return Ok(());
+    }
+    let Ok((mut buffer, mut drop_caret, buffer_relative)) = targets.get_mut(trigger.target) else {
+        return Ok(());
+    };
+
+    let buffer_size = buffer.borrow_with(&mut font_system.0).expected_size();
+    let buffer_coord = buffer_relative.compute_buffer_coord(&event.hit, buffer_size)?;
+    drop_caret.0 = buffer.hit(buffer_coord.x, buffer_coord.y);
+    buffer.set_redraw(true);
+
+    Ok(())
+}
+
+pub(super) fn handle_drag_leave(
+    trigger: Trigger<Pointer<DragLeave>>,
+    mut targets: Query<&mut DropCaret>,
+) {
+    if let Ok(mut drop_caret) = targets.get_mut(trigger.target) {
+        drop_caret.0 = None;
+    }
+}
+
+/// Drops a selection dragged from another editor, moving it unless the copy modifier is held,
+/// see [`copy_modifier_pressed`]
+pub(super) fn handle_drag_drop(
+    trigger: Trigger<Pointer<DragDrop>>,
+    mut editors: ParamSet<(
+        Query<(
+            &InputState,
+            &mut CosmicEditor,
+            Has<ReadOnly>,
+            Option<&InputFilter>,
+        )>,
+        Query<
+            (
+                EditorBuffer,
+                &mut DropCaret,
+                &MaxLines,
+                &MaxChars,
+                Option<&PasteFilter>,
+                Option<&InputFilter>,
+            ),
+            Without<ReadOnly>,
+        >,
+    )>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut focused: ResMut<FocusedWidget>,
+    mut evw_changed: EventWriter<CosmicTextChanged>,
+    mut evw_rejected: EventWriter<CosmicInputRejected>,
+    mut commands: Commands,
+) {
+    let event = trigger.event();
+    let target = trigger.target;
+    if event.button != PointerButton::Primary {
+        return;
+    }
+    let Some(drop) = editors
+        .p1()
+        .get_mut(target)
+        .ok()
+        .and_then(|(_, mut drop_caret, ..)| drop_caret.0.take())
+    else {
+        return;
+    };
+    let (rich_text, source_readonly) = {
+        let sources = editors.p0();
+        let Ok((input_state, source, source_readonly, _)) = sources.get(event.dropped) else {
+            return;
+        };
+        if !input_state.is_moving_selection() {
+            return;
+        }
+        let Some(rich_text) = RichClipboardText::from_selection(source) else {
+            return;
+        };
+        (rich_text, source_readonly)
+    };
+
+    let inserted = {
+        let mut targets = editors.p1();
+        let Ok((mut buffer, _, max_lines, max_chars, paste_filter, filter)) =
+            targets.get_mut(target)
+        else {
+            return;
+        };
+        let plain_text = rich_text.plain_text();
+        let filtered = match paste_filter {
+            Some(paste_filter) => match paste_filter.apply(target, &plain_text) {
+                Some(text) => Some(text).filter(|text| *text != plain_text),
+                None => return,
+            },
+            None => None,
+        };
+        // the formatting only fits the text if the filter left it alone
+        let spans = match &filtered {
+            Some(text) => vec![(text.as_str(), None)],
+            None => rich_text
+                .0
+                .iter()
+                .map(|(text, attrs)| (text.as_str(), Some(attrs.as_attrs())))
+                .collect(),
+        };
+        let result = buffer.with_buffer_mut(|b| {
+            let mut editor = cosmic_text::Editor::new(b);
+            editor.set_cursor(drop);
+            filtered_edit(&mut editor, filter, |editor| {
+                insert_pasted_spans(editor, spans, max_lines, max_chars)
+            })
+        });
+        buffer.set_redraw(true);
+        let inserted = result.is_ok();
+        ClipboardOutcome::filtered(EditCause::Drop, result).send_events(
+            target,
+            || buffer.get_text(),
+            &mut evw_changed,
+            &mut evw_rejected,
+            &mut commands,
+        );
+        inserted
+    };
+
+    // the text is only moved if the target took it
+    let copy = !inserted || source_readonly || copy_modifier_pressed(&keys);
+    if !copy {
+        let mut sources = editors.p0();
+        if let Ok((_, mut source, _, filter)) = sources.get_mut(event.dropped) {
+            let result = filtered_edit(&mut **source, filter, |source| {
+                source.delete_selection();
+            });
+            source.set_redraw(true);
+            ClipboardOutcome::filtered(EditCause::Drop, result).send_events(
+                event.dropped,
+                || source.get_text(),
+                &mut evw_changed,
+                &mut evw_rejected,
+                &mut commands,
+            );
+        }
+    }
+
+    if inserted {
+        focused.0 = Some(target);
+    }
+}
+
+/// Should a dropped selection be copied rather than moved?
+///
+/// \[Option\] on macOS, \[Ctrl\] elsewhere
+fn copy_modifier_pressed(keys: &ButtonInput<KeyCode>) -> bool {
+    #[cfg(target_os = "macos")]
+    let copy = keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]);
+
+    #[cfg(not(target_os = "macos"))]
+    let copy = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+
+    #[cfg(target_arch = "wasm32")]
+    let copy = if web_sys::window()
+        .unwrap()
+        .navigator()
+        .user_agent()
+        .unwrap_or("NoUA".into())
+        .contains("Macintosh")
+    {
+        keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight])
+    } else {
+        copy
+    };
+
+    copy
+}
+
+/// Is `cursor` inside the selection from `start` to `end`?
+fn is_within(cursor: Cursor, start: Cursor, end: Cursor) -> bool {
+    let position = (cursor.line, cursor.index);
+    (start.line, start.index) <= position && position < (end.line, end.index)
+}
+
+/// Moves (or copies) the selection to `drop`, selecting the dropped text,
+/// unless the `filter` rejects the result
+fn move_selection_within(
+    editor: &mut CosmicEditor,
+    mut drop: Cursor,
+    copy: bool,
+    max_lines: &MaxLines,
+    max_chars: &MaxChars,
+    filter: Option<&InputFilter>,
+) -> ClipboardOutcome {
+    let Some((start, end)) = editor.selection_bounds() else {
+        return ClipboardOutcome::Unchanged;
+    };
+    if is_within(drop, start, end) || (!copy && drop == end) {
+        // dropped onto itself, behave like a click
+        editor.set_selection(Selection::None);
+        editor.set_cursor(drop);
+        return ClipboardOutcome::Unchanged;
+    }
+    let Some(rich_text) = RichClipboardText::from_selection(editor) else {
+        return ClipboardOutcome::Unchanged;
+    };
+    let snapshot = filter.map(|_| EditSnapshot::take(&**editor));
+
+    editor.set_selection(Selection::None);
+    let mut deltas = Vec::new();
+    if !copy {
+        deltas = record_edit(&mut **editor, |editor| editor.delete_range(start, end));
+        // shift the drop position to account for the removed text
+        if (drop.line, drop.index) >= (end.line, end.index) {
+            if drop.line == end.line {
+                drop.index = start.index + (drop.index - end.index);
+            }
+            drop.line -= end.line - start.line;
+        }
+    }
+
+    editor.set_cursor(drop);
+    deltas.extend(record_edit(&mut **editor, |editor| {
+        insert_pasted_spans(
+            editor,
+            rich_text
+                .0
+                .iter()
+                .map(|(text, attrs)| (text.as_str(), Some(attrs.as_attrs()))),
+            max_lines,
+            max_chars,
+        )
+    }));
+    editor.set_selection(Selection::Normal(drop));
+    editor.set_redraw(true);
+
+    if let (Some(snapshot), Some(filter)) = (snapshot, filter) {
+        if let Some(rejected_text) = snapshot.reject_unless_accepted(&mut **editor, filter) {
+            return ClipboardOutcome::Rejected(rejected_text);
+        }
+    }
+    ClipboardOutcome::filtered(EditCause::Drop, Ok(deltas))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(text: &str) -> CosmicEditor {
+        let mut font_system = test_font_system();
+        let buffer = test_buffer(&mut font_system, text);
+        CosmicEditor::clone_from_buffer(&buffer)
+    }
+
+    fn select(editor: &mut CosmicEditor, start: usize, end: usize) {
+        editor.set_selection(Selection::Normal(Cursor::new(0, start)));
+        editor.set_cursor(Cursor::new(0, end));
+    }
+
+    #[test]
+    fn move_selection_forwards_and_copy_backwards() {
+        let (max_lines, max_chars) = (MaxLines(0), MaxChars(0));
+
+        let mut editor = editor("one two three");
+        select(&mut editor, 0, 4);
+        assert!(matches!(
+            move_selection_within(
+                &mut editor,
+                Cursor::new(0, 8),
+                false,
+                &max_lines,
+                &max_chars,
+                None,
+            ),
+            ClipboardOutcome::Changed { .. }
+        ));
+        assert_eq!(editor.get_text(), "two one three");
+        assert_eq!(editor.copy_selection().as_deref(), Some("one "));
+
+        select(&mut editor, 8, 13);
+        let ClipboardOutcome::Changed { deltas, .. } = move_selection_within(
+            &mut editor,
+            Cursor::new(0, 0),
+            true,
+            &max_lines,
+            &max_chars,
+            None,
+        ) else {
+            panic!("the selection wasn't copied");
+        };
+        assert_eq!(editor.get_text(), "threetwo one three");
+        assert_eq!(deltas.len(), 1);
+        assert_eq!((deltas[0].start.char, deltas[0].end.char), (0, 5));
+    }
+
+    #[test]
+    fn rejected_drop_is_undone() {
+        let filter = InputFilter::custom(|text| text.len() <= 8);
+
+        let mut editor = editor("one two");
+        select(&mut editor, 0, 3);
+        assert_eq!(
+            move_selection_within(
+                &mut editor,
+                Cursor::new(0, 7),
+                true,
+                &MaxLines(0),
+                &MaxChars(0),
+                Some(&filter),
+            ),
+            ClipboardOutcome::Rejected("one twoone".into())
+        );
+        assert_eq!(editor.get_text(), "one two");
+        assert_eq!(editor.copy_selection().as_deref(), Some("one"));
+    }
+}
@@ -0,0 +1,328 @@
+//! Dropping files from the OS onto a hovered [`CosmicEditBuffer`]
+//!
+//! Every dropped file triggers a [`CosmicFileDropped`] event on the hovered buffer.
+//! Files are rejected unless an observer chooses a [`FileDropAction`], and accepted files
+//! are read in the background on the [`IoTaskPool`].
+
+use std::path::PathBuf;
+
+use bevy::{
+    ecs::{event::EventCursor, system::RunSystemOnce},
+    tasks::{block_on, futures_lite::future, IoTaskPool, Task},
+};
+use cosmic_text::{Cursor, Edit, Selection};
+use render_implementations::RelativeQuery;
+
+use crate::{
+    input::{
+        clipboard::{insert_pasted_text, ClipboardOutcome, PasteFilter},
+        delta::{EditCause, TextDelta},
+        hover::HoverHit,
+        CosmicTextChanged, InputState,
+    },
+    input_filter::{filtered_edit, CosmicInputRejected, InputFilter},
+    prelude::*,
+    MaxChars, MaxLines,
+};
+
+/// Triggered on the hovered [`CosmicEditBuffer`] when a file is dropped onto the window.
+///
+/// Set [`CosmicFileDropped::action`] in an observer to accept the file
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_cosmic_edit::prelude::*;
+/// use bevy_cosmic_edit::input::file_drop::{CosmicFileDropped, FileDropAction};
+///
+/// # fn setup(mut commands: Commands) {
+/// commands
+///     .spawn(TextEdit)
+///     .observe(|mut trigger: Trigger<CosmicFileDropped>| {
+///         let event = trigger.event_mut();
+///         let extension = event.path.extension().and_then(|ext| ext.to_str());
+///         if matches!(extension, Some("txt" | "md")) {
+///             event.action = FileDropAction::Insert;
+///         }
+///     });
+/// # }
+/// ```
+#[derive(Event, Reflect, Debug)]
+pub struct CosmicFileDropped {
+    pub path: PathBuf,
+    /// What to do with the file, [`FileDropAction::Reject`] by default
+    pub action: FileDropAction,
+    /// Text to use instead of reading the file, e.g. after converting it
+    pub text: Option<String>,
+}
+
+/// What to do with a dropped file, see [`CosmicFileDropped`]
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileDropAction {
+    /// Insert the contents where the file was dropped
+    Insert,
+    /// Replace all the text with the contents
+    Replace,
+    #[default]
+    Reject,
+}
+
+/// A dropped file being read on the [`IoTaskPool`]
+pub(crate) struct PendingFileDrop {
+    target: Entity,
+    action: FileDropAction,
+    /// Where the file was dropped, for [`FileDropAction::Insert`]
+    drop: Option<Cursor>,
+    path: PathBuf,
+    read: Task<std::io::Result<String>>,
+}
+
+pub(crate) fn handle_file_drop(
+    world: &mut World,
+    mut cursor: Local<EventCursor<FileDragAndDrop>>,
+    mut pending: Local<Vec<PendingFileDrop>>,
+) {
+    // inserts files that have finished reading
+    let mut read = Vec::new();
+    pending.retain_mut(|file| match block_on(future::poll_once(&mut file.read)) {
+        Some(result) => {
+            match result {
+                Ok(text) => read.push((file.target, file.action, file.drop, text)),
+                Err(err) => {
+                    warn!(message = "Failed to read dropped file", path = ?file.path, ?err)
+                }
+            }
+            false
+        }
+        None => true,
+    });
+    for dropped in read {
+        insert_dropped(world, dropped);
+    }
+
+    // not added without a `WindowPlugin`
+    let Some(events) = world.get_resource::<Events<FileDragAndDrop>>() else {
+        return;
+    };
+    let dropped = cursor
+        .read(events)
+        .filter_map(|event| match event {
+            FileDragAndDrop::DroppedFile { path_buf, .. } => Some(path_buf.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if dropped.is_empty() {
+        return;
+    }
+
+    let Some(target) = world
+        .query_filtered::<(Entity, &InputState), Without<ReadOnly>>()
+        .iter(world)
+        .find(|(_, input_state)| input_state.is_hovering())
+        .map(|(entity, _)| entity)
+    else {
+        return;
+    };
+
+    for path in dropped {
+        let mut event = CosmicFileDropped {
+            path,
+            action: FileDropAction::Reject,
+            text: None,
+        };
+        world.trigger_targets_ref(&mut event, target);
+
+        if event.action == FileDropAction::Reject {
+            continue;
+        }
+        let drop = match event.action {
+            FileDropAction::Insert => match world.run_system_once_with(target, drop_position) {
+                Ok(Ok(drop)) => drop,
+                Ok(Err(err)) => {
+                    debug!(message = "Failed to find where a file was dropped", ?err);
+                    None
+                }
+                Err(_) => None,
+            },
+            _ => None,
+        };
+        match event.text {
+            Some(text) => insert_dropped(world, (target, event.action, drop, text)),
+            None => {
+                let path = event.path.clone();
+                pending.push(PendingFileDrop {
+                    target,
+                    action: event.action,
+                    drop,
+                    path: event.path,
+                    read: IoTaskPool::get().spawn(async move { std::fs::read_to_string(path) }),
+                });
+            }
+        }
+    }
+}
+
+fn insert_dropped(world: &mut World, dropped: (Entity, FileDropAction, Option<Cursor>, String)) {
+    if let Err(err) = world.run_system_once_with(dropped, insert_dropped_text) {
+        warn!(message = "Failed to insert dropped file", ?err);
+    }
+}
+
+/// Where the hovered buffer was hit
+fn drop_position(
+    In(target): In<Entity>,
+    mut editors: Query<(EditorBuffer, &HoverHit, RelativeQuery)>,
+    mut font_system: ResMut<CosmicFontSystem>,
+) -> render_implementations::Result<Option<Cursor>> {
+    let Ok((mut buffer, hover_hit, buffer_relative)) = editors.get_mut(target) else {
+        return Ok(None);
+    };
+    let Some(hit) = &hover_hit.0 else {
+        return Ok(None);
+    };
+    let buffer_size = buffer.borrow_with(&mut font_system.0).expected_size();
+    let buffer_coord = buffer_relative.compute_buffer_coord(hit, buffer_size)?;
+    Ok(buffer.hit(buffer_coord.x, buffer_coord.y))
+}
+
+/// Places the cursor for `action` and inserts `text`, unless the `filter` rejects the result
+fn insert_with<'b>(
+    editor: &mut impl Edit<'b>,
+    action: FileDropAction,
+    drop: Option<Cursor>,
+    text: &str,
+    max_lines: &MaxLines,
+    max_chars: &MaxChars,
+    filter: Option<&InputFilter>,
+) -> Result<Vec<TextDelta>, String> {
+    filtered_edit(editor, filter, |editor| {
+        let end = editor.with_buffer(|b| {
+            b.lines
+                .last()
+                .map(|line| Cursor::new(b.lines.len() - 1, line.text().len()))
+                .unwrap_or_default()
+        });
+        editor.set_selection(Selection::None);
+        match action {
+            FileDropAction::Replace => {
+                editor.set_selection(Selection::Normal(Cursor::new(0, 0)));
+                editor.set_cursor(end);
+            }
+            _ => {
+                // the text may have changed while the file was read
+                let drop = drop
+                    .filter(|drop| {
+                        editor.with_buffer(|b| {
+                            b.lines
+                                .get(drop.line)
+                                .is_some_and(|line| line.text().is_char_boundary(drop.index))
+                        })
+                    })
+                    .unwrap_or(end);
+                editor.set_cursor(drop);
+            }
+        }
+        insert_pasted_text(editor, text, max_lines, max_chars)
+    })
+}
+
+fn insert_dropped_text(
+    In((target, action, drop, text)): In<(Entity, FileDropAction, Option<Cursor>, String)>,
+    mut editors: Query<
+        (
+            EditorBuffer,
+            &MaxLines,
+            &MaxChars,
+            Option<&PasteFilter>,
+            Option<&InputFilter>,
+        ),
+        Without<ReadOnly>,
+    >,
+    mut focused: ResMut<FocusedWidget>,
+    mut evw_changed: EventWriter<CosmicTextChanged>,
+    mut evw_rejected: EventWriter<CosmicInputRejected>,
+    mut commands: Commands,
+) {
+    let Ok((mut buffer, max_lines, max_chars, paste_filter, filter)) = editors.get_mut(target)
+    else {
+        return;
+    };
+    let text = match paste_filter {
+        Some(paste_filter) => match paste_filter.apply(target, &text) {
+            Some(text) => text,
+            None => return,
+        },
+        None => text,
+    };
+
+    // edit through the focussed editor, so its cursor stays within the new text
+    let result = match buffer.editor() {
+        Some(editor) => insert_with(
+            &mut **editor,
+            action,
+            drop,
+            &text,
+            max_lines,
+            max_chars,
+            filter,
+        ),
+        None => buffer.with_buffer_mut(|b| {
+            let mut editor = cosmic_text::Editor::new(b);
+            insert_with(
+                &mut editor,
+                action,
+                drop,
+                &text,
+                max_lines,
+                max_chars,
+                filter,
+            )
+        }),
+    };
+    buffer.set_redraw(true);
+
+    focused.0 = Some(target);
+    ClipboardOutcome::filtered(EditCause::Drop, result).send_events(
+        target,
+        || buffer.get_text(),
+        &mut evw_changed,
+        &mut evw_rejected,
+        &mut commands,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropped_text_goes_through_paste_filter() {
+        let mut font_system = test_font_system();
+        let buffer = test_buffer(&mut font_system, "a");
+
+        let mut world = World::new();
+        world.insert_resource(CosmicFontSystem(font_system));
+        world.init_resource::<FocusedWidget>();
+        world.init_resource::<Events<CosmicTextChanged>>();
+        world.init_resource::<Events<CosmicInputRejected>>();
+        world.init_resource::<Assets<Image>>();
+        let entity = world
+            .spawn((
+                buffer,
+                PasteFilter::new(|_, text| (!text.contains('!')).then(|| text.to_uppercase())),
+            ))
+            .id();
+        let drop = |world: &mut World, text: &str| {
+            world
+                .run_system_once_with(
+                    (entity, FileDropAction::Insert, None, text.to_owned()),
+                    insert_dropped_text,
+                )
+                .unwrap();
+            world.get::<CosmicEditBuffer>(entity).unwrap().get_text()
+        };
+
+        assert_eq!(drop(&mut world, "bc"), "aBC");
+        assert_eq!(drop(&mut world, "d!"), "aBC");
+        assert_eq!(world.resource::<FocusedWidget>().0, Some(entity));
+    }
+}
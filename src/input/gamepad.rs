@@ -0,0 +1,369 @@
+//! Controller support for focussed editors
+//!
+//! Reads [`GamepadButtonStateChangedEvent`]s and translates them into editor commands
+//! through the [`GamepadMapping`] resource.
+
+use bevy::input::{gamepad::GamepadButtonStateChangedEvent, ButtonState};
+use bevy::utils::HashMap;
+use cosmic_text::{Action, Motion, Selection};
+
+use crate::{
+    input::{
+        delta::{record_edit, CosmicTextEdited, EditCause},
+        CosmicTextChanged,
+    },
+    prelude::*,
+    render_implementations::RelativeQuery,
+    MaxChars, MaxLines,
+};
+
+/// Editor commands that can be bound to a [`GamepadButton`]
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadCommand {
+    CursorLeft,
+    CursorRight,
+    CursorUp,
+    CursorDown,
+    PreviousWord,
+    NextWord,
+    Backspace,
+    Delete,
+    NewLine,
+    /// Sends a [`VirtualKeyboardRequested`] event for the focussed editor
+    OpenVirtualKeyboard,
+    /// Focus the next [`CosmicEditBuffer`] in reading order on screen, by the top left
+    /// corner of each widget. Sprites outside every camera are skipped, see [`SkipGamepadNavigation`]
+    FocusNext,
+    /// Focus the previous [`CosmicEditBuffer`] in reading order on screen, like [`GamepadCommand::FocusNext`]
+    FocusPrevious,
+    /// Sets [`FocusedWidget`] to `None`
+    Unfocus,
+}
+
+/// Maps [`GamepadButton`]s to [`GamepadCommand`]s.
+///
+/// Defaults to the D-pad moving the cursor, the shoulder buttons jumping words,
+/// the lower triggers moving between editors, [`GamepadButton::North`] opening the
+/// virtual keyboard, [`GamepadButton::West`] deleting and [`GamepadButton::East`] unfocussing.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// use bevy_cosmic_edit::input::gamepad::{GamepadCommand, GamepadMapping};
+///
+/// fn remap(mut mapping: ResMut<GamepadMapping>) {
+///     mapping.bind(GamepadButton::South, GamepadCommand::NewLine);
+///     mapping.unbind(GamepadButton::East);
+/// }
+/// ```
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct GamepadMapping(pub HashMap<GamepadButton, GamepadCommand>);
+
+impl Default for GamepadMapping {
+    fn default() -> Self {
+        Self(HashMap::from_iter([
+            (GamepadButton::DPadLeft, GamepadCommand::CursorLeft),
+            (GamepadButton::DPadRight, GamepadCommand::CursorRight),
+            (GamepadButton::DPadUp, GamepadCommand::CursorUp),
+            (GamepadButton::DPadDown, GamepadCommand::CursorDown),
+            (GamepadButton::LeftTrigger, GamepadCommand::PreviousWord),
+            (GamepadButton::RightTrigger, GamepadCommand::NextWord),
+            (GamepadButton::LeftTrigger2, GamepadCommand::FocusPrevious),
+            (GamepadButton::RightTrigger2, GamepadCommand::FocusNext),
+            (GamepadButton::North, GamepadCommand::OpenVirtualKeyboard),
+            (GamepadButton::West, GamepadCommand::Backspace),
+            (GamepadButton::East, GamepadCommand::Unfocus),
+        ]))
+    }
+}
+
+impl GamepadMapping {
+    /// A mapping with no bindings at all
+    pub fn empty() -> Self {
+        Self(HashMap::default())
+    }
+
+    pub fn bind(&mut self, button: GamepadButton, command: GamepadCommand) -> &mut Self {
+        self.0.insert(button, command);
+        self
+    }
+
+    pub fn unbind(&mut self, button: GamepadButton) -> &mut Self {
+        self.0.remove(&button);
+        self
+    }
+
+    pub fn get(&self, button: GamepadButton) -> Option<GamepadCommand> {
+        self.0.get(&button).copied()
+    }
+}
+
+/// Tag component to opt a [`CosmicEditBuffer`] out of
+/// [`GamepadCommand::FocusNext`] and [`GamepadCommand::FocusPrevious`] navigation
+#[derive(Component, Default)]
+pub struct SkipGamepadNavigation;
+
+/// Sent when [`GamepadCommand::OpenVirtualKeyboard`] is pressed while an editor is focussed.
+///
+/// bevy_cosmic_edit doesn't ship an on-screen keyboard, so apps should respond to this
+/// by showing their own, or the platform's (e.g. the Steam overlay keyboard).
+#[derive(Event, Reflect, Debug)]
+pub struct VirtualKeyboardRequested(pub Entity);
+
+pub(crate) fn gamepad_input(
+    mut evr: EventReader<GamepadButtonStateChangedEvent>,
+    mapping: Res<GamepadMapping>,
+    mut focus: ResMut<FocusedWidget>,
+    mut cosmic_edit_query: Query<(
+        &mut CosmicEditor,
+        &MaxLines,
+        &MaxChars,
+        Entity,
+        Has<ReadOnly>,
+    )>,
+    navigable: Query<
+        (Entity, RelativeQuery),
+        (With<CosmicEditBuffer>, Without<SkipGamepadNavigation>),
+    >,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut evw_changed: EventWriter<CosmicTextChanged>,
+    mut evw_keyboard: EventWriter<VirtualKeyboardRequested>,
+    mut font_system: ResMut<CosmicFontSystem>,
+    mut commands: Commands,
+) {
+    for ev in evr.read() {
+        if ev.state != ButtonState::Pressed {
+            continue;
+        }
+        let Some(command) = mapping.get(ev.button) else {
+            continue;
+        };
+
+        match command {
+            GamepadCommand::FocusNext | GamepadCommand::FocusPrevious => {
+                let entities =
+                    reading_order(navigable.iter().filter_map(|(entity, buffer_relative)| {
+                        let position = buffer_relative
+                            .widget_topleft_to_viewport(Vec2::ZERO, &cameras)
+                            .ok()?;
+                        Some((entity, position))
+                    }));
+                if entities.is_empty() {
+                    continue;
+                }
+                let current = focus
+                    .0
+                    .and_then(|focused| entities.iter().position(|e| *e == focused));
+                let next = match (command, current) {
+                    (GamepadCommand::FocusNext, Some(i)) => (i + 1) % entities.len(),
+                    (GamepadCommand::FocusNext, None) => 0,
+                    (_, Some(i)) => (i + entities.len() - 1) % entities.len(),
+                    (_, None) => entities.len() - 1,
+                };
+                focus.0 = Some(entities[next]);
+                continue;
+            }
+            GamepadCommand::Unfocus => {
+                focus.0 = None;
+                continue;
+            }
+            _ => {}
+        }
+
+        let Some(focused) = focus.0 else {
+            continue;
+        };
+        let Ok((mut editor, max_lines, max_chars, entity, readonly)) =
+            cosmic_edit_query.get_mut(focused)
+        else {
+            continue;
+        };
+        editor.cursor_visible = true;
+        editor.cursor_timer.reset();
+
+        let motion = match command {
+            GamepadCommand::CursorLeft => Some(Motion::Left),
+            GamepadCommand::CursorRight => Some(Motion::Right),
+            GamepadCommand::CursorUp => Some(Motion::Up),
+            GamepadCommand::CursorDown => Some(Motion::Down),
+            GamepadCommand::PreviousWord => Some(Motion::PreviousWord),
+            GamepadCommand::NextWord => Some(Motion::NextWord),
+            _ => None,
+        };
+        if let Some(motion) = motion {
+            editor.set_selection(Selection::None);
+            editor.action(&mut font_system.0, Action::Motion(motion));
+            continue;
+        }
+
+        if command == GamepadCommand::OpenVirtualKeyboard {
+            evw_keyboard.send(VirtualKeyboardRequested(entity));
+            continue;
+        }
+
+        if readonly {
+            continue;
+        }
+
+        let action = match command {
+            GamepadCommand::Backspace => Action::Backspace,
+            GamepadCommand::Delete => Action::Delete,
+            GamepadCommand::NewLine => {
+                if (max_lines.0 != 0 && editor.with_buffer(|b| b.lines.len()) >= max_lines.0)
+                    || (max_chars.0 != 0 && editor.get_text().len() >= max_chars.0)
+                {
+                    continue;
+                }
+                Action::Insert('\n')
+            }
+            _ => continue,
+        };
+        let deltas = record_edit(&mut **editor, |editor| {
+            editor.action(&mut font_system.0, action)
+        });
+        evw_changed.send(CosmicTextChanged((entity, editor.get_text())));
+        CosmicTextEdited::trigger(EditCause::Typed, deltas, entity, &mut commands);
+    }
+}
+
+/// Sorts `positions` in the viewport top to bottom, then left to right
+fn reading_order(positions: impl IntoIterator<Item = (Entity, Vec2)>) -> Vec<Entity> {
+    let mut positions = positions.into_iter().collect::<Vec<_>>();
+    positions.sort_by(|(a, a_pos), (b, b_pos)| {
+        a_pos
+            .y
+            .total_cmp(&b_pos.y)
+            .then(a_pos.x.total_cmp(&b_pos.x))
+            .then(a.cmp(b))
+    });
+    positions.into_iter().map(|(entity, _)| entity).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        ecs::system::RunSystemOnce,
+        input::gamepad::GamepadButtonStateChangedEvent,
+        render::camera::{camera_system, ManualTextureViews},
+        window::{PrimaryWindow, WindowCreated, WindowResized, WindowScaleFactorChanged},
+    };
+    use cosmic_text::Cursor;
+
+    use super::*;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.insert_resource(CosmicFontSystem(test_font_system()));
+        world.init_resource::<Assets<Image>>();
+        world.init_resource::<FocusedWidget>();
+        world.init_resource::<GamepadMapping>();
+        world.init_resource::<Events<GamepadButtonStateChangedEvent>>();
+        world.init_resource::<Events<CosmicTextChanged>>();
+        world.init_resource::<Events<VirtualKeyboardRequested>>();
+        world
+    }
+
+    fn press(world: &mut World, button: GamepadButton) {
+        let gamepad = Entity::PLACEHOLDER;
+        world.send_event(GamepadButtonStateChangedEvent::new(
+            gamepad,
+            button,
+            ButtonState::Pressed,
+        ));
+        world.send_event(GamepadButtonStateChangedEvent::new(
+            gamepad,
+            button,
+            ButtonState::Released,
+        ));
+        world.run_system_once(gamepad_input).unwrap();
+        world
+            .resource_mut::<Events<GamepadButtonStateChangedEvent>>()
+            .clear();
+    }
+
+    #[test]
+    fn focuses_in_reading_order_on_screen() {
+        let mut world = world();
+        world.init_resource::<ManualTextureViews>();
+        world.init_resource::<Events<WindowCreated>>();
+        world.init_resource::<Events<WindowResized>>();
+        world.init_resource::<Events<WindowScaleFactorChanged>>();
+        world.init_resource::<Events<AssetEvent<Image>>>();
+        // a 1280x720 window, with the 2d camera centered on the world origin
+        world.spawn((Window::default(), PrimaryWindow));
+        world.spawn((Camera2d, GlobalTransform::default()));
+        world
+            .run_system_once(camera_system::<OrthographicProjection>)
+            .unwrap();
+
+        let sprite = |world: &mut World, y: f32| {
+            let sprite = Sprite {
+                custom_size: Some(Vec2::new(100., 20.)),
+                ..default()
+            };
+            world
+                .spawn((TextEdit2d, sprite, GlobalTransform::from_xyz(0., y, 0.)))
+                .id()
+        };
+        // UI nodes are placed in pixels from the top of the window
+        let ui = |world: &mut World, y: f32| {
+            world
+                .spawn((TextEdit, GlobalTransform::from_xyz(640., y, 0.)))
+                .id()
+        };
+        let below_center = ui(&mut world, 600.);
+        let center = sprite(&mut world, 0.);
+        let top = sprite(&mut world, 300.);
+        let near_top = ui(&mut world, 100.);
+        world.spawn((TextEdit, SkipGamepadNavigation));
+
+        let mut order = Vec::new();
+        for _ in 0..5 {
+            press(&mut world, GamepadButton::RightTrigger2);
+            order.push(world.resource::<FocusedWidget>().0.unwrap());
+        }
+        assert_eq!(order, [top, near_top, center, below_center, top]);
+
+        press(&mut world, GamepadButton::LeftTrigger2);
+        assert_eq!(world.resource::<FocusedWidget>().0, Some(below_center));
+    }
+
+    #[test]
+    fn dispatches_mapped_commands() {
+        let mut world = world();
+        let buffer = test_buffer(&mut world.resource_mut::<CosmicFontSystem>().0, "hello");
+        let mut editor = CosmicEditor::clone_from_buffer(&buffer);
+        editor.set_cursor(Cursor::new(0, 5));
+        let entity = world.spawn((buffer, editor)).id();
+        world.resource_mut::<FocusedWidget>().0 = Some(entity);
+        let text = |world: &World| world.get::<CosmicEditor>(entity).unwrap().get_text();
+
+        press(&mut world, GamepadButton::West);
+        assert_eq!(text(&world), "hell");
+        press(&mut world, GamepadButton::DPadLeft);
+        press(&mut world, GamepadButton::West);
+        assert_eq!(text(&world), "hel");
+
+        press(&mut world, GamepadButton::North);
+        let requested = world.resource::<Events<VirtualKeyboardRequested>>();
+        assert_eq!(requested.len(), 1);
+
+        // rebinding replaces the default command, unbound buttons do nothing
+        world
+            .resource_mut::<GamepadMapping>()
+            .bind(GamepadButton::West, GamepadCommand::Delete)
+            .unbind(GamepadButton::North);
+        press(&mut world, GamepadButton::West);
+        assert_eq!(text(&world), "he");
+        press(&mut world, GamepadButton::North);
+        let requested = world.resource::<Events<VirtualKeyboardRequested>>();
+        assert_eq!(requested.len(), 1);
+
+        world.entity_mut(entity).insert(ReadOnly);
+        press(&mut world, GamepadButton::West);
+        assert_eq!(text(&world), "he");
+
+        press(&mut world, GamepadButton::East);
+        assert_eq!(world.resource::<FocusedWidget>().0, None);
+    }
+}
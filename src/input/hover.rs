@@ -1,4 +1,4 @@
-use bevy::{window::SystemCursorIcon, winit::cursor::CursorIcon};
+use bevy::{picking::backend::HitData, window::SystemCursorIcon, winit::cursor::CursorIcon};
 
 use crate::prelude::*;
 
@@ -24,13 +24,19 @@ impl Default for HoverCursor {
     }
 }
 
+/// Where the pointer last hovered over this widget
+#[derive(Component, Default, Debug)]
+pub(crate) struct HoverHit(pub Option<HitData>);
+
 impl InputState {
     /// `Over` event handler
     pub fn start_hovering(&mut self) {
         trace!("Starting hover");
         match self {
             InputState::Idle => *self = InputState::Hovering,
-            InputState::Hovering | InputState::Dragging { .. } => {}
+            InputState::Hovering
+            | InputState::Dragging { .. }
+            | InputState::MovingSelection { .. } => {}
         }
     }
 
@@ -41,7 +47,9 @@ impl InputState {
     /// Handler for [`Move`] event
     pub fn continue_hovering(&mut self) {
         match self {
-            InputState::Hovering | InputState::Dragging { .. } => {}
+            InputState::Hovering
+            | InputState::Dragging { .. }
+            | InputState::MovingSelection { .. } => {}
             InputState::Idle => {
                 // handles that case that a drag is finished
                 *self = InputState::Hovering;
@@ -54,22 +62,24 @@ impl InputState {
         trace!("Ending hoverr");
         match self {
             InputState::Hovering => *self = InputState::Idle,
-            InputState::Idle | InputState::Dragging { .. } => {}
+            InputState::Idle | InputState::Dragging { .. } | InputState::MovingSelection { .. } => {
+            }
         }
     }
 }
 
 pub(super) fn handle_hover_start(
     trigger: Trigger<Pointer<Over>>,
-    mut editor: Query<&mut InputState, With<CosmicEditBuffer>>,
+    mut editor: Query<(&mut InputState, &mut HoverHit), With<CosmicEditBuffer>>,
     mut hover_in_evw: EventWriter<TextHoverIn>,
 ) {
-    let Ok(mut input_state) = editor.get_mut(trigger.target) else {
+    let Ok((mut input_state, mut hover_hit)) = editor.get_mut(trigger.target) else {
         warn_no_editor_on_picking_event("handling cursor `Over` event");
         return;
     };
 
     input_state.start_hovering();
+    hover_hit.0 = Some(trigger.event().hit.clone());
 
     if input_state.is_hovering() {
         hover_in_evw.send(TextHoverIn);
@@ -78,14 +88,15 @@ pub(super) fn handle_hover_start(
 
 pub(super) fn handle_hover_continue(
     trigger: Trigger<Pointer<Move>>,
-    mut editor: Query<&mut InputState, With<CosmicEditBuffer>>,
+    mut editor: Query<(&mut InputState, &mut HoverHit), With<CosmicEditBuffer>>,
 ) {
-    let Ok(mut input_state) = editor.get_mut(trigger.target) else {
+    let Ok((mut input_state, mut hover_hit)) = editor.get_mut(trigger.target) else {
         warn_no_editor_on_picking_event("handling cursor `Move` event");
         return;
     };
 
     input_state.continue_hovering();
+    hover_hit.0 = Some(trigger.event().hit.clone());
 }
 
 pub(super) fn handle_hover_end(
@@ -1,7 +1,15 @@
 use bevy::input::keyboard::{Key, KeyboardInput};
 use cosmic_text::{Action, Cursor, Motion, Selection};
 
-use crate::{input::CosmicTextChanged, prelude::*, MaxChars, MaxLines};
+use crate::{
+    input::{
+        delta::{record_edit, CosmicTextEdited, EditCause},
+        CosmicTextChanged,
+    },
+    input_filter::{CosmicInputRejected, EditSnapshot, InputFilter},
+    prelude::*,
+    MaxChars, MaxLines,
+};
 
 pub(super) fn keypress_command(keys: &ButtonInput<KeyCode>) -> bool {
     #[cfg(target_os = "macos")]
@@ -183,19 +191,27 @@ pub(crate) fn kb_input_text(
         &MaxChars,
         Entity,
         Option<&ReadOnly>,
+        Option<&InputFilter>,
     )>,
     mut evw_changed: EventWriter<CosmicTextChanged>,
+    mut evw_rejected: EventWriter<CosmicInputRejected>,
     mut font_system: ResMut<CosmicFontSystem>,
     mut is_deleting: Local<bool>,
+    mut commands: Commands,
 ) {
     let Some(active_editor_entity) = active_editor.0 else {
         return;
     };
 
-    if let Ok((mut editor, max_lines, max_chars, entity, readonly_opt)) =
+    if let Ok((mut editor, max_lines, max_chars, entity, readonly_opt, input_filter)) =
         cosmic_edit_query.get_mut(active_editor_entity)
     {
         let command = keypress_command(&keys);
+        let snapshot = input_filter
+            .filter(|_| {
+                !char_evr.is_empty() || keys.any_just_pressed([KeyCode::Enter, KeyCode::Delete])
+            })
+            .map(|_| EditSnapshot::take(&**editor));
         if keys.get_just_pressed().len() != 0 {
             editor.cursor_visible = true;
             editor.cursor_timer.reset();
@@ -233,8 +249,12 @@ pub(crate) fn kb_input_text(
         if keys.just_released(KeyCode::Backspace) {
             *is_deleting = false;
         }
+        let font_system = &mut font_system.0;
+        let mut deltas = Vec::new();
         if keys.just_pressed(KeyCode::Delete) && !readonly {
-            editor.action(&mut font_system.0, Action::Delete);
+            deltas = record_edit(&mut **editor, |editor| {
+                editor.action(font_system, Action::Delete)
+            });
             editor.with_buffer_mut(|b| b.set_redraw(true));
         }
 
@@ -242,7 +262,6 @@ pub(crate) fn kb_input_text(
             return;
         }
 
-        let mut is_edit = false;
         let mut is_return = false;
         if keys.just_pressed(KeyCode::Enter) {
             is_return = true;
@@ -250,16 +269,18 @@ pub(crate) fn kb_input_text(
                 && (max_chars.0 == 0 || editor.get_text().len() < max_chars.0)
             {
                 // to have new line on wasm rather than E
-                is_edit = true;
-                editor.action(&mut font_system.0, Action::Insert('\n'));
+                deltas.extend(record_edit(&mut **editor, |editor| {
+                    editor.action(font_system, Action::Insert('\n'))
+                }));
             }
         }
 
         if !is_return {
             for char_ev in char_evr.read() {
-                is_edit = true;
                 if *is_deleting {
-                    editor.action(&mut font_system.0, Action::Backspace);
+                    deltas.extend(record_edit(&mut **editor, |editor| {
+                        editor.action(font_system, Action::Backspace)
+                    }));
                 } else if !command
                     && (max_chars.0 == 0 || editor.get_text().len() < max_chars.0)
                     && matches!(char_ev.state, bevy::input::ButtonState::Pressed)
@@ -269,11 +290,15 @@ pub(crate) fn kb_input_text(
                             let b = char.as_bytes();
                             for c in b {
                                 let c: char = (*c).into();
-                                editor.action(&mut font_system.0, Action::Insert(c));
+                                deltas.extend(record_edit(&mut **editor, |editor| {
+                                    editor.action(font_system, Action::Insert(c))
+                                }));
                             }
                         }
                         Key::Space => {
-                            editor.action(&mut font_system.0, Action::Insert(' '));
+                            deltas.extend(record_edit(&mut **editor, |editor| {
+                                editor.action(font_system, Action::Insert(' '))
+                            }));
                         }
                         _ => (),
                     }
@@ -281,13 +306,25 @@ pub(crate) fn kb_input_text(
             }
         }
 
-        if !is_edit {
+        // key events can arrive without editing, e.g. an Enter that accepted a completion
+        if deltas.is_empty() {
             return;
         }
 
+        if let (Some(snapshot), Some(filter)) = (snapshot, input_filter) {
+            if let Some(rejected_text) = snapshot.reject_unless_accepted(&mut **editor, filter) {
+                evw_rejected.send(CosmicInputRejected {
+                    entity,
+                    rejected_text,
+                });
+                return;
+            }
+        }
+
         evw_changed.send(CosmicTextChanged((
             entity,
             editor.with_buffer_mut(|b| b.get_text()),
         )));
+        CosmicTextEdited::trigger(EditCause::Typed, deltas, entity, &mut commands);
     }
 }
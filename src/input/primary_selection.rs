@@ -0,0 +1,138 @@
+//! X11/Wayland primary selection: select-to-copy and middle-click paste.
+//!
+//! Disabled by default, enable with the [`PrimarySelection`] resource.
+//! Does nothing if the [`ClipboardBackend`](crate::input::clipboard::ClipboardBackend)
+//! has no primary selection.
+
+use cosmic_text::Action;
+use render_implementations::RelativeQuery;
+
+use crate::{
+    input::{
+        clipboard::{insert_pasted_text, ClipboardOutcome, CosmicClipboard, PasteFilter},
+        delta::EditCause,
+        CosmicTextChanged,
+    },
+    input_filter::{filtered_edit, CosmicInputRejected, InputFilter},
+    password::Password,
+    prelude::*,
+    MaxChars, MaxLines,
+};
+
+/// Should the primary selection be used?
+///
+/// When enabled, selecting text in the focussed editor places it into the primary selection,
+/// and clicking the middle mouse button pastes the primary selection at the clicked position.
+#[derive(Resource, Reflect, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Resource)]
+pub enum PrimarySelection {
+    #[default]
+    Disabled,
+    Enabled,
+}
+
+impl PrimarySelection {
+    pub fn is_enabled(&self) -> bool {
+        matches!(self, PrimarySelection::Enabled)
+    }
+}
+
+/// Writes the focussed editor's selection to the primary selection whenever it changes.
+///
+/// Editors with a [`Password`] are skipped, as this runs while they hold the real text
+pub(super) fn update_primary_selection(
+    enabled: Res<PrimarySelection>,
+    active_editor: Res<FocusedWidget>,
+    mut clipboard: ResMut<CosmicClipboard>,
+    editors: Query<&CosmicEditor, (Changed<CosmicEditor>, Without<Password>)>,
+    mut last_selection: Local<Option<String>>,
+) {
+    if !enabled.is_enabled() {
+        return;
+    }
+    let Some(Ok(editor)) = active_editor.0.map(|e| editors.get(e)) else {
+        return;
+    };
+
+    let selection = editor.copy_selection().filter(|text| !text.is_empty());
+    if selection.is_none() || selection == *last_selection {
+        return;
+    }
+    if let Some(text) = &selection {
+        if let Err(err) = clipboard.set_primary(text) {
+            debug!(message = "Failed to set primary selection", ?err);
+        }
+    }
+    *last_selection = selection;
+}
+
+/// Pastes the primary selection where the middle mouse button was clicked
+pub(super) fn handle_middle_click(
+    trigger: Trigger<Pointer<Click>>,
+    enabled: Res<PrimarySelection>,
+    focused: Res<FocusedWidget>,
+    mut clipboard: ResMut<CosmicClipboard>,
+    mut editor: Query<
+        (
+            &mut CosmicEditor,
+            &MaxLines,
+            &MaxChars,
+            Option<&PasteFilter>,
+            Option<&InputFilter>,
+            RelativeQuery,
+        ),
+        Without<ReadOnly>,
+    >,
+    mut font_system: ResMut<CosmicFontSystem>,
+    mut evw_changed: EventWriter<CosmicTextChanged>,
+    mut evw_rejected: EventWriter<CosmicInputRejected>,
+    mut commands: Commands,
+) -> render_implementations::Result<()> {
+    let target = trigger.target;
+    let click = trigger.event();
+
+    if !enabled.is_enabled() || click.button != PointerButton::Middle {
+        return Ok(());
+    }
+    if focused.0 != Some(target) {
+        return Ok(());
+    }
+    let Ok((mut editor, max_lines, max_chars, paste_filter, input_filter, buffer_relative)) =
+        editor.get_mut(target)
+    else {
+        return Ok(());
+    };
+    let Ok(mut text) = clipboard.get_primary() else {
+        return Ok(());
+    };
+    if let Some(filter) = paste_filter {
+        let Some(filtered) = filter.apply(target, &text) else {
+            return Ok(());
+        };
+        text = filtered;
+    }
+
+    let font_system = &mut font_system.0;
+    let buffer_size = editor.with_buffer_mut(|b| b.borrow_with(font_system).expected_size());
+    let buffer_coord = buffer_relative.compute_buffer_coord(&click.hit, buffer_size)?;
+    editor.action(
+        font_system,
+        Action::Click {
+            x: buffer_coord.x as i32,
+            y: buffer_coord.y as i32,
+        },
+    );
+    let result = filtered_edit(&mut **editor, input_filter, |editor| {
+        insert_pasted_text(editor, &text, max_lines, max_chars)
+    });
+
+    ClipboardOutcome::filtered(EditCause::Paste, result).send_events(
+        target,
+        || editor.get_text(),
+        &mut evw_changed,
+        &mut evw_rejected,
+        &mut commands,
+    );
+
+    Ok(())
+}
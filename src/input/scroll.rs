@@ -1,10 +1,10 @@
 use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
 use cosmic_text::Action;
 
-use crate::{prelude::*, ScrollEnabled};
+use crate::{numeric_input::NumericField, prelude::*, ScrollEnabled};
 
 pub(crate) fn scroll(
-    mut editor: Query<(&mut CosmicEditor, &ScrollEnabled)>,
+    mut editor: Query<(&mut CosmicEditor, &ScrollEnabled), Without<NumericField>>,
     mut font_system: ResMut<CosmicFontSystem>,
     mut scroll_evr: EventReader<MouseWheel>,
 ) {
@@ -0,0 +1,204 @@
+//! Events for cursor movement and selection changes, see [`CosmicCursorMoved`] and
+//! [`CosmicSelectionChanged`]
+
+use cosmic_text::{Cursor, Edit};
+
+use crate::{input::delta::TextPosition, password::Password, prelude::*};
+
+/// Triggered on the focussed entity whenever its cursor moves,
+/// e.g. to show the line and column in a status bar
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_cosmic_edit::prelude::*;
+/// use bevy_cosmic_edit::input::selection::CosmicCursorMoved;
+///
+/// # fn setup(mut commands: Commands) {
+/// commands.spawn(TextEdit).observe(|trigger: Trigger<CosmicCursorMoved>| {
+///     let cursor = trigger.event().cursor;
+///     info!("Ln {}, Col {}", cursor.line + 1, cursor.char + 1);
+/// });
+/// # }
+/// ```
+#[derive(Event, Reflect, Debug, Clone, PartialEq, Eq)]
+pub struct CosmicCursorMoved {
+    pub cursor: TextPosition,
+    /// Start and end of the selection, if any text is selected
+    pub selection: Option<(TextPosition, TextPosition)>,
+}
+
+/// Triggered on the focussed entity whenever text is selected or deselected
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_cosmic_edit::prelude::*;
+/// use bevy_cosmic_edit::input::selection::CosmicSelectionChanged;
+///
+/// #[derive(Component)]
+/// struct BoldButton;
+///
+/// fn enable_bold_button(
+///     trigger: Trigger<CosmicSelectionChanged>,
+///     mut buttons: Query<&mut Visibility, With<BoldButton>>,
+/// ) {
+///     for mut visibility in buttons.iter_mut() {
+///         *visibility = match trigger.event().selection {
+///             Some(_) => Visibility::Inherited,
+///             None => Visibility::Hidden,
+///         };
+///     }
+/// }
+/// ```
+#[derive(Event, Reflect, Debug, Clone, PartialEq, Eq)]
+pub struct CosmicSelectionChanged {
+    pub cursor: TextPosition,
+    /// Start and end of the selection, `None` when deselected
+    pub selection: Option<(TextPosition, TextPosition)>,
+    /// Always `None` for a [`Password`]
+    pub selected_text: Option<String>,
+}
+
+/// Cursor and selection when the events were last sent
+#[derive(Component, Default, Debug)]
+pub(crate) struct LastCursor(Option<(Cursor, Option<(Cursor, Cursor)>)>);
+
+/// Non-empty selection bounds
+fn selection_bounds(editor: &CosmicEditor) -> Option<(Cursor, Cursor)> {
+    editor
+        .selection_bounds()
+        .filter(|(start, end)| (start.line, start.index) != (end.line, end.index))
+}
+
+/// Compares by position, ignoring affinity
+fn same_position(a: Cursor, b: Cursor) -> bool {
+    (a.line, a.index) == (b.line, b.index)
+}
+
+/// Sends [`CosmicCursorMoved`] and [`CosmicSelectionChanged`] however the cursor moved,
+/// before [`Password`] hides the text
+pub(crate) fn send_cursor_events(
+    mut editors: Query<
+        (Entity, &CosmicEditor, &mut LastCursor, Has<Password>),
+        Changed<CosmicEditor>,
+    >,
+    mut unfocused: RemovedComponents<CosmicEditor>,
+    mut last_cursors: Query<&mut LastCursor, Without<CosmicEditor>>,
+    mut commands: Commands,
+) {
+    for entity in unfocused.read() {
+        if let Ok(mut last_cursor) = last_cursors.get_mut(entity) {
+            last_cursor.0 = None;
+        }
+    }
+
+    for (entity, editor, mut last_cursor, password) in editors.iter_mut() {
+        let cursor = editor.cursor();
+        let selection = selection_bounds(editor);
+
+        let (moved, selection_changed) = match last_cursor.0 {
+            Some((last, last_selection)) => (
+                !same_position(cursor, last),
+                match (selection, last_selection) {
+                    (Some((start, end)), Some((last_start, last_end))) => {
+                        !same_position(start, last_start) || !same_position(end, last_end)
+                    }
+                    (None, None) => false,
+                    _ => true,
+                },
+            ),
+            None => (true, selection.is_some()),
+        };
+        if !moved && !selection_changed {
+            continue;
+        }
+        last_cursor.0 = Some((cursor, selection));
+
+        let (position, selection_positions) = editor.with_buffer(|buffer| {
+            (
+                TextPosition::from_cursor(buffer, cursor),
+                selection.map(|(start, end)| {
+                    (
+                        TextPosition::from_cursor(buffer, start),
+                        TextPosition::from_cursor(buffer, end),
+                    )
+                }),
+            )
+        });
+        if moved {
+            commands.trigger_targets(
+                CosmicCursorMoved {
+                    cursor: position,
+                    selection: selection_positions,
+                },
+                entity,
+            );
+        }
+        if selection_changed {
+            commands.trigger_targets(
+                CosmicSelectionChanged {
+                    cursor: position,
+                    selection: selection_positions,
+                    selected_text: selection
+                        .filter(|_| !password)
+                        .and_then(|_| editor.copy_selection()),
+                },
+                entity,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+    use cosmic_text::Selection;
+
+    use super::*;
+
+    #[derive(Resource, Default)]
+    struct Received(Vec<Option<String>>);
+
+    #[test]
+    fn selection_events_only_on_change() {
+        let mut font_system = test_font_system();
+        let buffer = test_buffer(&mut font_system, "hello world");
+
+        let mut world = World::new();
+        world.init_resource::<Received>();
+        let entity = world
+            .spawn((
+                CosmicEditor::clone_from_buffer(&buffer),
+                LastCursor::default(),
+            ))
+            .observe(
+                |trigger: Trigger<CosmicSelectionChanged>, mut received: ResMut<Received>| {
+                    received.0.push(trigger.event().selected_text.clone());
+                },
+            )
+            .id();
+
+        let select = |world: &mut World, selection: Selection, cursor: Cursor| {
+            let mut editor = world.get_mut::<CosmicEditor>(entity).unwrap();
+            editor.set_selection(selection);
+            editor.set_cursor(cursor);
+            world.run_system_once(send_cursor_events).unwrap();
+        };
+        select(&mut world, Selection::None, Cursor::new(0, 2));
+        select(
+            &mut world,
+            Selection::Normal(Cursor::new(0, 0)),
+            Cursor::new(0, 5),
+        );
+        select(
+            &mut world,
+            Selection::Normal(Cursor::new(0, 0)),
+            Cursor::new(0, 5),
+        );
+        select(&mut world, Selection::None, Cursor::new(0, 5));
+
+        assert_eq!(
+            world.resource::<Received>().0,
+            [Some("hello".to_owned()), None]
+        );
+    }
+}
@@ -0,0 +1,308 @@
+//! Undo and redo for every editor, see [`UndoHistory`]
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use cosmic_text::{Change, ChangeItem, Cursor, Edit, FontSystem, Selection};
+
+use crate::{
+    cosmic_edit::DefaultAttrs,
+    input::{
+        clipboard::ClipboardOutcome,
+        delta::{CosmicTextEdited, DeltaKind, EditCause, TextDelta},
+        keyboard::keypress_command,
+        CosmicTextChanged,
+    },
+    input_filter::CosmicInputRejected,
+    password::Password,
+    placeholder::Placeholder,
+    prelude::*,
+};
+
+/// The edits made to a [`CosmicEditBuffer`], so they can be undone and redone.
+///
+/// Each [`CosmicTextEdited`] is one step, including programmatic edits.
+/// \[Ctrl\]+\[Z\] undoes a step, and \[Ctrl\]+\[Y\] or \[Ctrl\]+\[Shift\]+\[Z\] redoes it
+/// (\[Cmd\] on macOS). Only the text is restored, not its formatting.
+///
+/// Nothing is recorded for a [`Password`]. If the text changes without an edit being
+/// reported, the history no longer fits the text and is cleared.
+#[derive(Component, Debug)]
+pub struct UndoHistory {
+    /// How many steps are kept, the oldest are forgotten first
+    pub limit: usize,
+    undo: Vec<UndoStep>,
+    redo: Vec<UndoStep>,
+}
+
+impl Default for UndoHistory {
+    fn default() -> Self {
+        Self {
+            limit: 100,
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+}
+
+impl UndoHistory {
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    fn push(&mut self, deltas: &[TextDelta], text: &str) {
+        let items = deltas
+            .iter()
+            .map(|delta| ChangeItem {
+                start: Cursor::new(delta.start.line, delta.start.byte),
+                end: Cursor::new(delta.end.line, delta.end.byte),
+                text: delta.text.clone(),
+                insert: delta.kind == DeltaKind::Inserted,
+            })
+            .collect();
+        self.undo.push(UndoStep {
+            change: Change { items },
+            text: text_hash(text),
+        });
+        if self.undo.len() > self.limit {
+            self.undo.drain(..self.undo.len() - self.limit);
+        }
+        self.redo.clear();
+    }
+}
+
+/// A recorded edit
+#[derive(Debug)]
+struct UndoStep {
+    change: Change,
+    /// Hash of the text the step can be undone (or redone) from
+    text: u64,
+}
+
+fn text_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum UndoOp {
+    Undo,
+    Redo,
+}
+
+/// Records every reported edit as a step, except undoing and redoing itself
+pub(crate) fn record_undo_steps(
+    trigger: Trigger<CosmicTextEdited>,
+    mut editors: Query<
+        (
+            &mut UndoHistory,
+            Option<&CosmicEditor>,
+            &CosmicEditBuffer,
+            Option<&Placeholder>,
+        ),
+        Without<Password>,
+    >,
+) {
+    let event = trigger.event();
+    if event.cause == EditCause::Undo {
+        return;
+    }
+    let Ok((mut history, editor, buffer, placeholder)) = editors.get_mut(trigger.entity()) else {
+        return;
+    };
+    // typing into the placeholder is reported again once it is removed
+    if placeholder.is_some_and(Placeholder::is_active) {
+        return;
+    }
+    let text = match editor {
+        Some(editor) => editor.get_text(),
+        None => buffer.get_text(),
+    };
+    history.push(&event.deltas, &text);
+}
+
+/// Undoes or redoes a step, returning the changes made
+pub(crate) fn apply_undo_op(
+    op: UndoOp,
+    history: &mut UndoHistory,
+    editor: &mut CosmicEditor,
+    placeholder: Option<Mut<Placeholder>>,
+    attrs: &DefaultAttrs,
+    font_system: &mut FontSystem,
+) -> Vec<TextDelta> {
+    let UndoHistory { undo, redo, .. } = history;
+    let (from, to) = match op {
+        UndoOp::Undo => (undo, redo),
+        UndoOp::Redo => (redo, undo),
+    };
+    let Some(step) = from.pop() else {
+        return Vec::new();
+    };
+    if let Some(mut placeholder) = placeholder {
+        placeholder.clear(editor, attrs.as_attrs(), font_system);
+    }
+    if text_hash(&editor.get_text()) != step.text {
+        history.clear();
+        return Vec::new();
+    }
+
+    let mut change = step.change.clone();
+    if op == UndoOp::Undo {
+        change.reverse();
+    }
+    editor.set_selection(Selection::None);
+    editor.apply_change(&change);
+    editor.set_redraw(true);
+    let deltas = editor.with_buffer(|buffer| TextDelta::from_change(buffer, &change));
+    to.push(UndoStep {
+        change: step.change,
+        text: text_hash(&editor.get_text()),
+    });
+    deltas
+}
+
+pub(crate) fn kb_undo(
+    active_editor: Res<FocusedWidget>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut editors: Query<
+        (
+            &mut CosmicEditor,
+            &mut UndoHistory,
+            Option<&mut Placeholder>,
+            &DefaultAttrs,
+        ),
+        Without<ReadOnly>,
+    >,
+    mut font_system: ResMut<CosmicFontSystem>,
+    mut evw_changed: EventWriter<CosmicTextChanged>,
+    mut evw_rejected: EventWriter<CosmicInputRejected>,
+    mut commands: Commands,
+) {
+    let Some(entity) = active_editor.0 else {
+        return;
+    };
+    if !keypress_command(&keys) {
+        return;
+    }
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let op = if keys.just_pressed(KeyCode::KeyZ) && !shift {
+        UndoOp::Undo
+    } else if keys.just_pressed(KeyCode::KeyZ) || keys.just_pressed(KeyCode::KeyY) {
+        UndoOp::Redo
+    } else {
+        return;
+    };
+    let Ok((mut editor, mut history, placeholder, attrs)) = editors.get_mut(entity) else {
+        return;
+    };
+
+    let deltas = apply_undo_op(
+        op,
+        &mut history,
+        &mut editor,
+        placeholder,
+        attrs,
+        &mut font_system.0,
+    );
+    ClipboardOutcome::filtered(EditCause::Undo, Ok(deltas)).send_events(
+        entity,
+        || editor.get_text(),
+        &mut evw_changed,
+        &mut evw_rejected,
+        &mut commands,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn undoes_and_redoes_edits() {
+        let mut font_system = test_font_system();
+        let buffer = test_buffer(&mut font_system, "hello");
+        let mut editor = CosmicEditor::clone_from_buffer(&buffer);
+        editor.set_cursor(Cursor::new(0, 5));
+
+        let mut world = World::new();
+        world.insert_resource(CosmicFontSystem(font_system));
+        world.init_resource::<Assets<Image>>();
+        world.add_observer(record_undo_steps);
+        let entity = world
+            .spawn((
+                buffer,
+                editor,
+                UndoHistory::default(),
+                DefaultAttrs::default(),
+            ))
+            .id();
+
+        let edit =
+            |world: &mut World, cause: EditCause, edit: fn(&mut cosmic_text::Editor<'static>)| {
+                let mut editor = world.get_mut::<CosmicEditor>(entity).unwrap();
+                let deltas = crate::input::delta::record_edit(&mut **editor, |editor| {
+                    edit(editor);
+                });
+                world.trigger_targets(CosmicTextEdited { cause, deltas }, entity);
+            };
+        let apply = |world: &mut World, op: UndoOp| {
+            world
+                .run_system_once(
+                    move |mut editors: Query<(
+                        &mut CosmicEditor,
+                        &mut UndoHistory,
+                        &DefaultAttrs,
+                    )>,
+                          mut font_system: ResMut<CosmicFontSystem>| {
+                        let (mut editor, mut history, attrs) = editors.single_mut();
+                        let deltas = apply_undo_op(
+                            op,
+                            &mut history,
+                            &mut editor,
+                            None,
+                            attrs,
+                            &mut font_system.0,
+                        );
+                        (editor.get_text(), deltas.len())
+                    },
+                )
+                .unwrap()
+        };
+
+        edit(&mut world, EditCause::Typed, |editor| {
+            editor.insert_string(" world", None);
+        });
+        edit(&mut world, EditCause::Cut, |editor| {
+            editor.delete_range(Cursor::new(0, 0), Cursor::new(0, 6));
+        });
+        assert_eq!(apply(&mut world, UndoOp::Undo), ("hello world".into(), 1));
+        assert_eq!(apply(&mut world, UndoOp::Undo), ("hello".into(), 1));
+        assert_eq!(apply(&mut world, UndoOp::Undo), ("hello".into(), 0));
+        assert_eq!(apply(&mut world, UndoOp::Redo), ("hello world".into(), 1));
+
+        // a new edit forgets what was undone
+        edit(&mut world, EditCause::Typed, |editor| {
+            editor.insert_string("!", None);
+        });
+        assert_eq!(apply(&mut world, UndoOp::Redo), ("hello world!".into(), 0));
+
+        // text changed without an edit being reported can't be undone
+        world
+            .get_mut::<CosmicEditor>(entity)
+            .unwrap()
+            .insert_string("?", None);
+        assert_eq!(apply(&mut world, UndoOp::Undo), ("hello world!?".into(), 0));
+        assert!(!world.get::<UndoHistory>(entity).unwrap().can_undo());
+    }
+}
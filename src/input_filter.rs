@@ -0,0 +1,314 @@
+//! Restrict which text can be entered into a [`CosmicEditBuffer`]
+//!
+//! Typed, pasted and dropped input that the [`InputFilter`] doesn't accept is undone before
+//! [`CosmicTextChanged`](crate::input::CosmicTextChanged) is sent. Any other change,
+//! e.g. setting the text programmatically, is reverted to the last accepted text.
+//! Text that was never accepted, e.g. when the filter is added, is left alone.
+
+use std::sync::Arc;
+
+use cosmic_text::{BufferLine, Cursor, Edit, Selection};
+
+use crate::{
+    input::{
+        delta::{record_edit, TextDelta},
+        InputSet,
+    },
+    placeholder::Placeholder,
+    prelude::*,
+};
+
+pub(crate) struct InputFilterPlugin;
+
+impl Plugin for InputFilterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CosmicInputRejected>()
+            .register_type::<CosmicInputRejected>()
+            .add_systems(Update, enforce_input_filters.after(InputSet));
+    }
+}
+
+/// Which text is allowed in this [`CosmicEditBuffer`].
+///
+/// The filter is checked against the whole text, so it must also accept incomplete input,
+/// like an empty field.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_cosmic_edit::prelude::*;
+/// use bevy_cosmic_edit::input_filter::InputFilter;
+///
+/// # fn setup(mut commands: Commands) {
+/// commands.spawn((TextEdit, InputFilter::Digits));
+/// commands.spawn((TextEdit, InputFilter::regex(r"[a-z_]{0,16}").unwrap()));
+/// commands.spawn((
+///     TextEdit,
+///     InputFilter::custom(|text| !text.contains("  ")),
+/// ));
+/// # }
+/// ```
+#[derive(Component, Clone)]
+#[require(AcceptedText)]
+pub enum InputFilter {
+    /// ASCII digits only
+    Digits,
+    /// A decimal number, with an optional leading `-` and at most one `.`
+    Decimal,
+    /// Letters and numbers, including non-ASCII ones
+    Alphanumeric,
+    /// Hexadecimal digits with an optional leading `#`, e.g. colours
+    Hex,
+    /// Text matched by the regex, which [`InputFilter::regex`] anchors to the whole text
+    Regex(regex::Regex),
+    /// Text accepted by the closure
+    Custom(Arc<dyn Fn(&str) -> bool + Send + Sync>),
+}
+
+impl InputFilter {
+    /// Accepts text that `pattern` matches entirely
+    pub fn regex(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self::Regex(regex::Regex::new(&format!("^(?:{pattern})$"))?))
+    }
+
+    pub fn custom(filter: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        Self::Custom(Arc::new(filter))
+    }
+
+    /// Is `text` allowed?
+    pub fn accepts(&self, text: &str) -> bool {
+        match self {
+            InputFilter::Digits => text.chars().all(|c| c.is_ascii_digit()),
+            InputFilter::Decimal => {
+                let digits = text.strip_prefix('-').unwrap_or(text);
+                digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+                    && digits.matches('.').count() <= 1
+            }
+            InputFilter::Alphanumeric => text.chars().all(char::is_alphanumeric),
+            InputFilter::Hex => text
+                .strip_prefix('#')
+                .unwrap_or(text)
+                .chars()
+                .all(|c| c.is_ascii_hexdigit()),
+            InputFilter::Regex(regex) => regex.is_match(text),
+            InputFilter::Custom(filter) => filter(text),
+        }
+    }
+}
+
+impl std::fmt::Debug for InputFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputFilter::Digits => write!(f, "Digits"),
+            InputFilter::Decimal => write!(f, "Decimal"),
+            InputFilter::Alphanumeric => write!(f, "Alphanumeric"),
+            InputFilter::Hex => write!(f, "Hex"),
+            InputFilter::Regex(regex) => f.debug_tuple("Regex").field(regex).finish(),
+            InputFilter::Custom(_) => f.debug_tuple("Custom").finish_non_exhaustive(),
+        }
+    }
+}
+
+/// Sent when input was rejected by an [`InputFilter`], e.g. to flash the field
+#[derive(Event, Reflect, Debug)]
+pub struct CosmicInputRejected {
+    pub entity: Entity,
+    /// The text that would have resulted from the input
+    pub rejected_text: String,
+}
+
+/// The last text accepted by the [`InputFilter`] and its lines, restored with their
+/// formatting after programmatic changes
+#[derive(Component, Default)]
+struct AcceptedText(Option<(String, Vec<BufferLine>)>);
+
+/// The state of an editor before an edit, so the edit can be undone
+pub(crate) struct EditSnapshot {
+    lines: Vec<BufferLine>,
+    cursor: Cursor,
+    selection: Selection,
+}
+
+impl EditSnapshot {
+    pub(crate) fn take<'b>(editor: &impl Edit<'b>) -> Self {
+        Self {
+            lines: editor.with_buffer(|b| b.lines.clone()),
+            cursor: editor.cursor(),
+            selection: editor.selection(),
+        }
+    }
+
+    pub(crate) fn restore<'b>(self, editor: &mut impl Edit<'b>) {
+        editor.with_buffer_mut(|b| {
+            b.lines = self.lines;
+            b.set_redraw(true);
+        });
+        editor.set_cursor(self.cursor);
+        editor.set_selection(self.selection);
+    }
+
+    /// Restores the snapshot if the editor's text isn't accepted by `filter`,
+    /// returning the rejected text
+    pub(crate) fn reject_unless_accepted<'b>(
+        self,
+        editor: &mut impl Edit<'b>,
+        filter: &InputFilter,
+    ) -> Option<String> {
+        let text = editor.with_buffer(|b| b.get_text());
+        if filter.accepts(&text) {
+            return None;
+        }
+        self.restore(editor);
+        Some(text)
+    }
+}
+
+/// Runs `edit`, undoing it if the `filter` doesn't accept the resulting text.
+///
+/// Returns the changes made, or the rejected text
+pub(crate) fn filtered_edit<'b, E: Edit<'b>>(
+    editor: &mut E,
+    filter: Option<&InputFilter>,
+    edit: impl FnOnce(&mut E),
+) -> Result<Vec<TextDelta>, String> {
+    let snapshot = filter.map(|_| EditSnapshot::take(editor));
+    let deltas = record_edit(editor, edit);
+    match (snapshot, filter) {
+        (Some(snapshot), Some(filter)) if !deltas.is_empty() => {
+            match snapshot.reject_unless_accepted(editor, filter) {
+                Some(rejected_text) => Err(rejected_text),
+                None => Ok(deltas),
+            }
+        }
+        _ => Ok(deltas),
+    }
+}
+
+fn enforce_input_filters(
+    mut editors: Query<
+        (
+            Entity,
+            EditorBuffer,
+            &InputFilter,
+            &mut AcceptedText,
+            Option<&Placeholder>,
+        ),
+        Or<(
+            Changed<CosmicEditBuffer>,
+            Changed<CosmicEditor>,
+            Changed<InputFilter>,
+        )>,
+    >,
+    mut font_system: ResMut<CosmicFontSystem>,
+    mut evw_rejected: EventWriter<CosmicInputRejected>,
+) {
+    for (entity, mut buffer, filter, mut accepted, placeholder) in editors.iter_mut() {
+        if placeholder.is_some_and(Placeholder::is_active) {
+            continue;
+        }
+        let text = buffer.get_text();
+        if accepted
+            .0
+            .as_ref()
+            .is_some_and(|(previous, _)| *previous == text)
+        {
+            continue;
+        }
+        if filter.accepts(&text) {
+            accepted.0 = Some((text, buffer.with_buffer(|b| b.lines.clone())));
+            continue;
+        }
+
+        // e.g. the filter was just added to text it doesn't accept, which is left alone
+        let Some((_, lines)) = accepted
+            .0
+            .clone()
+            .filter(|(previous, _)| filter.accepts(previous))
+        else {
+            evw_rejected.send(CosmicInputRejected {
+                entity,
+                rejected_text: text,
+            });
+            continue;
+        };
+        buffer.set_lines(lines);
+        if let Some(editor) = buffer.editor() {
+            editor.set_selection(Selection::None);
+            editor.action(
+                &mut font_system.0,
+                cosmic_text::Action::Motion(cosmic_text::Motion::BufferEnd),
+            );
+        }
+        evw_rejected.send(CosmicInputRejected {
+            entity,
+            rejected_text: text,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_filters() {
+        assert!(InputFilter::Digits.accepts("0123"));
+        assert!(!InputFilter::Digits.accepts("12a"));
+        assert!(InputFilter::Decimal.accepts("-3.5"));
+        assert!(InputFilter::Decimal.accepts(""));
+        assert!(!InputFilter::Decimal.accepts("1.2.3"));
+        assert!(!InputFilter::Decimal.accepts("1-2"));
+        assert!(InputFilter::Hex.accepts("#ff00AA"));
+        assert!(!InputFilter::Hex.accepts("#fg"));
+        assert!(InputFilter::Alphanumeric.accepts("abcß9"));
+        assert!(!InputFilter::Alphanumeric.accepts("a b"));
+
+        let regex = InputFilter::regex("[a-z]{0,3}").unwrap();
+        assert!(regex.accepts("ab"));
+        assert!(!regex.accepts("abcd"));
+        // the leftmost match isn't always the longest
+        let alternatives = InputFilter::regex("a|ab").unwrap();
+        assert!(alternatives.accepts("ab"));
+        assert!(!alternatives.accepts("abc"));
+    }
+
+    #[test]
+    fn programmatic_changes_are_reverted_with_formatting() {
+        use bevy::ecs::system::RunSystemOnce;
+        use cosmic_text::{Attrs, Weight};
+
+        let mut font_system = test_font_system();
+        let bold = Attrs::new().weight(Weight::BOLD);
+        let buffer = test_buffer(&mut font_system, "").with_rich_text(
+            &mut font_system,
+            [("1", Attrs::new()), ("2", bold)],
+            Attrs::new(),
+        );
+        let attrs_list = buffer.inner().lines[0].attrs_list().clone();
+
+        let mut world = World::new();
+        world.insert_resource(CosmicFontSystem(font_system));
+        world.init_resource::<Events<CosmicInputRejected>>();
+        world.init_resource::<Assets<Image>>();
+        let entity = world.spawn((buffer, InputFilter::Digits)).id();
+        world.run_system_once(enforce_input_filters).unwrap();
+
+        world
+            .run_system_once(
+                |mut buffers: Query<EditorBuffer>, mut font_system: ResMut<CosmicFontSystem>| {
+                    buffers
+                        .single_mut()
+                        .set_text(&mut font_system, "ab", Attrs::new());
+                },
+            )
+            .unwrap();
+        world.run_system_once(enforce_input_filters).unwrap();
+
+        let buffer = world.get::<CosmicEditBuffer>(entity).unwrap();
+        assert_eq!(buffer.get_text(), "12");
+        assert_eq!(buffer.inner().lines[0].attrs_list(), &attrs_list);
+        let rejected = world.resource::<Events<CosmicInputRejected>>();
+        let rejected = rejected.iter_current_update_events().collect::<Vec<_>>();
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].rejected_text, "ab");
+    }
+}
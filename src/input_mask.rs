@@ -0,0 +1,422 @@
+//! Fixed format input like phone numbers, dates or license keys
+//!
+//! See [`InputMask`]
+
+use cosmic_text::{Cursor, Edit, Selection};
+
+use crate::{
+    cosmic_edit::DefaultAttrs,
+    input::{
+        delta::{CosmicTextEdited, EditCause, TextDelta},
+        CosmicTextChanged, InputSet,
+    },
+    prelude::*,
+};
+
+pub(crate) struct InputMaskPlugin;
+
+impl Plugin for InputMaskPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(remember_edit_cause)
+            .add_systems(Update, apply_input_masks.after(InputSet));
+    }
+}
+
+/// Formats a single line [`CosmicEditBuffer`] according to a pattern.
+///
+/// In the pattern, `#` is a digit slot, `A` a letter slot, `X` a letter or digit slot
+/// and `*` accepts any character. Precede a slot character with `\` to use it literally.
+/// Every other character is a literal separator, which is inserted automatically and
+/// skipped over by the cursor. Unfilled slots show the placeholder character.
+///
+/// Formatting typed or pasted text is reported with the same [`EditCause`] as the edit.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_cosmic_edit::prelude::*;
+/// use bevy_cosmic_edit::input_mask::InputMask;
+///
+/// # fn setup(mut commands: Commands) {
+/// commands.spawn((TextEdit, InputMask::new("(###) ###-####")));
+/// commands.spawn((TextEdit, InputMask::new("XXXX-XXXX-XXXX").with_placeholder('•')));
+/// # }
+///
+/// fn read_phone_numbers(masks: Query<&InputMask, Changed<InputMask>>) {
+///     for mask in masks.iter() {
+///         info!("{} ({})", mask.formatted(), mask.raw());
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Clone)]
+pub struct InputMask {
+    slots: Vec<MaskChar>,
+    placeholder: char,
+    raw: String,
+    /// Text of the buffer the last time it was formatted
+    last_text: Option<String>,
+    /// Char index of the cursor the last time it was snapped
+    last_cursor: Option<usize>,
+    /// Why the text was last edited, to report formatting it the same way
+    edit_cause: Option<EditCause>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MaskChar {
+    Digit,
+    Letter,
+    Alphanumeric,
+    Any,
+    Literal(char),
+}
+
+impl MaskChar {
+    fn accepts(self, c: char) -> bool {
+        match self {
+            MaskChar::Digit => c.is_ascii_digit(),
+            MaskChar::Letter => c.is_alphabetic(),
+            MaskChar::Alphanumeric => c.is_alphanumeric(),
+            MaskChar::Any => !c.is_control(),
+            MaskChar::Literal(_) => false,
+        }
+    }
+}
+
+impl InputMask {
+    pub fn new(pattern: &str) -> Self {
+        let mut slots = Vec::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            slots.push(match c {
+                '#' => MaskChar::Digit,
+                'A' => MaskChar::Letter,
+                'X' => MaskChar::Alphanumeric,
+                '*' => MaskChar::Any,
+                '\\' => MaskChar::Literal(chars.next().unwrap_or('\\')),
+                c => MaskChar::Literal(c),
+            });
+        }
+        Self {
+            slots,
+            placeholder: '_',
+            raw: String::new(),
+            last_text: None,
+            last_cursor: None,
+            edit_cause: None,
+        }
+    }
+
+    /// Character shown in unfilled slots, `_` by default
+    pub fn with_placeholder(mut self, placeholder: char) -> Self {
+        self.placeholder = placeholder;
+        self
+    }
+
+    /// Only the characters entered into slots, e.g. `5551234567`
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// Replaces the entered characters, dropping any that don't fit the pattern
+    pub fn set_raw(&mut self, raw: &str) {
+        self.raw = self.fit(raw.chars());
+    }
+
+    /// Have all slots been filled?
+    pub fn is_complete(&self) -> bool {
+        self.raw.chars().count() == self.slot_count()
+    }
+
+    /// The text as displayed, e.g. `(555) 123-4___`
+    pub fn formatted(&self) -> String {
+        let mut raw = self.raw.chars();
+        self.slots
+            .iter()
+            .map(|slot| match slot {
+                MaskChar::Literal(c) => *c,
+                _ => raw.next().unwrap_or(self.placeholder),
+            })
+            .collect()
+    }
+
+    fn slot_count(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|slot| !matches!(slot, MaskChar::Literal(_)))
+            .count()
+    }
+
+    /// Char index in the formatted text of the `n`th slot, or the end
+    fn slot_position(&self, n: usize) -> usize {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| !matches!(slot, MaskChar::Literal(_)))
+            .nth(n)
+            .map_or(self.slots.len(), |(i, _)| i)
+    }
+
+    /// Assigns characters to slots in order, skipping any that don't fit
+    fn fit(&self, chars: impl IntoIterator<Item = char>) -> String {
+        let mut slots = self
+            .slots
+            .iter()
+            .filter(|slot| !matches!(slot, MaskChar::Literal(_)))
+            .peekable();
+        let mut raw = String::new();
+        for c in chars {
+            let Some(slot) = slots.peek() else {
+                break;
+            };
+            if slot.accepts(c) {
+                raw.push(c);
+                slots.next();
+            }
+        }
+        raw
+    }
+
+    /// Chars of `text` entered into slots, rather than part of the mask, with their index.
+    ///
+    /// Text before and after the edit is compared with the last formatted text, where only
+    /// filled slots are input. Edited text in between is lined up with the slots from the
+    /// start of the edit, skipping literals in their place
+    fn entered_chars(&self, text: &str) -> Vec<(usize, char)> {
+        let new = text.chars().collect::<Vec<_>>();
+        let old = self
+            .last_text
+            .clone()
+            .unwrap_or_else(|| self.formatted())
+            .chars()
+            .collect::<Vec<_>>();
+        let prefix = new.iter().zip(&old).take_while(|(a, b)| a == b).count();
+        let suffix = new
+            .iter()
+            .rev()
+            .zip(old.iter().rev())
+            .take(new.len().min(old.len()) - prefix)
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        let filled = self.raw.chars().count();
+        let is_filled_slot = |i: usize| {
+            !matches!(self.slots.get(i), None | Some(MaskChar::Literal(_)))
+                && self.slots[..i]
+                    .iter()
+                    .filter(|slot| !matches!(slot, MaskChar::Literal(_)))
+                    .count()
+                    < filled
+        };
+        new.iter()
+            .copied()
+            .enumerate()
+            .filter(|(i, c)| {
+                if *i < prefix {
+                    is_filled_slot(*i)
+                } else if *i >= new.len() - suffix {
+                    is_filled_slot(*i + old.len() - new.len())
+                } else {
+                    self.slots.get(*i) != Some(&MaskChar::Literal(*c))
+                }
+            })
+            .collect()
+    }
+
+    /// Re-derives the raw value from edited text.
+    ///
+    /// Returns the number of slots before the cursor
+    fn update_from_edit(&mut self, text: &str, cursor: usize, old_cursor: usize) -> usize {
+        let entered = self.entered_chars(text);
+        let mut before = self
+            .fit(entered.iter().filter(|(i, _)| *i < cursor).map(|(_, c)| *c))
+            .chars()
+            .count();
+        let raw = self.fit(entered.iter().map(|(_, c)| *c));
+
+        let deleted_separator = raw == self.raw
+            && text.chars().count() < self.slots.len()
+            && Some(text) != self.last_text.as_deref();
+        if deleted_separator {
+            // deleting a literal or placeholder deletes the neighbouring slot instead
+            let mut chars = raw.chars().collect::<Vec<_>>();
+            if cursor < old_cursor && before > 0 {
+                before -= 1;
+                chars.remove(before);
+            } else if cursor >= old_cursor && before < chars.len() {
+                chars.remove(before);
+            }
+            self.raw = self.fit(chars);
+        } else {
+            self.raw = raw;
+        }
+        before.min(self.raw.chars().count())
+    }
+
+    /// Moves a char index onto a slot, in the direction the cursor moved
+    fn snap_cursor(&self, cursor: usize, old_cursor: usize) -> usize {
+        let filled = self.raw.chars().count();
+        let last_stop = self.slot_position(filled);
+        let first_stop = self.slot_position(0);
+        let is_stop = |i: usize| (0..=filled).any(|n| self.slot_position(n) == i);
+
+        let cursor = cursor.clamp(first_stop, last_stop);
+        if is_stop(cursor) {
+            return cursor;
+        }
+        if cursor < old_cursor {
+            (first_stop..cursor)
+                .rev()
+                .find(|i| is_stop(*i))
+                .unwrap_or(first_stop)
+        } else {
+            (cursor..=last_stop)
+                .find(|i| is_stop(*i))
+                .unwrap_or(last_stop)
+        }
+    }
+}
+
+fn byte_index(text: &str, char_index: usize) -> usize {
+    text.char_indices()
+        .nth(char_index)
+        .map_or(text.len(), |(i, _)| i)
+}
+
+fn char_index(text: &str, byte_index: usize) -> usize {
+    text[..byte_index.min(text.len())].chars().count()
+}
+
+/// Remembers why the text was edited, unless it was formatting it
+fn remember_edit_cause(
+    trigger: Trigger<CosmicTextEdited>,
+    mut masks: Query<(&mut InputMask, &CosmicEditBuffer, Option<&CosmicEditor>)>,
+) {
+    let Ok((mut mask, buffer, editor)) = masks.get_mut(trigger.entity()) else {
+        return;
+    };
+    let text = editor.map_or_else(|| buffer.get_text(), |editor| editor.get_text());
+    if mask.last_text.as_ref() != Some(&text) {
+        mask.bypass_change_detection().edit_cause = Some(trigger.event().cause);
+    }
+}
+
+fn apply_input_masks(
+    mut masks: Query<
+        (Entity, &mut InputMask, EditorBuffer, &DefaultAttrs),
+        Or<(
+            Changed<InputMask>,
+            Changed<CosmicEditor>,
+            Changed<CosmicEditBuffer>,
+        )>,
+    >,
+    mut font_system: ResMut<CosmicFontSystem>,
+    mut evw_changed: EventWriter<CosmicTextChanged>,
+    mut commands: Commands,
+) {
+    for (entity, mut mask, mut buffer, attrs) in masks.iter_mut() {
+        let text = buffer.get_text();
+        if mask.last_text.is_none() && mask.raw.is_empty() {
+            // initial text of the buffer
+            let input = mask
+                .entered_chars(&text)
+                .into_iter()
+                .map(|(_, c)| c)
+                .collect::<String>();
+            mask.set_raw(&input);
+        }
+
+        let cursor = buffer
+            .editor()
+            .map(|editor| char_index(&text, editor.cursor().index));
+        let old_cursor = mask.last_cursor;
+        let edited =
+            mask.last_text.as_ref().is_some_and(|last| *last != text) && text != mask.formatted();
+        let new_cursor = if edited {
+            let mask = mask.bypass_change_detection();
+            let cursor = cursor.unwrap_or(text.chars().count());
+            let before = mask.update_from_edit(&text, cursor, old_cursor.unwrap_or(cursor));
+            Some(mask.slot_position(before))
+        } else {
+            cursor.map(|cursor| mask.snap_cursor(cursor, old_cursor.unwrap_or(cursor)))
+        };
+
+        let formatted = mask.formatted();
+        let mask = mask.bypass_change_detection();
+        let cause = mask.edit_cause.take().filter(|_| edited);
+        if text != formatted {
+            match cause.filter(|cause| !cause.is_programmatic()) {
+                // reported like the edit, so it isn't mistaken for a programmatic change
+                Some(cause) => {
+                    buffer.set_text_silently(&mut font_system, &formatted, attrs.as_attrs());
+                    evw_changed.send(CosmicTextChanged((entity, formatted.clone())));
+                    let deltas = TextDelta::replace_all(&text, &formatted);
+                    CosmicTextEdited::trigger(cause, deltas, entity, &mut commands);
+                }
+                None => {
+                    buffer.set_text(&mut font_system, &formatted, attrs.as_attrs());
+                }
+            }
+        }
+        mask.last_text = Some(formatted.clone());
+
+        if let (Some(editor), Some(new_cursor)) = (buffer.editor(), new_cursor) {
+            if edited {
+                editor.set_selection(Selection::None);
+            }
+            if edited || cursor != Some(new_cursor) {
+                editor.set_cursor(Cursor::new(0, byte_index(&formatted, new_cursor)));
+            }
+            mask.last_cursor = Some(new_cursor);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_and_edits() {
+        let mut mask = InputMask::new("(###) ###-####");
+        mask.last_text = Some(mask.formatted());
+        assert_eq!(mask.formatted(), "(___) ___-____");
+
+        // pasting a formatted number at the start
+        let before = mask.update_from_edit("(555) 123-4567(___) ___-____", 14, 0);
+        assert_eq!(mask.raw(), "5551234567");
+        assert_eq!(before, 10);
+        assert!(mask.is_complete());
+        mask.last_text = Some(mask.formatted());
+
+        // backspace over the space after `)`
+        mask.set_raw("1234");
+        mask.last_text = Some(mask.formatted());
+        assert_eq!(mask.formatted(), "(123) 4__-____");
+        let before = mask.update_from_edit("(123)4__-____", 5, 6);
+        assert_eq!(mask.raw(), "124");
+        assert_eq!(mask.slot_position(before), 3);
+
+        // letters don't fit digit slots
+        mask.set_raw("12a3");
+        assert_eq!(mask.raw(), "123");
+
+        // separators and the placeholder can be typed into any slots
+        let mut mask = InputMask::new("**/**");
+        mask.last_text = Some(mask.formatted());
+        mask.update_from_edit("/__/__", 1, 0);
+        mask.last_text = Some(mask.formatted());
+        assert_eq!(mask.formatted(), "/_/__");
+        mask.update_from_edit("/__/__", 2, 1);
+        assert_eq!(mask.raw(), "/_");
+    }
+
+    #[test]
+    fn cursor_skips_literals() {
+        let mut mask = InputMask::new("XXXX-XXXX");
+        mask.set_raw("ABCDE");
+        assert_eq!(mask.formatted(), "ABCD-E___");
+        // moving left over `-`
+        assert_eq!(mask.snap_cursor(4, 5), 3);
+        // moving right past the last filled slot
+        assert_eq!(mask.snap_cursor(8, 6), 6);
+    }
+}
@@ -107,8 +107,22 @@ pub mod render_implementations;
 pub mod utils;
 
 // extra modules
+pub mod bind_text;
+pub mod completion;
+pub mod context_menu;
+pub mod find;
+pub mod highlights;
+pub mod input_filter;
+pub mod input_mask;
+pub mod line_numbers;
+pub mod markdown;
+pub mod numeric_input;
 pub mod password;
+pub mod persistence;
 pub mod placeholder;
+pub mod rich_text;
+pub mod spellcheck;
+pub mod syntax_highlight;
 pub mod user_select;
 
 #[cfg(feature = "internal-debugging")]
@@ -0,0 +1,282 @@
+//! Line numbers drawn in a gutter to the left of the text, see [`LineNumbers`]
+
+use cosmic_text::{Attrs, Buffer, Cursor, Edit, FontSystem, Selection, Shaping};
+
+use crate::prelude::*;
+
+pub(crate) struct LineNumbersPlugin;
+
+impl Plugin for LineNumbersPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<LineNumbers>();
+    }
+}
+
+/// What the numbers in the gutter count
+#[derive(Reflect, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineNumbering {
+    /// Lines separated by `\n`, numbered on their first wrapped line
+    #[default]
+    Logical,
+    /// Every line as it is shown, counting each wrapped line
+    Visual,
+}
+
+/// Shows line numbers in a gutter to the left of the text.
+///
+/// The gutter fits the number of digits needed, and clicking in it selects the line.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_cosmic_edit::prelude::*;
+/// use bevy_cosmic_edit::line_numbers::LineNumbers;
+///
+/// # fn setup(mut commands: Commands) {
+/// commands.spawn((TextEdit, LineNumbers::default()));
+/// # }
+/// ```
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
+#[require(Gutter)]
+pub struct LineNumbers {
+    pub numbering: LineNumbering,
+    pub color: Color,
+    /// Color of the cursor's line number, if different
+    pub current_line_color: Option<Color>,
+    pub background: Color,
+    /// Space on both sides of the numbers
+    pub padding: f32,
+    /// Digits to make room for at least, so the gutter resizes less often
+    pub min_digits: usize,
+}
+
+impl Default for LineNumbers {
+    fn default() -> Self {
+        Self {
+            numbering: LineNumbering::Logical,
+            color: Color::srgb(0.5, 0.5, 0.5),
+            current_line_color: None,
+            background: Color::NONE,
+            padding: 8.,
+            min_digits: 2,
+        }
+    }
+}
+
+/// Width of the gutter when last rendered
+#[derive(Component, Default, Debug)]
+pub(crate) struct Gutter {
+    pub(crate) width: f32,
+    /// Number of visual lines, to size [`LineNumbering::Visual`] gutters
+    visual_lines: usize,
+}
+
+/// Lays out `text` in `scratch`, returning its width and baseline
+fn shape_number(
+    scratch: &mut Buffer,
+    font_system: &mut FontSystem,
+    text: &str,
+    attrs: Attrs,
+) -> (f32, f32) {
+    scratch.set_text(font_system, text, attrs, Shaping::Advanced);
+    scratch
+        .layout_runs()
+        .next()
+        .map_or((0., 0.), |run| (run.line_w, run.line_y))
+}
+
+impl LineNumbers {
+    /// Updates the width of the gutter for `buffer`, before it is laid out
+    pub(crate) fn size_gutter(
+        &self,
+        gutter: &mut Gutter,
+        buffer: &Buffer,
+        scratch: &mut Buffer,
+        font_system: &mut FontSystem,
+        attrs: Attrs,
+    ) {
+        let lines = match self.numbering {
+            LineNumbering::Logical => buffer.lines.len(),
+            LineNumbering::Visual => gutter.visual_lines.max(buffer.lines.len()),
+        };
+        let digits = lines.to_string().len().max(self.min_digits);
+        scratch.set_metrics(font_system, buffer.metrics());
+        scratch.set_size(font_system, None, None);
+        let (width, _) = shape_number(scratch, font_system, &"0".repeat(digits), attrs);
+        gutter.width = (width + self.padding * 2.).ceil();
+    }
+
+    /// The line index, number and baseline of each visible line that is numbered.
+    ///
+    /// Lines below the visible ones aren't laid out just to count their wrapped lines, so
+    /// the gutter is only sized for those once they have been shown
+    fn visible_numbers(
+        &self,
+        gutter: &mut Gutter,
+        buffer: &mut Buffer,
+        font_system: &mut FontSystem,
+    ) -> Vec<(usize, usize, f32)> {
+        // line, start of the first glyph and baseline of each visible run
+        let runs = buffer
+            .layout_runs()
+            .map(|run| {
+                let start = run.glyphs.first().map_or(0, |glyph| glyph.start);
+                (run.line_i, start, run.line_y)
+            })
+            .collect::<Vec<_>>();
+
+        if self.numbering == LineNumbering::Logical {
+            return runs
+                .into_iter()
+                .filter(|(_, start, _)| *start == 0)
+                .map(|(line_i, _, line_y)| (line_i, line_i + 1, line_y))
+                .collect();
+        }
+
+        let last_visible = runs.last().map_or(0, |(line_i, ..)| *line_i);
+        let mut visual_before = Vec::with_capacity(last_visible + 1);
+        let mut visual_lines = 0;
+        for line_i in 0..buffer.lines.len() {
+            let wrapped = match line_i <= last_visible {
+                true => {
+                    visual_before.push(visual_lines);
+                    buffer
+                        .line_layout(font_system, line_i)
+                        .map(|layout| layout.len())
+                }
+                false => buffer.lines[line_i].layout_opt().as_ref().map(Vec::len),
+            };
+            visual_lines += wrapped.map_or(1, |wrapped| wrapped.max(1));
+        }
+        gutter.visual_lines = visual_lines;
+
+        runs.into_iter()
+            .map(|(line_i, start, line_y)| {
+                // already laid out above
+                let index_in_line = buffer
+                    .line_layout(font_system, line_i)
+                    .and_then(|layout| {
+                        layout.iter().position(|layout_line| {
+                            layout_line.glyphs.first().map_or(0, |glyph| glyph.start) == start
+                        })
+                    })
+                    .unwrap_or(0);
+                (line_i, visual_before[line_i] + index_in_line + 1, line_y)
+            })
+            .collect()
+    }
+
+    /// Draws the numbers of the visible lines, calling `draw` with x relative to the
+    /// left of the widget and y in buffer coordinates
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn draw(
+        &self,
+        gutter: &mut Gutter,
+        buffer: &mut Buffer,
+        cursor: Option<Cursor>,
+        scratch: &mut Buffer,
+        font_system: &mut FontSystem,
+        swash_cache: &mut cosmic_text::SwashCache,
+        attrs: Attrs,
+        mut draw: impl FnMut(i32, i32, u32, u32, cosmic_text::Color),
+    ) {
+        for (line_i, number, line_y) in self.visible_numbers(gutter, buffer, font_system) {
+            let color = match self.current_line_color {
+                Some(color) if cursor.is_some_and(|cursor| cursor.line == line_i) => color,
+                _ => self.color,
+            };
+            let (width, baseline) = shape_number(scratch, font_system, &number.to_string(), attrs);
+            let x = (gutter.width - self.padding - width) as i32;
+            let y = (line_y - baseline) as i32;
+            scratch.draw(
+                font_system,
+                swash_cache,
+                color.to_cosmic(),
+                |glyph_x, glyph_y, w, h, color| draw(x + glyph_x, y + glyph_y, w, h, color),
+            );
+        }
+    }
+}
+
+/// Selects the whole line at `y`, including its line break
+pub(crate) fn select_line_at<'a>(editor: &mut impl Edit<'a>, y: f32) {
+    let Some(cursor) = editor.with_buffer(|buffer| buffer.hit(0., y)) else {
+        return;
+    };
+    let line = cursor.line;
+    let end = editor.with_buffer(|buffer| match buffer.lines.get(line + 1) {
+        Some(_) => Cursor::new(line + 1, 0),
+        None => Cursor::new(line, buffer.lines[line].text().len()),
+    });
+    editor.set_selection(Selection::Normal(Cursor::new(line, 0)));
+    editor.set_cursor(end);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gutter_fits_digits() {
+        let mut font_system = test_font_system();
+        let metrics = cosmic_text::Metrics::new(20., 20.);
+        let mut scratch = Buffer::new(&mut font_system, metrics);
+        let mut buffer = Buffer::new(&mut font_system, metrics);
+        let numbers = LineNumbers {
+            padding: 0.,
+            min_digits: 1,
+            ..default()
+        };
+        let mut gutter = Gutter::default();
+        let mut width_for = |buffer: &mut Buffer, lines: usize| {
+            let text = vec!["x"; lines].join("\n");
+            buffer.set_text(&mut font_system, &text, Attrs::new(), Shaping::Advanced);
+            let font_system = &mut font_system;
+            numbers.size_gutter(&mut gutter, buffer, &mut scratch, font_system, Attrs::new());
+            gutter.width
+        };
+
+        let one_digit = width_for(&mut buffer, 9);
+        let two_digits = width_for(&mut buffer, 10);
+        assert!(one_digit > 0.);
+        assert_eq!(two_digits, (one_digit * 2.).ceil());
+    }
+
+    #[test]
+    fn numbers_visible_wrapped_lines() {
+        let mut font_system = test_font_system();
+        let metrics = cosmic_text::Metrics::new(20., 20.);
+        let mut buffer = Buffer::new(&mut font_system, metrics);
+        // narrow enough to wrap every line, with room for a few visual lines
+        buffer.set_size(&mut font_system, Some(60.), Some(60.));
+        let text = vec!["one two three"; 20].join("\n");
+        buffer.set_text(&mut font_system, &text, Attrs::new(), Shaping::Advanced);
+        buffer.shape_until_scroll(&mut font_system, false);
+        let numbers = LineNumbers {
+            numbering: LineNumbering::Visual,
+            ..default()
+        };
+        let mut gutter = Gutter::default();
+        let mut visible = |buffer: &mut Buffer, font_system: &mut FontSystem| {
+            numbers
+                .visible_numbers(&mut gutter, buffer, font_system)
+                .into_iter()
+                .map(|(line_i, number, _)| (line_i, number))
+                .collect::<Vec<_>>()
+        };
+        let per_line = buffer.line_layout(&mut font_system, 0).unwrap().len();
+        assert!(per_line > 1);
+
+        assert_eq!(
+            visible(&mut buffer, &mut font_system)[..2],
+            [(0, 1), (0, 2)]
+        );
+        assert!(buffer.lines[19].layout_opt().is_none());
+
+        buffer.set_scroll(cosmic_text::Scroll::new(10, 0., 0.));
+        buffer.shape_until_scroll(&mut font_system, false);
+        assert_eq!(
+            visible(&mut buffer, &mut font_system)[0],
+            (10, 10 * per_line + 1)
+        );
+    }
+}
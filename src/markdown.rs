@@ -0,0 +1,629 @@
+//! Converting between a subset of Markdown and rich text, and styling Markdown while it is
+//! edited with [`MarkdownStyling`]
+//!
+//! Supports `**bold**`, `*italic*`, `` `code` ``, `# headings`, `[links](url)` and `- lists`.
+//! Lines are kept as they are rather than joined into paragraphs, and list bullets are
+//! shown as `•`.
+//!
+//! ```
+//! # use bevy::prelude::*;
+//! # use bevy_cosmic_edit::prelude::*;
+//! use bevy_cosmic_edit::cosmic_text::{Attrs, AttrsOwned, Metrics};
+//! use bevy_cosmic_edit::markdown::{MarkdownLinks, MarkdownStyle};
+//!
+//! fn setup(
+//!     mut commands: Commands,
+//!     mut font_system: ResMut<CosmicFontSystem>,
+//!     mut links: ResMut<MarkdownLinks>,
+//! ) {
+//!     let buffer = CosmicEditBuffer::new(&mut font_system, Metrics::new(16., 20.)).with_markdown(
+//!         &mut font_system,
+//!         "# Notes\n- **Buy** milk",
+//!         &MarkdownStyle::default(),
+//!         &mut links,
+//!         Attrs::new(),
+//!     );
+//!     commands.spawn((TextEdit, buffer));
+//! }
+//!
+//! fn save(buffers: Query<&CosmicEditBuffer>, links: Res<MarkdownLinks>) {
+//!     for buffer in buffers.iter() {
+//!         let default_attrs = AttrsOwned::new(Attrs::new());
+//!         let markdown = buffer.to_markdown(&MarkdownStyle::default(), &links, default_attrs);
+//!         info!("{markdown}");
+//!     }
+//! }
+//! ```
+
+use std::ops::Range;
+
+use cosmic_text::{
+    Attrs, AttrsList, AttrsOwned, Color, FamilyOwned, FontSystem, Metrics, Style, Weight,
+};
+
+use crate::{input::CosmicTextChanged, placeholder::Placeholder, prelude::*};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.init_resource::<MarkdownLinks>().add_systems(
+        PostUpdate,
+        style_markdown
+            .after(crate::input::delta::send_programmatic_edits)
+            .before(crate::rich_text::send_attrs_changed),
+    );
+}
+
+/// The formatting of a span of Markdown
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MarkdownFormat {
+    pub bold: bool,
+    pub italic: bool,
+    pub code: bool,
+    /// Heading level, from 1 to 6
+    pub heading: Option<u8>,
+    pub link: Option<String>,
+}
+
+/// URLs of links, shared by all buffers.
+///
+/// Links are stored in the `metadata` of their attributes, as an id from
+/// [`MarkdownLinks::intern`].
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MarkdownLinks(Vec<String>);
+
+impl MarkdownLinks {
+    /// The id of `url`, used as `metadata`
+    pub fn intern(&mut self, url: &str) -> usize {
+        let index = match self.0.iter().position(|link| link == url) {
+            Some(index) => index,
+            None => {
+                self.0.push(url.to_owned());
+                self.0.len() - 1
+            }
+        };
+        index + 1
+    }
+
+    /// The URL of a link's `metadata`
+    pub fn get(&self, metadata: usize) -> Option<&str> {
+        let index = metadata.checked_sub(1)?;
+        self.0.get(index).map(String::as_str)
+    }
+}
+
+/// How Markdown is shown as rich text
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkdownStyle {
+    pub code_family: FamilyOwned,
+    pub code_color: Option<Color>,
+    pub link_color: Color,
+    /// Metrics of headings, from level 1 to 6
+    pub heading_metrics: [Metrics; 6],
+}
+
+impl Default for MarkdownStyle {
+    fn default() -> Self {
+        Self {
+            code_family: FamilyOwned::Monospace,
+            code_color: None,
+            link_color: Color::rgb(0x3b, 0x82, 0xf6),
+            heading_metrics: [
+                Metrics::new(32., 40.),
+                Metrics::new(28., 36.),
+                Metrics::new(24., 30.),
+                Metrics::new(20., 26.),
+                Metrics::new(18., 24.),
+                Metrics::new(16., 22.),
+            ],
+        }
+    }
+}
+
+impl MarkdownStyle {
+    /// `base` with `format` applied, links need their `metadata` set separately
+    pub fn attrs<'a>(&'a self, format: &MarkdownFormat, base: Attrs<'a>) -> Attrs<'a> {
+        let mut attrs = base;
+        if format.bold {
+            attrs = attrs.weight(Weight::BOLD);
+        }
+        if format.italic {
+            attrs = attrs.style(Style::Italic);
+        }
+        if format.code {
+            attrs = attrs.family(self.code_family.as_family());
+            if let Some(color) = self.code_color {
+                attrs = attrs.color(color);
+            }
+        }
+        if let Some(level) = format.heading {
+            let level = level.clamp(1, 6) as usize;
+            attrs = attrs
+                .weight(Weight::BOLD)
+                .metrics(self.heading_metrics[level - 1]);
+        }
+        if format.link.is_some() {
+            attrs = attrs.color(self.link_color);
+        }
+        attrs
+    }
+
+    /// The formatting `attrs` look like.
+    ///
+    /// Text is only code if its family differs from the `default` one, so a buffer
+    /// that is monospace throughout has no code spans
+    pub fn format(&self, attrs: Attrs, default: Attrs, links: &MarkdownLinks) -> MarkdownFormat {
+        let heading = attrs.metrics_opt.and_then(|metrics| {
+            let metrics = Metrics::from(metrics);
+            let index = self.heading_metrics.iter().position(|m| *m == metrics)?;
+            Some(index as u8 + 1)
+        });
+        MarkdownFormat {
+            bold: attrs.weight >= Weight::SEMIBOLD,
+            italic: attrs.style != Style::Normal,
+            code: attrs.family == self.code_family.as_family() && attrs.family != default.family,
+            heading,
+            link: links.get(attrs.metadata).map(str::to_owned),
+        }
+    }
+}
+
+/// Appends a span, merging it with the last one if it has the same formatting
+fn push_span(spans: &mut Vec<(String, MarkdownFormat)>, text: &str, format: &MarkdownFormat) {
+    if text.is_empty() {
+        return;
+    }
+    match spans.last_mut() {
+        Some((last, last_format)) if last_format == format => last.push_str(text),
+        _ => spans.push((text.to_owned(), format.clone())),
+    }
+}
+
+/// What part of a line of Markdown a range is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Part {
+    Text,
+    /// Formatting like `**` or `# `
+    Marker,
+    /// `- ` of a list item
+    Bullet,
+}
+
+type PartSink<'a> = dyn FnMut(Range<usize>, &MarkdownFormat, Part) + 'a;
+
+/// Splits a line of Markdown into byte ranges and their formatting
+fn parse_line(line: &str, sink: &mut PartSink) {
+    let hashes = line.len() - line.trim_start_matches('#').len();
+    if (1..=6).contains(&hashes) && line[hashes..].starts_with(' ') {
+        let format = MarkdownFormat {
+            heading: Some(hashes as u8),
+            ..default()
+        };
+        sink(0..hashes + 1, &format, Part::Marker);
+        parse_inline(line, hashes + 1..line.len(), &format, sink);
+        return;
+    }
+
+    let indent = line.len() - line.trim_start().len();
+    let format = MarkdownFormat::default();
+    if ["- ", "* ", "+ "]
+        .iter()
+        .any(|bullet| line[indent..].starts_with(bullet))
+    {
+        sink(0..indent, &format, Part::Text);
+        sink(indent..indent + 2, &format, Part::Bullet);
+        parse_inline(line, indent + 2..line.len(), &format, sink);
+        return;
+    }
+
+    parse_inline(line, 0..line.len(), &format, sink);
+}
+
+fn parse_inline(line: &str, range: Range<usize>, format: &MarkdownFormat, sink: &mut PartSink) {
+    let text = &line[range.clone()];
+    let at = |i: usize| range.start + i;
+    let mut format = format.clone();
+    let mut i = 0;
+    while let Some(c) = text[i..].chars().next() {
+        let rest = &text[i..];
+        let after = &text[i + c.len_utf8()..];
+
+        if c == '\\' {
+            if let Some(escaped) = after.chars().next().filter(char::is_ascii_punctuation) {
+                sink(at(i)..at(i + 1), &format, Part::Marker);
+                sink(
+                    at(i + 1)..at(i + 1 + escaped.len_utf8()),
+                    &format,
+                    Part::Text,
+                );
+                i += 1 + escaped.len_utf8();
+                continue;
+            }
+        }
+
+        if c == '`' {
+            if let Some(end) = after.find('`') {
+                let code = MarkdownFormat {
+                    code: true,
+                    ..format.clone()
+                };
+                sink(at(i)..at(i + 1), &code, Part::Marker);
+                sink(at(i + 1)..at(i + 1 + end), &code, Part::Text);
+                sink(at(i + 1 + end)..at(i + 2 + end), &code, Part::Marker);
+                i += 1 + end + 1;
+                continue;
+            }
+        }
+
+        if c == '[' {
+            let link = after.find("](").and_then(|close| {
+                let url_len = after[close + 2..].find(')')?;
+                Some((close, url_len))
+            });
+            if let Some((close, url_len)) = link {
+                let link = MarkdownFormat {
+                    link: Some(after[close + 2..close + 2 + url_len].to_owned()),
+                    ..format.clone()
+                };
+                let end = i + 1 + close + 2 + url_len + 1;
+                sink(at(i)..at(i + 1), &link, Part::Marker);
+                parse_inline(line, at(i + 1)..at(i + 1 + close), &link, sink);
+                sink(at(i + 1 + close)..at(end), &link, Part::Marker);
+                i = end;
+                continue;
+            }
+        }
+
+        if c == '*' || c == '_' {
+            let double = rest.starts_with(if c == '*' { "**" } else { "__" });
+            let marker = &rest[..if double { 2 } else { 1 }];
+            let on = if double { format.bold } else { format.italic };
+            let intraword = c == '_'
+                && text[..i]
+                    .chars()
+                    .next_back()
+                    .is_some_and(char::is_alphanumeric);
+            if on || (!intraword && text[i + marker.len()..].contains(marker)) {
+                let mut marker_format = format.clone();
+                match double {
+                    true => marker_format.bold = true,
+                    false => marker_format.italic = true,
+                }
+                sink(at(i)..at(i + marker.len()), &marker_format, Part::Marker);
+                match double {
+                    true => format.bold = !on,
+                    false => format.italic = !on,
+                }
+                i += marker.len();
+                continue;
+            }
+        }
+
+        sink(at(i)..at(i + c.len_utf8()), &format, Part::Text);
+        i += c.len_utf8();
+    }
+}
+
+/// Parses Markdown into spans of text, with lines separated by `\n`
+pub fn parse(markdown: &str) -> Vec<(String, MarkdownFormat)> {
+    let mut spans = Vec::new();
+    for (line_i, line) in markdown.lines().enumerate() {
+        if line_i > 0 {
+            push_span(&mut spans, "\n", &MarkdownFormat::default());
+        }
+        parse_line(line, &mut |range, format, part| match part {
+            Part::Text => push_span(&mut spans, &line[range], format),
+            Part::Bullet => push_span(&mut spans, "• ", format),
+            Part::Marker => {}
+        });
+    }
+    spans
+}
+
+/// Backslash escapes characters that would otherwise be read as formatting
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut prev = None;
+    for c in text.chars() {
+        let intraword = c == '_' && prev.is_some_and(char::is_alphanumeric);
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']') && !intraword {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+        prev = Some(c);
+    }
+    escaped
+}
+
+/// Writes lines of spans as Markdown
+pub fn write(lines: &[Vec<(String, MarkdownFormat)>]) -> String {
+    let mut markdown = Vec::with_capacity(lines.len());
+    for line in lines {
+        let heading = line
+            .iter()
+            .filter(|(text, _)| !text.is_empty())
+            .map(|(_, format)| format.heading)
+            .reduce(|a, b| if a == b { a } else { None })
+            .flatten();
+
+        let mut out = String::new();
+        if let Some(level) = heading {
+            out.push_str(&"#".repeat(level as usize));
+            out.push(' ');
+        }
+
+        let mut spans: Vec<(String, MarkdownFormat)> = Vec::new();
+        for (text, format) in line {
+            let format = MarkdownFormat {
+                bold: format.bold && heading.is_none(),
+                heading: None,
+                ..format.clone()
+            };
+            push_span(&mut spans, text, &format);
+        }
+
+        let mut open_link: Option<&str> = None;
+        for (span_i, (text, format)) in spans.iter().enumerate() {
+            if open_link.is_some() && open_link != format.link.as_deref() {
+                out.push_str(&format!("]({})", open_link.take().unwrap()));
+            }
+            if open_link.is_none() {
+                if let Some(link) = &format.link {
+                    out.push('[');
+                    open_link = Some(link);
+                }
+            }
+
+            let mut text = match format.code {
+                true => format!("`{text}`"),
+                false => escape(text),
+            };
+            if span_i == 0 && heading.is_none() && !format.code {
+                let content = text.trim_start();
+                let indent = text.len() - content.len();
+                if let Some(item) = content.strip_prefix("• ") {
+                    text = format!("{}- {item}", &text[..indent]);
+                } else if content.starts_with('#')
+                    || content.starts_with("- ")
+                    || content.starts_with("+ ")
+                {
+                    text.insert(indent, '\\');
+                }
+            }
+            if format.italic {
+                text = format!("*{text}*");
+            }
+            if format.bold {
+                text = format!("**{text}**");
+            }
+            out.push_str(&text);
+        }
+        if let Some(link) = open_link {
+            out.push_str(&format!("]({link})"));
+        }
+        markdown.push(out);
+    }
+    markdown.join("\n")
+}
+
+/// Styles the text as Markdown while it is edited, with the formatting markers dimmed
+/// rather than hidden.
+///
+/// Each line's attributes are derived from its text whenever the text changes, replacing
+/// any other attributes, and the cursor and selection stay where they are.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_cosmic_edit::prelude::*;
+/// use bevy_cosmic_edit::markdown::MarkdownStyling;
+///
+/// # fn setup(mut commands: Commands) {
+/// commands.spawn((TextEdit, MarkdownStyling::default()));
+/// # }
+/// ```
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct MarkdownStyling {
+    pub style: MarkdownStyle,
+    /// Color of markers like `**` and `# `
+    pub marker_color: Color,
+}
+
+impl Default for MarkdownStyling {
+    fn default() -> Self {
+        Self {
+            style: MarkdownStyle::default(),
+            marker_color: Color::rgba(0x80, 0x80, 0x80, 0xff),
+        }
+    }
+}
+
+impl MarkdownStyling {
+    /// Attributes of a line of Markdown
+    pub fn line_attrs(&self, line: &str, defaults: Attrs) -> AttrsList {
+        let mut attrs_list = AttrsList::new(defaults);
+        parse_line(line, &mut |range, format, part| {
+            let mut attrs = self.style.attrs(format, defaults);
+            if part != Part::Text {
+                attrs = attrs.color(self.marker_color);
+            }
+            if attrs != defaults {
+                attrs_list.add_span(range, attrs);
+            }
+        });
+        attrs_list
+    }
+}
+
+fn style_markdown(
+    mut buffers: Query<(
+        Entity,
+        EditorBuffer,
+        Ref<MarkdownStyling>,
+        Option<&Placeholder>,
+    )>,
+    mut evr_changed: EventReader<CosmicTextChanged>,
+) {
+    let changed = evr_changed
+        .read()
+        .map(|CosmicTextChanged((entity, _))| *entity)
+        .collect::<Vec<_>>();
+    for (entity, mut buffer, styling, placeholder) in buffers.iter_mut() {
+        if !styling.is_changed() && !changed.contains(&entity) {
+            continue;
+        }
+        if placeholder.is_some_and(Placeholder::is_active) {
+            continue;
+        }
+
+        let restyled = buffer.with_buffer_mut(|buffer| {
+            let mut restyled = false;
+            for line in buffer.lines.iter_mut() {
+                let defaults = AttrsOwned::new(line.attrs_list().defaults());
+                let attrs_list = styling.line_attrs(line.text(), defaults.as_attrs());
+                restyled |= line.set_attrs_list(attrs_list);
+            }
+            if restyled {
+                buffer.set_redraw(true);
+            }
+            restyled
+        });
+        if restyled {
+            buffer.mark_restyled();
+        }
+    }
+}
+
+impl CosmicEditBuffer {
+    /// Add Markdown to a newly created [`CosmicEditBuffer`], see [`crate::markdown`]
+    pub fn with_markdown(
+        self,
+        font_system: &mut FontSystem,
+        markdown: &str,
+        style: &MarkdownStyle,
+        links: &mut MarkdownLinks,
+        attrs: Attrs,
+    ) -> Self {
+        let spans = parse(markdown);
+        let span_attrs = spans
+            .iter()
+            .map(|(_, format)| {
+                let attrs = style.attrs(format, attrs);
+                match &format.link {
+                    Some(link) => attrs.metadata(links.intern(link)),
+                    None => attrs,
+                }
+            })
+            .collect::<Vec<_>>();
+        self.with_rich_text(
+            font_system,
+            spans.iter().map(|(text, _)| text.as_str()).zip(span_attrs),
+            attrs,
+        )
+    }
+
+    /// The text as Markdown, see [`crate::markdown`]
+    pub fn to_markdown(
+        &self,
+        style: &MarkdownStyle,
+        links: &MarkdownLinks,
+        default_attrs: AttrsOwned,
+    ) -> String {
+        let lines = self
+            .get_text_spans(default_attrs.clone())
+            .into_iter()
+            .map(|line| {
+                line.into_iter()
+                    .map(|(text, attrs)| {
+                        let format =
+                            style.format(attrs.as_attrs(), default_attrs.as_attrs(), links);
+                        (text, format)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        write(&lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_markdown() {
+        let mut db = test_font_db();
+        db.set_monospace_family("Fira Mono");
+        // the test font has no italic face
+        let italic = cosmic_text::fontdb::FaceInfo {
+            style: Style::Italic,
+            ..db.faces().next().unwrap().clone()
+        };
+        db.push_face_info(italic);
+        let mut font_system = cosmic_text::FontSystem::new_with_locale_and_db("en-US".into(), db);
+        let style = MarkdownStyle::default();
+        let mut links = MarkdownLinks::default();
+
+        let markdown = "# Notes about `code`\n\
+            Some **bold**, *italic* and ***both***\n\
+            - a [**bold** link](https://bevyengine.org)\n  \
+            - nested \\*not italic\\*\n\
+            1. snake_case";
+        let buffer = CosmicEditBuffer::new(&mut font_system, Metrics::new(16., 20.)).with_markdown(
+            &mut font_system,
+            markdown,
+            &style,
+            &mut links,
+            Attrs::new(),
+        );
+
+        assert_eq!(
+            buffer.get_text(),
+            "Notes about code\nSome bold, italic and both\n• a bold link\n  • nested *not italic*\n1. snake_case"
+        );
+        let default_attrs = AttrsOwned::new(Attrs::new());
+        assert_eq!(buffer.to_markdown(&style, &links, default_attrs), markdown);
+
+        // monospace throughout isn't code
+        let mono = Attrs::new().family(cosmic_text::Family::Monospace);
+        let buffer = CosmicEditBuffer::new(&mut font_system, Metrics::new(16., 20.)).with_markdown(
+            &mut font_system,
+            "a **b**",
+            &style,
+            &mut links,
+            mono,
+        );
+        let default_attrs = AttrsOwned::new(mono);
+        assert_eq!(buffer.to_markdown(&style, &links, default_attrs), "a **b**");
+    }
+
+    #[test]
+    fn styles_while_editing() {
+        use bevy::ecs::system::RunSystemOnce;
+        use cosmic_text::{Cursor, Edit};
+
+        let mut font_system = test_font_system();
+        let buffer = CosmicEditBuffer::new(&mut font_system, Metrics::new(16., 20.)).with_text(
+            &mut font_system,
+            "a **b**",
+            Attrs::new(),
+        );
+        let mut editor = CosmicEditor::clone_from_buffer(&buffer);
+        editor.set_cursor(Cursor::new(0, 4));
+
+        let mut world = World::new();
+        world.init_resource::<Assets<Image>>();
+        world.init_resource::<Events<CosmicTextChanged>>();
+        let styling = MarkdownStyling::default();
+        let marker_color = styling.marker_color;
+        let entity = world.spawn((buffer, editor, styling)).id();
+        world.run_system_once(style_markdown).unwrap();
+
+        let editor = world.get::<CosmicEditor>(entity).unwrap();
+        assert_eq!(editor.cursor(), Cursor::new(0, 4));
+        editor.with_buffer(|buffer| {
+            let attrs_list = buffer.lines[0].attrs_list();
+            assert_eq!(attrs_list.get_span(0).weight, Weight::NORMAL);
+            assert_eq!(attrs_list.get_span(2).color_opt, Some(marker_color));
+            assert_eq!(attrs_list.get_span(4).weight, Weight::BOLD);
+            assert_eq!(attrs_list.get_span(4).color_opt, None);
+        });
+    }
+}
@@ -0,0 +1,407 @@
+//! Number fields with clamping, stepping and scrubbing
+//!
+//! See [`NumericInput`]
+
+use std::{fmt::Display, marker::PhantomData, str::FromStr};
+
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use cosmic_text::{Action, Motion, Selection};
+use num_traits::{Bounded, Num};
+
+use crate::{
+    cosmic_edit::DefaultAttrs,
+    input::{InputSet, InputState},
+    prelude::*,
+};
+
+/// Numbers that can be used with [`NumericInput`]
+pub trait NumericValue:
+    Num + Bounded + PartialOrd + Copy + FromStr + Display + Send + Sync + 'static
+{
+}
+
+impl<T> NumericValue for T where
+    T: Num + Bounded + PartialOrd + Copy + FromStr + Display + Send + Sync + 'static
+{
+}
+
+pub(crate) fn plugin(app: &mut App) {
+    app.add_plugins((
+        NumericInputPlugin::<f32>::default(),
+        NumericInputPlugin::<f64>::default(),
+        NumericInputPlugin::<i32>::default(),
+        NumericInputPlugin::<i64>::default(),
+        NumericInputPlugin::<u32>::default(),
+        NumericInputPlugin::<u64>::default(),
+        NumericInputPlugin::<usize>::default(),
+    ));
+}
+
+/// Adds support for [`NumericInput<T>`].
+///
+/// Already added by [`CosmicEditPlugin`] for `f32`, `f64`, `i32`, `i64`, `u32`, `u64`
+/// and `usize`, add it yourself for other types.
+pub struct NumericInputPlugin<T>(PhantomData<T>);
+
+impl<T> Default for NumericInputPlugin<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: NumericValue> Plugin for NumericInputPlugin<T> {
+    fn build(&self, app: &mut App) {
+        app.add_event::<NumericValueChanged<T>>().add_systems(
+            Update,
+            (read_typed_values::<T>, step_values::<T>, write_values::<T>)
+                .chain()
+                .after(InputSet),
+        );
+    }
+}
+
+/// Turns a [`CosmicEditBuffer`] into a number field.
+///
+/// Typed text is parsed and clamped to `min..=max`. \[ArrowUp\]/\[ArrowDown\] and the mouse
+/// wheel change the value by `step`, once per line scrolled, as does dragging with \[Ctrl\] held if
+/// [`NumericInput::with_scrub`] is used. Listen for [`NumericValueChanged<T>`]
+/// rather than [`CosmicTextChanged`](crate::input::CosmicTextChanged).
+///
+/// While focussed, text that doesn't parse is left alone. Once unfocussed,
+/// the text is replaced with the clamped value.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_cosmic_edit::prelude::*;
+/// use bevy_cosmic_edit::numeric_input::{NumericInput, NumericValueChanged};
+///
+/// # fn setup(mut commands: Commands) {
+/// commands.spawn((
+///     TextEdit,
+///     NumericInput::new(0.5f32)
+///         .with_range(0., 1.)
+///         .with_step(0.05)
+///         .with_precision(2)
+///         .with_scrub(4.),
+/// ));
+/// # }
+///
+/// fn on_volume_changed(mut events: EventReader<NumericValueChanged<f32>>) {
+///     for event in events.read() {
+///         info!("Volume is now {}", event.value);
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Clone)]
+#[require(NumericField)]
+pub struct NumericInput<T: NumericValue> {
+    pub value: T,
+    pub min: T,
+    pub max: T,
+    pub step: T,
+    /// Decimal places shown, only used for floats
+    pub precision: Option<usize>,
+    /// Pixels to drag for each step while \[Ctrl\] is held, `None` disables scrubbing
+    pub scrub_pixels_per_step: Option<f32>,
+    scrub_remainder: f32,
+    /// Lines scrolled while hovered that didn't make a whole step yet
+    wheel_remainder: f32,
+    /// Value written into the buffer the last time
+    displayed: Option<T>,
+    /// Text read from the buffer the last time
+    last_text: Option<String>,
+}
+
+impl<T: NumericValue> NumericInput<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            min: T::min_value(),
+            max: T::max_value(),
+            step: T::one(),
+            precision: None,
+            scrub_pixels_per_step: None,
+            scrub_remainder: 0.,
+            wheel_remainder: 0.,
+            displayed: None,
+            last_text: None,
+        }
+    }
+
+    pub fn with_range(mut self, min: T, max: T) -> Self {
+        self.min = min;
+        self.max = max;
+        self.value = self.clamp(self.value);
+        self
+    }
+
+    pub fn with_step(mut self, step: T) -> Self {
+        self.step = step;
+        self
+    }
+
+    pub fn with_precision(mut self, precision: usize) -> Self {
+        self.precision = Some(precision);
+        self
+    }
+
+    /// Enables changing the value by dragging with \[Ctrl\] held
+    pub fn with_scrub(mut self, pixels_per_step: f32) -> Self {
+        self.scrub_pixels_per_step = Some(pixels_per_step);
+        self
+    }
+
+    pub fn clamp(&self, value: T) -> T {
+        if value < self.min {
+            self.min
+        } else if value > self.max {
+            self.max
+        } else {
+            value
+        }
+    }
+
+    /// Adds `steps` times [`NumericInput::step`], saturating at the range
+    pub fn step_by(&mut self, steps: i32) {
+        let (zero, step) = (T::zero(), self.step);
+        if step <= zero {
+            return;
+        }
+        // only subtracting numbers of the same sign can't overflow, for any `T`
+        for _ in 0..steps.unsigned_abs() {
+            self.value = if steps > 0 {
+                if self.value >= self.max {
+                    self.max
+                } else if self.value < zero {
+                    self.clamp(self.value + step)
+                } else if self.max - self.value < step {
+                    self.max
+                } else {
+                    self.value + step
+                }
+            } else if self.value <= self.min {
+                self.min
+            } else if self.min >= zero || self.value < zero {
+                if self.value - self.min < step {
+                    self.min
+                } else {
+                    self.value - step
+                }
+            } else {
+                self.clamp(self.value - step)
+            };
+        }
+    }
+
+    pub fn format(&self, value: T) -> String {
+        match self.precision {
+            Some(precision) => format!("{value:.precision$}"),
+            None => value.to_string(),
+        }
+    }
+}
+
+/// Marks a [`NumericInput<T>`] of any `T`, which handles the mouse wheel instead of scrolling
+#[derive(Component, Default)]
+pub(crate) struct NumericField;
+
+/// Sent whenever the value of a [`NumericInput<T>`] changes through user input
+#[derive(Event, Debug)]
+pub struct NumericValueChanged<T: NumericValue> {
+    pub entity: Entity,
+    pub value: T,
+}
+
+fn read_typed_values<T: NumericValue>(
+    mut inputs: Query<
+        (Entity, &mut NumericInput<T>, EditorBuffer),
+        Or<(Changed<CosmicEditor>, Changed<CosmicEditBuffer>)>,
+    >,
+    mut evw_changed: EventWriter<NumericValueChanged<T>>,
+) {
+    for (entity, mut input, buffer) in inputs.iter_mut() {
+        let text = buffer.get_text();
+        if input.last_text.as_ref() == Some(&text) {
+            continue;
+        }
+        input.last_text = Some(text.clone());
+
+        let Ok(parsed) = text.trim().parse::<T>() else {
+            continue;
+        };
+        let value = input.clamp(parsed);
+        // don't rewrite the text while typing, even if out of range
+        input.displayed = Some(value);
+        if value != input.value {
+            input.value = value;
+            evw_changed.send(NumericValueChanged { entity, value });
+        }
+    }
+}
+
+fn step_values<T: NumericValue>(
+    mut inputs: Query<
+        (Entity, &mut NumericInput<T>, &InputState, &CosmicEditBuffer),
+        Without<ReadOnly>,
+    >,
+    focused: Res<FocusedWidget>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut wheel_evr: EventReader<MouseWheel>,
+    mut drag_evr: EventReader<Pointer<Drag>>,
+    mut evw_changed: EventWriter<NumericValueChanged<T>>,
+) {
+    let wheel = wheel_evr.read().collect::<Vec<_>>();
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let drags = drag_evr
+        .read()
+        .filter(|drag| ctrl && drag.button == PointerButton::Primary)
+        .map(|drag| (drag.target, drag.delta.x))
+        .collect::<Vec<_>>();
+
+    for (entity, mut input, input_state, buffer) in inputs.iter_mut() {
+        let mut steps = 0;
+        if focused.0 == Some(entity) {
+            if keys.just_pressed(KeyCode::ArrowUp) {
+                steps += 1;
+            }
+            if keys.just_pressed(KeyCode::ArrowDown) {
+                steps -= 1;
+            }
+        }
+        if input_state.is_hovering() {
+            let line_height = buffer.inner().metrics().line_height;
+            for ev in wheel.iter() {
+                let lines = match ev.unit {
+                    MouseScrollUnit::Line => ev.y,
+                    MouseScrollUnit::Pixel => ev.y / line_height,
+                };
+                // turning the wheel back starts over
+                if lines * input.wheel_remainder < 0. {
+                    input.wheel_remainder = 0.;
+                }
+                input.wheel_remainder += lines;
+            }
+            let scrolled = input.wheel_remainder.trunc();
+            input.wheel_remainder -= scrolled;
+            steps += scrolled as i32;
+        } else {
+            input.wheel_remainder = 0.;
+        }
+        if let Some(pixels_per_step) = input.scrub_pixels_per_step {
+            for (_, delta) in drags.iter().filter(|(target, _)| *target == entity) {
+                input.scrub_remainder += delta;
+                let scrubbed = (input.scrub_remainder / pixels_per_step).trunc();
+                input.scrub_remainder -= scrubbed * pixels_per_step;
+                steps += scrubbed as i32;
+            }
+        }
+        if steps == 0 {
+            continue;
+        }
+
+        let previous = input.value;
+        input.step_by(steps);
+        if input.value != previous {
+            evw_changed.send(NumericValueChanged {
+                entity,
+                value: input.value,
+            });
+        }
+    }
+}
+
+/// Writes the value into the buffer when changed by stepping or programmatically,
+/// and cleans up the text once unfocussed
+fn write_values<T: NumericValue>(
+    mut inputs: Query<(Entity, &mut NumericInput<T>, EditorBuffer, &DefaultAttrs)>,
+    focused: Res<FocusedWidget>,
+    mut font_system: ResMut<CosmicFontSystem>,
+) {
+    for (entity, mut input, mut buffer, attrs) in inputs.iter_mut() {
+        let is_focused = focused.0 == Some(entity);
+        let value_changed = input.displayed != Some(input.value);
+        if !value_changed && (is_focused || !focused.is_changed()) {
+            continue;
+        }
+
+        let text = input.format(input.value);
+        if buffer.get_text() != text {
+            buffer.set_text(&mut font_system, &text, attrs.as_attrs());
+            if let Some(editor) = buffer.editor() {
+                editor.set_selection(Selection::None);
+                editor.action(&mut font_system.0, Action::Motion(Motion::BufferEnd));
+            }
+        }
+        let value = input.value;
+        input.displayed = Some(value);
+        input.last_text = Some(text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn wheel_steps_once_per_line() {
+        let mut font_system = test_font_system();
+        let buffer = test_buffer(&mut font_system, "0");
+
+        let mut world = World::new();
+        world.insert_resource(CosmicFontSystem(font_system));
+        world.init_resource::<Assets<Image>>();
+        world.init_resource::<FocusedWidget>();
+        world.init_resource::<ButtonInput<KeyCode>>();
+        world.init_resource::<Events<MouseWheel>>();
+        world.init_resource::<Events<Pointer<Drag>>>();
+        world.init_resource::<Events<NumericValueChanged<i32>>>();
+        let entity = world
+            .spawn((buffer, NumericInput::new(0i32), InputState::Hovering))
+            .id();
+
+        let scroll = |world: &mut World, unit: MouseScrollUnit, y: f32| {
+            world.send_event(MouseWheel {
+                unit,
+                x: 0.,
+                y,
+                window: Entity::PLACEHOLDER,
+            });
+            world.run_system_once(step_values::<i32>).unwrap();
+            world.resource_mut::<Events<MouseWheel>>().clear();
+            world.get::<NumericInput<i32>>(entity).unwrap().value
+        };
+
+        // the test buffer's lines are 20 pixels high
+        assert_eq!(scroll(&mut world, MouseScrollUnit::Pixel, 8.), 0);
+        assert_eq!(scroll(&mut world, MouseScrollUnit::Pixel, 8.), 0);
+        assert_eq!(scroll(&mut world, MouseScrollUnit::Pixel, 8.), 1);
+        assert_eq!(scroll(&mut world, MouseScrollUnit::Line, 3.), 4);
+        assert_eq!(scroll(&mut world, MouseScrollUnit::Line, -1.), 3);
+    }
+
+    #[test]
+    fn steps_saturate() {
+        let mut input = NumericInput::new(1u32).with_range(0, 10).with_step(3);
+        input.step_by(-1);
+        assert_eq!(input.value, 0);
+        input.step_by(4);
+        assert_eq!(input.value, 10);
+
+        // steps bigger than the range don't overflow
+        let mut input = NumericInput::new(1u32).with_range(0, 2).with_step(3);
+        input.step_by(1);
+        assert_eq!(input.value, 2);
+        input.step_by(-1);
+        assert_eq!(input.value, 0);
+        let mut input = NumericInput::new(0i32).with_step(i32::MAX);
+        input.step_by(-3);
+        assert_eq!(input.value, i32::MIN);
+        input.step_by(3);
+        assert_eq!(input.value, i32::MAX);
+
+        let input = NumericInput::new(0.26f32).with_precision(1);
+        assert_eq!(input.format(input.value), "0.3");
+    }
+}
@@ -152,7 +152,7 @@ fn hide_password_text(
             None => {
                 let text = editor.get_text();
 
-                editor.set_text(
+                editor.set_text_silently(
                     &mut font_system,
                     password.glyph.to_string().repeat(text.len()).as_str(),
                     attrs.as_attrs(),
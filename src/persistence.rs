@@ -0,0 +1,585 @@
+//! Saving and restoring editors with scenes or save files
+//!
+//! See [`CosmicEditSnapshot`]
+
+use cosmic_text::{
+    Attrs, CacheKeyFlags, Color, Cursor, Edit, Family, Metrics, Scroll, Selection, Stretch, Style,
+    Weight,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cosmic_edit::DefaultAttrs,
+    input::{CosmicTextChanged, InputSet},
+    markdown::MarkdownLinks,
+    password::Password,
+    placeholder::Placeholder,
+    prelude::*,
+    rich_text::CosmicAttrsChanged,
+};
+
+pub(crate) struct PersistencePlugin;
+
+impl Plugin for PersistencePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (apply_snapshots, restore_snapshot_cursors)
+                .chain()
+                .after(InputSet),
+        )
+        .add_systems(
+            PostUpdate,
+            refresh_snapshots
+                .after(crate::input::delta::send_programmatic_edits)
+                .after(crate::rich_text::send_attrs_changed)
+                .before(crate::password::PasswordSet),
+        )
+        .register_type::<CosmicEditSnapshot>()
+        .register_type::<SavedSpan>()
+        .register_type::<SavedAttrs>()
+        .register_type::<SavedFamily>()
+        .register_type::<SavedCursor>()
+        .register_type::<SavedScroll>();
+    }
+}
+
+/// A serializable copy of an editor's text, formatting and state.
+///
+/// Add it to a [`CosmicEditBuffer`] entity to keep it up to date as the editor changes, so
+/// it is included when saving a [`DynamicScene`]. Inserting or changing it, e.g. when the
+/// scene is loaded, replaces the editor's contents. A default snapshot is filled in from
+/// the editor instead.
+///
+/// It also (de)serializes with serde on its own, e.g. for drafts in a save file.
+///
+/// The text of a [`Password`] isn't saved. Markdown links are saved by their URL, see
+/// [`SavedAttrs::link`].
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_cosmic_edit::prelude::*;
+/// use bevy_cosmic_edit::persistence::CosmicEditSnapshot;
+///
+/// # fn setup(mut commands: Commands) {
+/// commands.spawn((TextEdit, CosmicEditSnapshot::default()));
+/// # }
+///
+/// fn save_drafts(snapshots: Query<&CosmicEditSnapshot>) {
+///     for snapshot in snapshots.iter() {
+///         info!("Saving {:?}", snapshot.text());
+///     }
+/// }
+/// ```
+#[derive(Component, Reflect, Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[reflect(Component, Serialize, Deserialize, Default)]
+pub struct CosmicEditSnapshot {
+    /// Spans of text and their attributes, lines are separated by `\n`
+    pub spans: Vec<SavedSpan>,
+    pub font_size: f32,
+    pub line_height: f32,
+    /// Cursor of the editor when it was last focussed
+    pub cursor: Option<SavedCursor>,
+    /// The other end of the selection from the cursor
+    pub selection: Option<SavedCursor>,
+    pub scroll: SavedScroll,
+    /// Cursor and selection still to be restored once focussed
+    #[reflect(ignore)]
+    #[serde(skip)]
+    pending_cursor: bool,
+}
+
+#[derive(Reflect, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SavedSpan {
+    pub text: String,
+    pub attrs: SavedAttrs,
+}
+
+/// Serializable [`Attrs`]
+///
+/// A `metadata` that is the id of a link in [`MarkdownLinks`] is saved as [`SavedAttrs::link`]
+/// instead, as ids aren't the same between runs
+#[derive(Reflect, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SavedAttrs {
+    /// RGBA
+    pub color: Option<[u8; 4]>,
+    pub family: SavedFamily,
+    /// See [`Stretch::to_number`]
+    pub stretch: u16,
+    pub italic: bool,
+    pub oblique: bool,
+    pub weight: u16,
+    pub metadata: usize,
+    /// URL of a Markdown link, interned again when restored
+    #[serde(default)]
+    pub link: Option<String>,
+    /// Font size and line height, if different from the buffer's
+    #[serde(default)]
+    pub metrics: Option<(f32, f32)>,
+    /// Bits of [`CacheKeyFlags`]
+    #[serde(default)]
+    pub cache_key_flags: u32,
+}
+
+/// Serializable [`Family`]
+#[derive(Reflect, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum SavedFamily {
+    Name(String),
+    Serif,
+    SansSerif,
+    Cursive,
+    Fantasy,
+    Monospace,
+}
+
+#[derive(Reflect, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SavedCursor {
+    pub line: usize,
+    /// Byte offset within the line
+    pub index: usize,
+}
+
+#[derive(Reflect, Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct SavedScroll {
+    pub line: usize,
+    pub vertical: f32,
+    pub horizontal: f32,
+}
+
+impl SavedAttrs {
+    pub fn new(attrs: Attrs) -> Self {
+        Self {
+            color: attrs.color_opt.map(Color::as_rgba),
+            family: match attrs.family {
+                Family::Name(name) => SavedFamily::Name(name.to_owned()),
+                Family::Serif => SavedFamily::Serif,
+                Family::SansSerif => SavedFamily::SansSerif,
+                Family::Cursive => SavedFamily::Cursive,
+                Family::Fantasy => SavedFamily::Fantasy,
+                Family::Monospace => SavedFamily::Monospace,
+            },
+            stretch: attrs.stretch.to_number(),
+            italic: attrs.style == Style::Italic,
+            oblique: attrs.style == Style::Oblique,
+            weight: attrs.weight.0,
+            metadata: attrs.metadata,
+            link: None,
+            metrics: attrs.metrics_opt.map(|metrics| {
+                let metrics = Metrics::from(metrics);
+                (metrics.font_size, metrics.line_height)
+            }),
+            cache_key_flags: attrs.cache_key_flags.bits(),
+        }
+    }
+
+    /// Saves the link of [`SavedAttrs::metadata`], if it has one
+    pub fn with_links(mut self, links: &MarkdownLinks) -> Self {
+        if let Some(url) = links.get(self.metadata) {
+            self.link = Some(url.to_owned());
+            self.metadata = 0;
+        }
+        self
+    }
+
+    /// The attributes, with [`SavedAttrs::link`] as the `metadata` if set
+    pub fn as_attrs_with_links(&self, links: &mut MarkdownLinks) -> Attrs<'_> {
+        let attrs = self.as_attrs();
+        match &self.link {
+            Some(url) => attrs.metadata(links.intern(url)),
+            None => attrs,
+        }
+    }
+
+    /// The attributes, ignoring [`SavedAttrs::link`]
+    pub fn as_attrs(&self) -> Attrs<'_> {
+        let family = match &self.family {
+            SavedFamily::Name(name) => Family::Name(name),
+            SavedFamily::Serif => Family::Serif,
+            SavedFamily::SansSerif => Family::SansSerif,
+            SavedFamily::Cursive => Family::Cursive,
+            SavedFamily::Fantasy => Family::Fantasy,
+            SavedFamily::Monospace => Family::Monospace,
+        };
+        let stretch = [
+            Stretch::UltraCondensed,
+            Stretch::ExtraCondensed,
+            Stretch::Condensed,
+            Stretch::SemiCondensed,
+            Stretch::Normal,
+            Stretch::SemiExpanded,
+            Stretch::Expanded,
+            Stretch::ExtraExpanded,
+            Stretch::UltraExpanded,
+        ]
+        .into_iter()
+        .find(|stretch| stretch.to_number() == self.stretch)
+        .unwrap_or(Stretch::Normal);
+        let style = match (self.italic, self.oblique) {
+            (true, _) => Style::Italic,
+            (_, true) => Style::Oblique,
+            _ => Style::Normal,
+        };
+
+        let mut attrs = Attrs::new()
+            .family(family)
+            .stretch(stretch)
+            .style(style)
+            .weight(Weight(self.weight))
+            .metadata(self.metadata)
+            .cache_key_flags(CacheKeyFlags::from_bits_truncate(self.cache_key_flags));
+        if let Some([r, g, b, a]) = self.color {
+            attrs = attrs.color(Color::rgba(r, g, b, a));
+        }
+        if let Some((font_size, line_height)) = self.metrics {
+            attrs = attrs.metrics(Metrics::new(font_size, line_height));
+        }
+        attrs
+    }
+}
+
+impl SavedCursor {
+    fn new(cursor: Cursor) -> Self {
+        Self {
+            line: cursor.line,
+            index: cursor.index,
+        }
+    }
+
+    /// The cursor, moved into the buffer if it is out of bounds
+    fn to_cursor(self, buffer: &Buffer) -> Cursor {
+        crate::rich_text::clamp_cursor(buffer, Cursor::new(self.line, self.index))
+    }
+}
+
+impl CosmicEditSnapshot {
+    /// Takes a snapshot of a buffer, and optionally the editor's cursor and selection
+    pub fn new(buffer: &Buffer, editor: Option<&CosmicEditor>, links: &MarkdownLinks) -> Self {
+        let metrics = buffer.metrics();
+        let scroll = buffer.scroll();
+        let mut snapshot = Self {
+            spans: Vec::new(),
+            font_size: metrics.font_size,
+            line_height: metrics.line_height,
+            cursor: None,
+            selection: None,
+            scroll: SavedScroll {
+                line: scroll.line,
+                vertical: scroll.vertical,
+                horizontal: scroll.horizontal,
+            },
+            pending_cursor: false,
+        };
+        snapshot.save_text(buffer, links);
+        if let Some(editor) = editor {
+            snapshot.save_cursor(editor);
+        }
+        snapshot
+    }
+
+    /// The plain text without formatting
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    fn save_text(&mut self, buffer: &Buffer, links: &MarkdownLinks) {
+        let mut spans: Vec<SavedSpan> = Vec::new();
+        let mut push = |text: &str, attrs: Attrs| {
+            let attrs = SavedAttrs::new(attrs).with_links(links);
+            match spans.last_mut() {
+                Some(last) if last.attrs == attrs => last.text.push_str(text),
+                _ => spans.push(SavedSpan {
+                    text: text.to_owned(),
+                    attrs,
+                }),
+            }
+        };
+
+        for (line_i, line) in buffer.lines.iter().enumerate() {
+            let text = line.text();
+            let attrs_list = line.attrs_list();
+            let mut position = 0;
+            for (range, attrs) in attrs_list.spans() {
+                if range.start > position {
+                    push(&text[position..range.start], attrs_list.defaults());
+                }
+                push(&text[range.clone()], attrs.as_attrs());
+                position = range.end;
+            }
+            if position < text.len() {
+                push(&text[position..], attrs_list.defaults());
+            }
+            if line_i + 1 < buffer.lines.len() {
+                push("\n", attrs_list.defaults());
+            }
+        }
+        self.spans = spans;
+    }
+
+    fn save_cursor(&mut self, editor: &CosmicEditor) {
+        self.cursor = Some(SavedCursor::new(editor.cursor()));
+        self.selection = match editor.selection() {
+            Selection::None => None,
+            Selection::Normal(cursor) | Selection::Line(cursor) | Selection::Word(cursor) => {
+                Some(SavedCursor::new(cursor))
+            }
+        };
+        let scroll = editor.with_buffer(|buffer| buffer.scroll());
+        self.scroll = SavedScroll {
+            line: scroll.line,
+            vertical: scroll.vertical,
+            horizontal: scroll.horizontal,
+        };
+    }
+
+    fn restore_cursor(&self, editor: &mut CosmicEditor) {
+        let Some(cursor) = self.cursor else {
+            return;
+        };
+        let (cursor, selection) = editor.with_buffer(|buffer| {
+            (
+                cursor.to_cursor(buffer),
+                self.selection.map(|selection| selection.to_cursor(buffer)),
+            )
+        });
+        editor.set_cursor(cursor);
+        editor.set_selection(selection.map_or(Selection::None, Selection::Normal));
+    }
+}
+
+fn apply_snapshots(
+    mut snapshots: Query<
+        (
+            &mut CosmicEditSnapshot,
+            EditorBuffer,
+            &DefaultAttrs,
+            Option<&Placeholder>,
+            Has<Password>,
+        ),
+        Changed<CosmicEditSnapshot>,
+    >,
+    mut font_system: ResMut<CosmicFontSystem>,
+    mut links: ResMut<MarkdownLinks>,
+) {
+    for (mut snapshot, mut buffer, default_attrs, placeholder, password) in snapshots.iter_mut() {
+        let snapshot = snapshot.bypass_change_detection();
+        if *snapshot == CosmicEditSnapshot::default() {
+            let placeholder_active = placeholder.is_some_and(Placeholder::is_active);
+            *snapshot = CosmicEditSnapshot::new(buffer.get_raw_buffer(), None, &links);
+            if let Some(editor) = buffer.editor() {
+                snapshot.save_cursor(editor);
+            }
+            if placeholder_active || password {
+                snapshot.spans.clear();
+            }
+            continue;
+        }
+
+        let font_system = &mut font_system.0;
+        buffer.set_rich_text(
+            font_system,
+            snapshot.spans.iter().map(|span| {
+                (
+                    span.text.as_str(),
+                    span.attrs.as_attrs_with_links(&mut links),
+                )
+            }),
+            default_attrs.as_attrs(),
+        );
+        if snapshot.font_size > 0. && snapshot.line_height > 0. {
+            buffer.set_metrics(
+                font_system,
+                Metrics::new(snapshot.font_size, snapshot.line_height),
+            );
+        }
+        let scroll = snapshot.scroll;
+        buffer.set_scroll(Scroll::new(scroll.line, scroll.vertical, scroll.horizontal));
+        match buffer.editor() {
+            Some(editor) => snapshot.restore_cursor(editor),
+            None => snapshot.pending_cursor = true,
+        }
+    }
+}
+
+/// Restores the saved cursor when a restored editor is first focussed, unless it was
+/// focussed by clicking somewhere in the text
+fn restore_snapshot_cursors(
+    mut editors: Query<(&mut CosmicEditSnapshot, &mut CosmicEditor), Added<CosmicEditor>>,
+) {
+    for (mut snapshot, mut editor) in editors.iter_mut() {
+        let snapshot = snapshot.bypass_change_detection();
+        if !snapshot.pending_cursor {
+            continue;
+        }
+        snapshot.pending_cursor = false;
+        if editor.cursor() == Cursor::default() && editor.selection() == Selection::None {
+            snapshot.restore_cursor(&mut editor);
+        }
+    }
+}
+
+/// Keeps snapshots up to date, before [`Password`] hides the text
+fn refresh_snapshots(
+    mut snapshots: Query<
+        (
+            &mut CosmicEditSnapshot,
+            Option<&CosmicEditor>,
+            &CosmicEditBuffer,
+            Option<&Placeholder>,
+        ),
+        Without<Password>,
+    >,
+    mut evr_changed: EventReader<CosmicTextChanged>,
+    mut evr_restyled: EventReader<CosmicAttrsChanged>,
+    changed_editors: Query<Entity, Changed<CosmicEditor>>,
+    links: Res<MarkdownLinks>,
+) {
+    let changed = evr_changed
+        .read()
+        .map(|CosmicTextChanged((entity, _))| *entity);
+    let restyled = evr_restyled
+        .read()
+        .map(|CosmicAttrsChanged(entity)| *entity);
+    for entity in changed.chain(restyled) {
+        let Ok((mut snapshot, editor, buffer, placeholder)) = snapshots.get_mut(entity) else {
+            continue;
+        };
+        let snapshot = snapshot.bypass_change_detection();
+        match editor {
+            Some(editor) => editor.with_buffer(|buffer| snapshot.save_text(buffer, &links)),
+            None => snapshot.save_text(buffer.inner(), &links),
+        }
+        if placeholder.is_some_and(|placeholder| {
+            placeholder.is_active() && snapshot.text() == placeholder.text
+        }) {
+            snapshot.spans.clear();
+        }
+    }
+
+    for entity in changed_editors.iter() {
+        if let Ok((mut snapshot, Some(editor), ..)) = snapshots.get_mut(entity) {
+            let snapshot = snapshot.bypass_change_detection();
+            snapshot.save_cursor(editor);
+            snapshot.pending_cursor = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn round_trips_through_buffer() {
+        let font_system = test_font_system();
+
+        let mut world = World::new();
+        world.insert_resource(CosmicFontSystem(font_system));
+        world.init_resource::<Assets<Image>>();
+        world.init_resource::<MarkdownLinks>();
+        let bold = SavedAttrs::new(
+            Attrs::new()
+                .weight(Weight::BOLD)
+                .color(Color::rgb(255, 0, 0))
+                .metrics(Metrics::new(32., 40.)),
+        );
+        assert_eq!(bold.metrics, Some((32., 40.)));
+        let snapshot = CosmicEditSnapshot {
+            spans: vec![
+                SavedSpan {
+                    text: "plain\n".into(),
+                    attrs: SavedAttrs::new(Attrs::new()),
+                },
+                SavedSpan {
+                    text: "bold".into(),
+                    attrs: bold,
+                },
+            ],
+            font_size: 16.,
+            line_height: 24.,
+            cursor: Some(SavedCursor { line: 1, index: 2 }),
+            selection: None,
+            ..default()
+        };
+        let entity = world
+            .spawn((CosmicEditBuffer::default(), snapshot.clone()))
+            .id();
+        world.run_system_once(apply_snapshots).unwrap();
+
+        let buffer = world.get::<CosmicEditBuffer>(entity).unwrap().inner();
+        assert_eq!(buffer.metrics(), Metrics::new(16., 24.));
+        let mut saved = CosmicEditSnapshot::new(buffer, None, world.resource());
+        saved.cursor = snapshot.cursor;
+        assert_eq!(saved, snapshot);
+    }
+
+    #[test]
+    fn round_trips_through_scene() {
+        use bevy::{
+            ecs::entity::EntityHashMap,
+            scene::{ron, serde::SceneDeserializer, DynamicSceneBuilder},
+        };
+        use serde::de::DeserializeSeed;
+
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<CosmicEditSnapshot>();
+        let new_world = || {
+            let mut world = World::new();
+            world.insert_resource(registry.clone());
+            world.insert_resource(CosmicFontSystem(test_font_system()));
+            world.init_resource::<Assets<Image>>();
+            world.init_resource::<MarkdownLinks>();
+            world
+        };
+
+        // saved while another link was known, so the link id differs after loading
+        let mut world = new_world();
+        let mut links = world.resource_mut::<MarkdownLinks>();
+        links.intern("https://example.com");
+        let link = links.intern("https://bevyengine.org");
+        let buffer = test_buffer(&mut world.resource_mut::<CosmicFontSystem>().0, "")
+            .with_rich_text(
+                &mut world.resource_mut::<CosmicFontSystem>().0,
+                [
+                    ("see ", Attrs::new()),
+                    ("bevy", Attrs::new().metadata(link)),
+                ],
+                Attrs::new(),
+            );
+        let snapshot = CosmicEditSnapshot::new(buffer.inner(), None, world.resource());
+        assert_eq!(
+            snapshot.spans[1].attrs.link.as_deref(),
+            Some("https://bevyengine.org")
+        );
+        let entity = world.spawn(snapshot.clone()).id();
+
+        let scene = DynamicSceneBuilder::from_world(&world)
+            .extract_entity(entity)
+            .build();
+        let serialized = scene.serialize(&registry.read()).unwrap();
+        let mut deserializer = ron::de::Deserializer::from_str(&serialized).unwrap();
+        let scene = SceneDeserializer {
+            type_registry: &registry.read(),
+        }
+        .deserialize(&mut deserializer)
+        .unwrap();
+
+        let mut world = new_world();
+        let mut entity_map = EntityHashMap::default();
+        scene.write_to_world(&mut world, &mut entity_map).unwrap();
+        let loaded = entity_map[&entity];
+        assert_eq!(world.get::<CosmicEditSnapshot>(loaded), Some(&snapshot));
+
+        world.entity_mut(loaded).insert(CosmicEditBuffer::default());
+        world.run_system_once(apply_snapshots).unwrap();
+        let buffer = world.get::<CosmicEditBuffer>(loaded).unwrap().inner();
+        let attrs_list = buffer.lines[0].attrs_list();
+        let (_, attrs) = attrs_list.spans().into_iter().last().unwrap();
+        assert_eq!(
+            world.resource::<MarkdownLinks>().get(attrs.metadata),
+            Some("https://bevyengine.org")
+        );
+        assert_ne!(attrs.metadata, link);
+    }
+}
@@ -1,5 +1,7 @@
 use crate::{
-    cosmic_edit::DefaultAttrs, input::CosmicTextChanged, input::InputSet, prelude::*,
+    cosmic_edit::DefaultAttrs,
+    input::{delta::PendingTextEdits, CosmicTextChanged, InputSet},
+    prelude::*,
     render::RenderSet,
 };
 use cosmic_text::{Attrs, Edit};
@@ -50,6 +52,23 @@ impl Placeholder {
     pub fn is_active(&self) -> bool {
         self.active
     }
+
+    /// Empties the editor if the placeholder is shown, without reporting a change
+    pub(crate) fn clear(
+        &mut self,
+        editor: &mut CosmicEditor,
+        attrs: Attrs,
+        font_system: &mut cosmic_text::FontSystem,
+    ) {
+        if !self.active {
+            return;
+        }
+        editor.with_buffer_mut(|buffer| {
+            buffer.set_text(font_system, "", attrs, cosmic_text::Shaping::Advanced)
+        });
+        editor.set_cursor(cosmic_text::Cursor::new(0, 0));
+        self.active = false;
+    }
 }
 
 pub(crate) struct PlaceholderPlugin;
@@ -81,7 +100,7 @@ fn add_placeholder_to_buffer(
         }
 
         if buffer.get_text().is_empty() {
-            buffer.set_text(&mut font_system, placeholder.text, placeholder.attrs);
+            buffer.set_text_silently(&mut font_system, placeholder.text, placeholder.attrs);
             placeholder.active = true;
         }
     }
@@ -126,11 +145,16 @@ fn move_cursor_to_start_of_placeholder(mut q: Query<(&mut CosmicEditor, &mut Pla
 }
 
 fn remove_placeholder_on_input(
-    mut q: Query<(&mut CosmicEditor, &mut Placeholder, &DefaultAttrs)>,
+    mut q: Query<(
+        &mut CosmicEditor,
+        &mut Placeholder,
+        &DefaultAttrs,
+        &mut PendingTextEdits,
+    )>,
     evr: EventReader<CosmicTextChanged>,
     mut font_system: ResMut<CosmicFontSystem>,
 ) {
-    for (mut editor, mut placeholder, attrs) in q.iter_mut() {
+    for (mut editor, mut placeholder, attrs, mut pending_edits) in q.iter_mut() {
         if !placeholder.active {
             return;
         }
@@ -138,6 +162,17 @@ fn remove_placeholder_on_input(
             return;
         }
 
+        // the text as reported to users, without the placeholder
+        let reported_text = |editor: &CosmicEditor| {
+            let text = editor.get_text();
+            if text == placeholder.text {
+                String::new()
+            } else {
+                text
+            }
+        };
+        let old_text = reported_text(&editor);
+
         let mut lines = 0;
 
         let last_line = editor.with_buffer_mut(|b| {
@@ -215,14 +250,15 @@ fn remove_placeholder_on_input(
 
             Some(single_line)
         });
+        pending_edits.record_replace(&old_text, &reported_text(&editor));
 
         let Some(last_line) = last_line else {
             return;
         };
 
         editor.set_cursor(cosmic_text::Cursor::new(
-            (lines - 1).max(0),
-            last_line.bytes().len(),
+            lines.saturating_sub(1),
+            last_line.len(),
         ));
 
         placeholder.active = false;
@@ -26,6 +26,20 @@ impl Plugin for CosmicEditPlugin {
             crate::password::PasswordPlugin,
             crate::user_select::UserSelectPlugin,
             crate::double_click::plugin,
+            crate::context_menu::ContextMenuPlugin,
+            crate::input_filter::InputFilterPlugin,
+            crate::input_mask::InputMaskPlugin,
+            crate::numeric_input::plugin,
+            (
+                crate::persistence::PersistencePlugin,
+                crate::rich_text::RichTextPlugin,
+                crate::markdown::plugin,
+                crate::syntax_highlight::SyntaxHighlightPlugin,
+                crate::line_numbers::LineNumbersPlugin,
+                crate::find::FindPlugin,
+                crate::highlights::HighlightsPlugin,
+                crate::completion::CompletionPlugin,
+            ),
         ))
         // TODO: Use the builtin bevy CosmicFontSystem
         .insert_resource(crate::cosmic_edit::CosmicFontSystem(font_system));
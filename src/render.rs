@@ -1,4 +1,12 @@
-use crate::{cosmic_edit::ReadOnly, prelude::*};
+use crate::{
+    cosmic_edit::ReadOnly,
+    find::Search,
+    highlights::{draw_highlights, Highlight, TextHighlights},
+    input::drag::DropCaret,
+    line_numbers::{Gutter, LineNumbers},
+    prelude::*,
+    spellcheck::Misspellings,
+};
 use crate::{cosmic_edit::*, BufferMutExtras};
 use bevy::render::render_resource::Extent3d;
 use image::{imageops::FilterType, GenericImageView};
@@ -94,6 +102,10 @@ pub(crate) struct WidgetBufferCoordTransformation {
     /// top of the buffer
     top_padding: f32,
 
+    /// Width of the [`LineNumbers`](crate::line_numbers::LineNumbers) gutter
+    /// left of the buffer
+    left_padding: f32,
+
     render_target_size: Vec2,
 }
 
@@ -107,27 +119,36 @@ impl WidgetBufferCoordTransformation {
         // debug!(?top_padding, ?render_target_height, ?buffer_height);
         Self {
             top_padding,
+            left_padding: 0.,
             render_target_size,
         }
     }
 
+    /// Offsets the buffer right by a gutter `width` wide
+    pub fn with_gutter(self, width: f32) -> Self {
+        Self {
+            left_padding: width,
+            ..self
+        }
+    }
+
     /// If you have the buffer coord, used for rendering
     // Confusing ngl, but it works
     pub fn buffer_to_widget(&self, buffer: Vec2) -> Vec2 {
-        Vec2::new(buffer.x, buffer.y + self.top_padding)
+        Vec2::new(buffer.x + self.left_padding, buffer.y + self.top_padding)
     }
 
     /// If you have the relative widget coord centered (0, 0) in the middle of the widget,
     /// returns the buffer coord starting (0, 0) top left and working downward
     pub fn widget_origined_to_buffer_topleft(&self, widget: Vec2) -> Vec2 {
         Vec2::new(
-            widget.x + self.render_target_size.x / 2.,
+            widget.x + self.render_target_size.x / 2. - self.left_padding,
             -widget.y + self.render_target_size.y / 2. - self.top_padding,
         )
     }
 
     pub fn widget_topleft_to_buffer_topleft(&self, widget: Vec2) -> Vec2 {
-        Vec2::new(widget.x, widget.y - self.top_padding)
+        Vec2::new(widget.x - self.left_padding, widget.y - self.top_padding)
     }
 
     #[allow(dead_code)]
@@ -136,6 +157,61 @@ impl WidgetBufferCoordTransformation {
     }
 }
 
+/// Blends a `width` by `height` rectangle at `buffer_coord` onto `pixels`,
+/// except where it is scrolled under the gutter
+fn fill_buffer_rect(
+    pixels: &mut [u8],
+    transformation: &WidgetBufferCoordTransformation,
+    buffer_coord: IVec2,
+    width: u32,
+    height: u32,
+    color: cosmic_text::Color,
+) {
+    let size = transformation.render_target_size.as_ivec2();
+    for row in 0..height as i32 {
+        for col in 0..width as i32 {
+            // compute padding_top
+            let widget_coord = transformation
+                .buffer_to_widget((buffer_coord + IVec2::new(col, row)).as_vec2())
+                .as_ivec2();
+            if (widget_coord.x as f32) < transformation.left_padding {
+                continue;
+            }
+
+            // actually draw pixel
+            draw_pixel(
+                pixels,
+                size.x,
+                size.y,
+                widget_coord.x,
+                widget_coord.y,
+                color,
+            );
+        }
+    }
+}
+
+/// Left edge, top and height of a caret drawn at `cursor`, in buffer coordinates
+pub(crate) fn caret_position(
+    buffer: &Buffer,
+    cursor: cosmic_text::Cursor,
+) -> Option<(i32, i32, i32)> {
+    buffer
+        .layout_runs()
+        .filter(|run| run.line_i == cursor.line)
+        .find(|run| {
+            let end = run.glyphs.last().map_or(0, |glyph| glyph.end);
+            cursor.index <= end
+        })
+        .map(|run| {
+            let x = match run.glyphs.iter().find(|glyph| glyph.start >= cursor.index) {
+                Some(glyph) => glyph.x,
+                None => run.glyphs.last().map_or(0., |glyph| glyph.x + glyph.w),
+            };
+            (x as i32, run.line_top as i32, run.line_height as i32)
+        })
+}
+
 /// Renders to the [CosmicRenderOutput]
 fn render_texture(
     mut query: Query<(
@@ -151,10 +227,19 @@ fn render_texture(
         Option<&ReadOnly>,
         &CosmicTextAlign,
         &CosmicWrap,
+        Option<&DropCaret>,
+        (Option<&LineNumbers>, Option<&mut Gutter>),
+        (
+            Option<&Search>,
+            Option<&TextHighlights>,
+            Option<&Misspellings>,
+        ),
     )>,
     mut font_system: ResMut<CosmicFontSystem>,
     mut images: ResMut<Assets<Image>>,
     mut swash_cache_state: ResMut<SwashCache>,
+    // lays out line numbers
+    mut scratch: Local<Option<Buffer>>,
 ) {
     for (
         mut editor,
@@ -169,6 +254,9 @@ fn render_texture(
         readonly_opt,
         text_align,
         wrap,
+        drop_caret,
+        (line_numbers, mut gutter),
+        (search, text_highlights, misspellings),
     ) in query.iter_mut()
     {
         let font_system = &mut font_system.0;
@@ -221,45 +309,33 @@ fn render_texture(
             .color_opt
             .unwrap_or(cosmic_text::Color::rgb(0, 0, 0));
 
+        let scratch =
+            scratch.get_or_insert_with(|| Buffer::new_empty(cosmic_text::Metrics::new(1., 1.)));
+        let gutter_width = match (line_numbers, gutter.as_deref_mut()) {
+            (Some(line_numbers), Some(gutter)) => {
+                line_numbers.size_gutter(gutter, &editor, scratch, font_system, attrs.0.as_attrs());
+                gutter.width
+            }
+            (None, Some(gutter)) => {
+                gutter.width = 0.;
+                0.
+            }
+            _ => 0.,
+        };
+
         // compute y-offset
         let buffer_size = editor.borrow_with(font_system).expected_size();
         let transformation = WidgetBufferCoordTransformation::new(
             text_align.vertical,
             render_target_size,
             buffer_size,
-        );
-
-        // let mut actually_rendered_max = IVec2::ZERO;
-        // let mut actually_rendered_min = IVec2::new(i32::MAX, i32::MAX);
-        let draw_closure = |x, y, w, h, color| {
-            for row in 0..h as i32 {
-                for col in 0..w as i32 {
-                    let buffer_coord = IVec2::new(x + col, y + row);
-                    // actually_rendered_max = actually_rendered_max.max(buffer_coord);
-                    // actually_rendered_min = actually_rendered_min.min(buffer_coord);
-
-                    // compute padding_top
-                    let widget_coord = transformation
-                        .buffer_to_widget(buffer_coord.as_vec2())
-                        .as_ivec2();
-
-                    // actually draw pixel
-                    draw_pixel(
-                        &mut pixels,
-                        render_target_size.x as i32,
-                        render_target_size.y as i32,
-                        widget_coord.x,
-                        widget_coord.y,
-                        color,
-                    );
-                }
-            }
-        };
+        )
+        .with_gutter(gutter_width);
 
         editor.set_size(
             font_system,
             Some(match wrap {
-                CosmicWrap::Wrap => render_target_size.x,
+                CosmicWrap::Wrap => (render_target_size.x - gutter_width).max(0.),
                 // probably high enough
                 CosmicWrap::InfiniteLine => f32::MAX / 10f32.powi(3),
             }),
@@ -271,6 +347,46 @@ fn render_texture(
             }
         }
 
+        // Highlight search matches and highlighted ranges behind the glyphs
+        let selected = editor.editor().and_then(|editor| editor.selection_bounds());
+        let search_highlights = search.into_iter().flat_map(|search| {
+            search.matches().iter().map(move |found| {
+                let color = match selected == Some((found.start, found.end)) {
+                    true => search.selected_color,
+                    false => search.color,
+                };
+                Highlight::new(found.start, found.end).background(color)
+            })
+        });
+        let highlights = search_highlights
+            .chain(
+                text_highlights
+                    .into_iter()
+                    .flat_map(|h| h.0.iter().cloned()),
+            )
+            .chain(
+                misspellings
+                    .into_iter()
+                    .flat_map(|m| m.highlights().iter().cloned()),
+            )
+            .collect::<Vec<_>>();
+        if !highlights.is_empty() {
+            editor
+                .borrow_with(font_system)
+                .with_buffer_mut(|buffer| buffer.shape_until_scroll(false));
+            draw_highlights(&editor, &highlights, false, |x, y, w, h, color| {
+                fill_buffer_rect(&mut pixels, &transformation, IVec2::new(x, y), w, h, color)
+            });
+        }
+
+        // let mut actually_rendered_max = IVec2::ZERO;
+        // let mut actually_rendered_min = IVec2::new(i32::MAX, i32::MAX);
+        let draw_closure = |x, y, w, h, color| {
+            // actually_rendered_max = actually_rendered_max.max(IVec2::new(x, y));
+            // actually_rendered_min = actually_rendered_min.min(IVec2::new(x, y));
+            fill_buffer_rect(&mut pixels, &transformation, IVec2::new(x, y), w, h, color)
+        };
+
         // Draw glyphs
         if let Some(editor) = editor.editor() {
             // todo: optimizations (see below comments)
@@ -364,6 +480,77 @@ fn render_texture(
             // buffer.set_redraw(false);
         }
 
+        if !highlights.is_empty() {
+            draw_highlights(&editor, &highlights, true, |x, y, w, h, color| {
+                fill_buffer_rect(&mut pixels, &transformation, IVec2::new(x, y), w, h, color)
+            });
+        }
+
+        // Draw where dragged text would be dropped
+        if let Some(&DropCaret(Some(cursor))) = drop_caret {
+            if let Some((x, y, height)) = caret_position(&editor, cursor) {
+                for row in 0..height {
+                    for col in 0..2 {
+                        let widget_coord = transformation
+                            .buffer_to_widget(Vec2::new((x + col) as f32, (y + row) as f32))
+                            .as_ivec2();
+                        draw_pixel(
+                            &mut pixels,
+                            render_target_size.x as i32,
+                            render_target_size.y as i32,
+                            widget_coord.x,
+                            widget_coord.y,
+                            cursor_color.0.to_cosmic(),
+                        );
+                    }
+                }
+            }
+        }
+
+        if let (Some(line_numbers), Some(gutter)) = (line_numbers, gutter) {
+            let background = line_numbers.background.to_cosmic();
+            for y in 0..render_target_size.y as i32 {
+                for x in 0..gutter.width as i32 {
+                    draw_pixel(
+                        &mut pixels,
+                        render_target_size.x as i32,
+                        render_target_size.y as i32,
+                        x,
+                        y,
+                        background,
+                    );
+                }
+            }
+
+            let cursor = editor.editor().map(|editor| editor.cursor());
+            line_numbers.draw(
+                gutter.into_inner(),
+                &mut editor,
+                cursor,
+                scratch,
+                font_system,
+                &mut swash_cache_state.0,
+                attrs.0.as_attrs(),
+                |x, y, w, h, color| {
+                    for row in 0..h as i32 {
+                        for col in 0..w as i32 {
+                            let widget_y = transformation
+                                .buffer_to_widget(Vec2::new(0., (y + row) as f32))
+                                .y as i32;
+                            draw_pixel(
+                                &mut pixels,
+                                render_target_size.x as i32,
+                                render_target_size.y as i32,
+                                x + col,
+                                widget_y,
+                                color,
+                            );
+                        }
+                    }
+                },
+            );
+        }
+
         if let Some(prev_image) = images.get_mut(&canvas.0) {
             prev_image.data.clear();
             // Updates the stored asset image with the computed pixels
@@ -50,6 +50,9 @@ mod error {
         SpriteExpectedHitdataPosition,
 
         UiExpectedCursorPosition,
+
+        /// When a sprite isn't visible to any camera, so it has no viewport position
+        SpriteOutsideViewport,
     }
 
     impl RenderTargetError {
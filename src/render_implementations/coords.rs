@@ -3,6 +3,7 @@ use bevy::picking::backend::HitData;
 use bevy::ui::RelativeCursorPosition;
 use render_implementations::prelude::*;
 
+use crate::line_numbers::Gutter;
 use crate::render::WidgetBufferCoordTransformation;
 use crate::render_implementations::CosmicWidgetSize;
 use crate::{prelude::*, CosmicTextAlign};
@@ -15,6 +16,8 @@ pub(crate) struct RelativeQuery {
 
     sprite_global_transform: &'static GlobalTransform,
     ui_cursor_position: Option<&'static RelativeCursorPosition>,
+    ui_node: Option<&'static ComputedNode>,
+    gutter: Option<&'static Gutter>,
 }
 
 impl<'s> std::ops::Deref for RelativeQueryItem<'s> {
@@ -26,6 +29,71 @@ impl<'s> std::ops::Deref for RelativeQueryItem<'s> {
 }
 
 impl RelativeQueryItem<'_> {
+    fn gutter_width(&self) -> f32 {
+        self.gutter.map_or(0., |gutter| gutter.width)
+    }
+
+    /// Whether `buffer_coord` lies in the [`LineNumbers`](crate::line_numbers::LineNumbers) gutter
+    pub fn in_gutter(&self, buffer_coord: Vec2) -> bool {
+        self.gutter_width() > 0. && buffer_coord.x < 0.
+    }
+
+    /// The position of `buffer_coord` from the top left of the widget, in logical pixels
+    pub fn buffer_to_widget_topleft(&self, buffer_coord: Vec2, buffer_size: Vec2) -> Result<Vec2> {
+        let transformation = WidgetBufferCoordTransformation::new(
+            self.text_align.vertical,
+            self.widget_size.logical_size()?,
+            buffer_size,
+        )
+        .with_gutter(self.gutter_width());
+        Ok(transformation.buffer_to_widget(buffer_coord))
+    }
+
+    /// The position of `buffer_coord` in the viewport, in logical pixels, e.g. to place a popup.
+    ///
+    /// Sprites are projected with the first of `cameras` they are visible to
+    pub fn buffer_to_viewport<'c>(
+        &self,
+        buffer_coord: Vec2,
+        buffer_size: Vec2,
+        cameras: impl IntoIterator<Item = (&'c Camera, &'c GlobalTransform)>,
+    ) -> Result<Vec2> {
+        let offset = self.buffer_to_widget_topleft(buffer_coord, buffer_size)?;
+        self.widget_topleft_to_viewport(offset, cameras)
+    }
+
+    /// The position of `offset` from the top left of the widget in the viewport,
+    /// in logical pixels, see [`Self::buffer_to_viewport`]
+    pub fn widget_topleft_to_viewport<'c>(
+        &self,
+        offset: Vec2,
+        cameras: impl IntoIterator<Item = (&'c Camera, &'c GlobalTransform)>,
+    ) -> Result<Vec2> {
+        let widget_size = self.widget_size.logical_size()?;
+        match self.scan()? {
+            SourceType::Sprite => {
+                let local = Vec3::new(
+                    offset.x - widget_size.x / 2.,
+                    widget_size.y / 2. - offset.y,
+                    0.,
+                );
+                let world = self.sprite_global_transform.transform_point(local);
+                cameras
+                    .into_iter()
+                    .find_map(|(camera, transform)| camera.world_to_viewport(transform, world).ok())
+                    .ok_or(RenderTargetError::SpriteOutsideViewport)
+            }
+            SourceType::Ui => {
+                let node = self
+                    .ui_node
+                    .ok_or(RenderTargetError::required_component_missing::<ComputedNode>())?;
+                let center =
+                    self.sprite_global_transform.translation().xy() * node.inverse_scale_factor();
+                Ok(center - widget_size / 2. + offset)
+            }
+        }
+    }
+
     pub fn compute_buffer_coord(&self, hit_data: &HitData, buffer_size: Vec2) -> Result<Vec2> {
         match self.scan()? {
             SourceType::Sprite => {
@@ -54,7 +122,8 @@ impl RelativeQueryItem<'_> {
                     text_align.vertical,
                     render_target_size,
                     buffer_size,
-                );
+                )
+                .with_gutter(self.gutter_width());
                 // .xy swizzle depends on normal vector being perfectly out of screen
                 let buffer_coord =
                     transformation.widget_origined_to_buffer_topleft(relative_position);
@@ -82,7 +151,8 @@ impl RelativeQueryItem<'_> {
                     text_align.vertical,
                     widget_size,
                     buffer_size,
-                );
+                )
+                .with_gutter(self.gutter_width());
 
                 let buffer_coord =
                     transformation.widget_topleft_to_buffer_topleft(relative_position);
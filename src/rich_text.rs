@@ -0,0 +1,483 @@
+//! Changing the attributes of parts of the text, e.g. for bold, italic and color buttons
+//!
+//! See [`EditorBufferItem::apply_attrs`] and [`CosmicEditor::set_typing_attrs`]
+
+use std::ops::Range;
+
+use cosmic_text::{
+    Attrs, AttrsList, CacheMetrics, Color, Cursor, Family, FamilyOwned, Metrics, Style, Weight,
+};
+
+use crate::{
+    editor_buffer::EditorBufferItem,
+    input::delta::{CosmicTextEdited, DeltaKind, EditCause},
+    prelude::*,
+};
+
+pub(crate) struct RichTextPlugin;
+
+impl Plugin for RichTextPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CosmicAttrsChanged>()
+            .add_observer(apply_typing_attrs)
+            .add_systems(
+                PostUpdate,
+                (send_attrs_changed, forget_moved_typing_attrs)
+                    .before(crate::password::PasswordSet),
+            );
+    }
+}
+
+/// Attributes to change, fields left as `None` are kept
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_cosmic_edit::prelude::*;
+/// use bevy_cosmic_edit::rich_text::{AttrsChange, TextRange};
+///
+/// fn toggle_bold(mut editor: Query<EditorBuffer>, focused: Res<FocusedWidget>) {
+///     let Some(Ok(mut buffer)) = focused.0.map(|e| editor.get_mut(e)) else {
+///         return;
+///     };
+///     buffer.toggle_attrs(TextRange::Selection, &AttrsChange::bold());
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AttrsChange {
+    /// `Some(None)` for the default text color
+    pub color: Option<Option<Color>>,
+    pub family: Option<FamilyOwned>,
+    pub style: Option<Style>,
+    pub weight: Option<Weight>,
+    /// Font size and line height, `Some(None)` for the buffer's metrics
+    pub metrics: Option<Option<Metrics>>,
+}
+
+impl AttrsChange {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bold() -> Self {
+        Self::new().weight(Weight::BOLD)
+    }
+
+    pub fn italic() -> Self {
+        Self::new().style(Style::Italic)
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = Some(Some(color));
+        self
+    }
+
+    pub fn family(mut self, family: Family) -> Self {
+        self.family = Some(FamilyOwned::new(family));
+        self
+    }
+
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    pub fn weight(mut self, weight: Weight) -> Self {
+        self.weight = Some(weight);
+        self
+    }
+
+    pub fn metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(Some(metrics));
+        self
+    }
+
+    /// `attrs` with the changed fields replaced
+    pub fn apply<'a>(&'a self, mut attrs: Attrs<'a>) -> Attrs<'a> {
+        if let Some(color) = self.color {
+            attrs.color_opt = color;
+        }
+        if let Some(family) = &self.family {
+            attrs.family = family.as_family();
+        }
+        if let Some(style) = self.style {
+            attrs.style = style;
+        }
+        if let Some(weight) = self.weight {
+            attrs.weight = weight;
+        }
+        if let Some(metrics) = self.metrics {
+            attrs.metrics_opt = metrics.map(CacheMetrics::from);
+        }
+        attrs
+    }
+
+    /// Whether `attrs` already has every changed field
+    pub fn is_applied(&self, attrs: Attrs) -> bool {
+        self.apply(attrs) == attrs
+    }
+
+    /// The same fields with their values from `attrs`, e.g. to revert to a line's defaults
+    pub fn values_in(&self, attrs: Attrs) -> Self {
+        Self {
+            color: self.color.map(|_| attrs.color_opt),
+            family: self.family.as_ref().map(|_| FamilyOwned::new(attrs.family)),
+            style: self.style.map(|_| attrs.style),
+            weight: self.weight.map(|_| attrs.weight),
+            metrics: self.metrics.map(|_| attrs.metrics_opt.map(Metrics::from)),
+        }
+    }
+
+    /// Both changes, preferring the fields of `other`
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            color: other.color.or(self.color),
+            family: other.family.or(self.family),
+            style: other.style.or(self.style),
+            weight: other.weight.or(self.weight),
+            metrics: other.metrics.or(self.metrics),
+        }
+    }
+}
+
+/// Part of the text to change the attributes of
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextRange {
+    /// The editor's selection.
+    ///
+    /// If nothing is selected, the [typing attributes](CosmicEditor::set_typing_attrs)
+    /// are changed instead.
+    Selection,
+    /// Between two cursors, in either order
+    Cursors(Cursor, Cursor),
+    /// Byte offsets into the text, with lines separated by `\n`
+    Bytes(Range<usize>),
+}
+
+/// Sent when attributes are changed through [`EditorBuffer`], which doesn't send
+/// [`CosmicTextChanged`](crate::input::CosmicTextChanged) as the text stays the same
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CosmicAttrsChanged(pub Entity);
+
+/// Whether [`CosmicAttrsChanged`] should be sent
+#[derive(Component, Default, Debug)]
+pub(crate) struct PendingRestyle(pub(crate) bool);
+
+/// The cursor, moved into the buffer if it is out of bounds
+pub(crate) fn clamp_cursor(buffer: &Buffer, cursor: Cursor) -> Cursor {
+    let line = cursor.line.min(buffer.lines.len().saturating_sub(1));
+    let text = buffer.lines.get(line).map_or("", |line| line.text());
+    let mut index = cursor.index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    Cursor::new(line, index)
+}
+
+fn cursor_at_byte(buffer: &Buffer, mut byte: usize) -> Cursor {
+    for (line_i, line) in buffer.lines.iter().enumerate() {
+        let len = line.text().len();
+        if byte <= len {
+            return clamp_cursor(buffer, Cursor::new(line_i, byte));
+        }
+        byte -= len + 1;
+    }
+    clamp_cursor(buffer, Cursor::new(usize::MAX, usize::MAX))
+}
+
+fn ordered(a: Cursor, b: Cursor) -> (Cursor, Cursor) {
+    if (a.line, a.index) <= (b.line, b.index) {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Non-empty selection bounds
+fn selection_bounds(editor: &CosmicEditor) -> Option<(Cursor, Cursor)> {
+    editor
+        .selection_bounds()
+        .filter(|(start, end)| (start.line, start.index) != (end.line, end.index))
+}
+
+/// Calls `f` with the attributes of each part of the lines from `start` to `end`, split at
+/// `start`, `end` and span boundaries, along with the line's defaults
+fn for_each_segment(
+    buffer: &Buffer,
+    start: Cursor,
+    end: Cursor,
+    mut f: impl FnMut(usize, Range<usize>, Attrs, Attrs),
+) {
+    for line_i in start.line..=end.line.min(buffer.lines.len().saturating_sub(1)) {
+        let line = &buffer.lines[line_i];
+        let len = line.text().len();
+        let from = if line_i == start.line { start.index } else { 0 };
+        let to = if line_i == end.line { end.index } else { len };
+
+        let attrs_list = line.attrs_list();
+        let mut cuts = vec![0, from, to, len];
+        for (range, _) in attrs_list.spans() {
+            cuts.extend([range.start, range.end]);
+        }
+        cuts.retain(|cut| *cut <= len);
+        cuts.sort_unstable();
+        cuts.dedup();
+        for pair in cuts.windows(2) {
+            f(
+                line_i,
+                pair[0]..pair[1],
+                attrs_list.get_span(pair[0]),
+                attrs_list.defaults(),
+            );
+        }
+    }
+}
+
+/// Changes the attributes of `start..end`, with the change for each line depending on its
+/// defaults. Adjacent spans with the same attributes are merged.
+///
+/// Returns whether anything changed
+fn restyle(
+    buffer: &mut Buffer,
+    start: Cursor,
+    end: Cursor,
+    change_for_line: impl Fn(Attrs) -> AttrsChange,
+) -> bool {
+    let mut lists: Vec<(usize, AttrsList)> = Vec::new();
+    for_each_segment(buffer, start, end, |line_i, range, attrs, defaults| {
+        if lists.last().is_none_or(|(i, _)| *i != line_i) {
+            lists.push((line_i, AttrsList::new(defaults)));
+        }
+        let (_, list) = lists.last_mut().unwrap();
+        let inside = (line_i, range.start) >= (start.line, start.index)
+            && (line_i, range.end) <= (end.line, end.index);
+        let change = change_for_line(defaults);
+        let attrs = if inside { change.apply(attrs) } else { attrs };
+        if attrs != defaults {
+            list.add_span(range, attrs);
+        }
+    });
+
+    let mut changed = false;
+    for (line_i, list) in lists {
+        changed |= buffer.lines[line_i].set_attrs_list(list);
+    }
+    if changed {
+        buffer.set_redraw(true);
+    }
+    changed
+}
+
+impl EditorBufferItem<'_> {
+    /// Applies `change` to the text in `range`
+    pub fn apply_attrs(&mut self, range: TextRange, change: &AttrsChange) {
+        self.restyle_range(range, |_| change.clone());
+    }
+
+    /// Reverts the fields of `change` to each line's default attributes in `range`
+    pub fn remove_attrs(&mut self, range: TextRange, change: &AttrsChange) {
+        self.restyle_range(range, |defaults| change.values_in(defaults));
+    }
+
+    /// Removes `change` if all of `range` already has it, otherwise applies it.
+    ///
+    /// Returns whether it was applied, e.g. to highlight a bold button
+    pub fn toggle_attrs(&mut self, range: TextRange, change: &AttrsChange) -> bool {
+        let apply = !self.has_attrs(range.clone(), change);
+        match apply {
+            true => self.apply_attrs(range, change),
+            false => self.remove_attrs(range, change),
+        }
+        apply
+    }
+
+    /// Whether all of `range` has the fields of `change`.
+    ///
+    /// For an empty range, checks the attributes newly typed text would have
+    pub fn has_attrs(&mut self, range: TextRange, change: &AttrsChange) -> bool {
+        let Some((start, end)) = self.resolve_range(range) else {
+            return false;
+        };
+        if (start.line, start.index) == (end.line, end.index) {
+            let typing_attrs = self
+                .editor()
+                .and_then(|editor| editor.typing_attrs().cloned());
+            return self.with_buffer(|buffer| {
+                let attrs = buffer.lines[start.line]
+                    .attrs_list()
+                    .get_span(start.index.saturating_sub(1));
+                let typing_attrs = typing_attrs.unwrap_or_default();
+                change.is_applied(typing_attrs.apply(attrs))
+            });
+        }
+
+        let mut all = true;
+        self.with_buffer(|buffer| {
+            for_each_segment(buffer, start, end, |line_i, range, attrs, _| {
+                let inside = (line_i, range.start) >= (start.line, start.index)
+                    && (line_i, range.end) <= (end.line, end.index);
+                all &= !inside || change.is_applied(attrs);
+            })
+        });
+        all
+    }
+
+    /// Ordered bounds of `range`, or the cursor for an empty selection
+    fn resolve_range(&mut self, range: TextRange) -> Option<(Cursor, Cursor)> {
+        match range {
+            TextRange::Selection => {
+                let editor = self.editor()?;
+                Some(selection_bounds(editor).unwrap_or((editor.cursor(), editor.cursor())))
+            }
+            TextRange::Cursors(a, b) => {
+                Some(self.with_buffer(|buffer| {
+                    ordered(clamp_cursor(buffer, a), clamp_cursor(buffer, b))
+                }))
+            }
+            TextRange::Bytes(bytes) => Some(self.with_buffer(|buffer| {
+                ordered(
+                    cursor_at_byte(buffer, bytes.start),
+                    cursor_at_byte(buffer, bytes.end),
+                )
+            })),
+        }
+    }
+
+    fn restyle_range(&mut self, range: TextRange, change_for_line: impl Fn(Attrs) -> AttrsChange) {
+        if range == TextRange::Selection {
+            let Some(editor) = self.editor() else {
+                return;
+            };
+            if selection_bounds(editor).is_none() {
+                let cursor = editor.cursor();
+                let change = editor.with_buffer(|buffer| {
+                    change_for_line(buffer.lines[cursor.line].attrs_list().defaults())
+                });
+                let typing_attrs = editor.typing_attrs().cloned().unwrap_or_default();
+                editor.set_typing_attrs(Some(typing_attrs.merge(change)));
+                return;
+            }
+        }
+
+        let Some((start, end)) = self.resolve_range(range) else {
+            return;
+        };
+        if self.with_buffer_mut(|buffer| restyle(buffer, start, end, change_for_line)) {
+            self.mark_restyled();
+        }
+    }
+}
+
+impl CosmicEditor {
+    /// Attributes applied to newly typed text.
+    ///
+    /// Without them, typed text has the attributes of the text before the cursor.
+    pub fn typing_attrs(&self) -> Option<&AttrsChange> {
+        self.typing_attrs.as_ref().map(|(change, _)| change)
+    }
+
+    /// Sets the attributes applied to newly typed text, until the cursor is moved
+    /// somewhere else
+    pub fn set_typing_attrs(&mut self, change: Option<AttrsChange>) {
+        let cursor = self.cursor();
+        self.typing_attrs = change.map(|change| (change, cursor));
+    }
+}
+
+fn apply_typing_attrs(trigger: Trigger<CosmicTextEdited>, mut editors: Query<&mut CosmicEditor>) {
+    let event = trigger.event();
+    if event.cause != EditCause::Typed {
+        return;
+    }
+    let Ok(mut editor) = editors.get_mut(trigger.entity()) else {
+        return;
+    };
+    let Some((change, _)) = editor.typing_attrs.clone() else {
+        return;
+    };
+    for delta in event.deltas.iter() {
+        if delta.kind == DeltaKind::Inserted {
+            let start = Cursor::new(delta.start.line, delta.start.byte);
+            let end = Cursor::new(delta.end.line, delta.end.byte);
+            editor.with_buffer_mut(|buffer| restyle(buffer, start, end, |_| change.clone()));
+        }
+    }
+    editor.set_typing_attrs(Some(change));
+}
+
+fn forget_moved_typing_attrs(mut editors: Query<&mut CosmicEditor, Changed<CosmicEditor>>) {
+    for mut editor in editors.iter_mut() {
+        let cursor = editor.cursor();
+        if editor
+            .typing_attrs
+            .as_ref()
+            .is_some_and(|(_, at)| (at.line, at.index) != (cursor.line, cursor.index))
+        {
+            editor.typing_attrs = None;
+        }
+    }
+}
+
+pub(crate) fn send_attrs_changed(
+    mut buffers: Query<(Entity, &mut PendingRestyle), Changed<PendingRestyle>>,
+    mut evw_changed: EventWriter<CosmicAttrsChanged>,
+) {
+    for (entity, mut pending) in buffers.iter_mut() {
+        if pending.0 {
+            pending.bypass_change_detection().0 = false;
+            evw_changed.send(CosmicAttrsChanged(entity));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn merges_and_toggles_spans() {
+        let mut font_system = test_font_system();
+        let buffer = test_buffer(&mut font_system, "hello world");
+
+        let mut world = World::new();
+        world.init_resource::<Assets<Image>>();
+        let entity = world.spawn(buffer).id();
+        let restyle = |world: &mut World, f: fn(&mut EditorBufferItem) -> bool| {
+            world
+                .run_system_once(move |mut buffers: Query<EditorBuffer>| {
+                    f(&mut buffers.single_mut())
+                })
+                .unwrap()
+        };
+        let spans = |world: &World| {
+            let buffer = world.get::<CosmicEditBuffer>(entity).unwrap().inner();
+            let attrs_list = buffer.lines[0].attrs_list();
+            attrs_list
+                .spans()
+                .into_iter()
+                .map(|(range, attrs)| (range.clone(), attrs.weight))
+                .collect::<Vec<_>>()
+        };
+
+        restyle(&mut world, |buffer| {
+            buffer.apply_attrs(TextRange::Bytes(0..5), &AttrsChange::bold());
+            buffer.apply_attrs(TextRange::Bytes(3..8), &AttrsChange::bold());
+            true
+        });
+        assert_eq!(spans(&world), [(0..8, Weight::BOLD)]);
+        assert!(world.get::<PendingRestyle>(entity).unwrap().0);
+
+        let applied = restyle(&mut world, |buffer| {
+            buffer.toggle_attrs(TextRange::Bytes(2..4), &AttrsChange::bold())
+        });
+        assert!(!applied);
+        assert_eq!(spans(&world), [(0..2, Weight::BOLD), (4..8, Weight::BOLD)]);
+
+        restyle(&mut world, |buffer| {
+            buffer.remove_attrs(TextRange::Bytes(0..11), &AttrsChange::bold());
+            true
+        });
+        assert_eq!(spans(&world), []);
+    }
+}
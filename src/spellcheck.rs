@@ -0,0 +1,472 @@
+//! Underlining misspelled words and suggesting corrections, see [`Spellcheck`]
+
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    ecs::system::SystemParam,
+};
+use cosmic_text::{Buffer, Cursor, Edit};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    editor_buffer::EditorBufferItem,
+    highlights::{Highlight, TextHighlights, UnderlineStyle},
+    input::delta::CosmicTextEdited,
+    password::Password,
+    placeholder::Placeholder,
+    prelude::*,
+};
+
+/// Adds support for the [`Spellcheck`] resource and loading [`Dictionary`] assets
+pub struct SpellcheckPlugin;
+
+impl Plugin for SpellcheckPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Dictionary>()
+            .init_asset_loader::<DictionaryLoader>()
+            .add_observer(mark_misspellings_stale)
+            .add_systems(
+                PostUpdate,
+                (
+                    check_spelling.run_if(resource_exists::<Spellcheck>),
+                    remove_misspellings.run_if(resource_removed::<Spellcheck>),
+                )
+                    .after(crate::input::delta::send_programmatic_edits)
+                    .before(crate::password::PasswordSet),
+            );
+    }
+}
+
+/// Decides which words are spelled correctly
+pub trait SpellChecker: Send + Sync + 'static {
+    fn is_correct(&self, word: &str) -> bool;
+
+    /// Up to `max` corrections for `word`, best first
+    fn suggest(&self, word: &str, max: usize) -> Vec<String>;
+}
+
+/// A list of correctly spelled words, ignoring case.
+///
+/// Loads from `.words` files with one word per line
+#[derive(Asset, TypePath, Debug, Clone, Default)]
+pub struct Dictionary {
+    words: HashSet<String>,
+}
+
+fn normalize(word: &str) -> String {
+    word.to_lowercase().replace('’', "'")
+}
+
+/// Number of single character edits turning `a` into `b`, if at most `limit`
+fn edit_distance(a: &[char], b: &[char], limit: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > limit {
+        return None;
+    }
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        if current.iter().all(|distance| *distance > limit) {
+            return None;
+        }
+        previous = current;
+    }
+    Some(previous[b.len()]).filter(|distance| *distance <= limit)
+}
+
+impl Dictionary {
+    pub fn from_words<'a>(words: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut dictionary = Self::default();
+        for word in words {
+            dictionary.insert(word);
+        }
+        dictionary
+    }
+
+    /// Adds `word`, e.g. when the user chooses to add it to the dictionary
+    pub fn insert(&mut self, word: &str) {
+        let word = word.trim();
+        if !word.is_empty() {
+            self.words.insert(normalize(word));
+        }
+    }
+}
+
+impl SpellChecker for Dictionary {
+    fn is_correct(&self, word: &str) -> bool {
+        self.words.contains(&normalize(word))
+    }
+
+    /// Words at most two edits away, matching the capitalization of `word`
+    fn suggest(&self, word: &str, max: usize) -> Vec<String> {
+        let chars = normalize(word).chars().collect::<Vec<_>>();
+        let mut suggestions = self
+            .words
+            .iter()
+            .filter_map(|candidate| {
+                let candidate_chars = candidate.chars().collect::<Vec<_>>();
+                edit_distance(&chars, &candidate_chars, 2).map(|distance| (distance, candidate))
+            })
+            .collect::<Vec<_>>();
+        suggestions.sort_unstable();
+
+        let capitalized = word.chars().next().is_some_and(char::is_uppercase);
+        suggestions
+            .into_iter()
+            .take(max)
+            .map(|(_, suggestion)| match capitalized {
+                true => {
+                    let mut chars = suggestion.chars();
+                    chars
+                        .next()
+                        .map(|first| first.to_uppercase().chain(chars).collect())
+                        .unwrap_or_default()
+                }
+                false => suggestion.clone(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct DictionaryLoader;
+
+impl AssetLoader for DictionaryLoader {
+    type Asset = Dictionary;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Dictionary, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let text = String::from_utf8(bytes)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        Ok(Dictionary::from_words(text.lines()))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["words"]
+    }
+}
+
+/// Where words are checked from
+#[derive(Clone)]
+pub enum SpellcheckSource {
+    /// A word list, checked once it has loaded
+    Dictionary(Handle<Dictionary>),
+    Custom(Arc<dyn SpellChecker>),
+}
+
+/// Which widgets are spellchecked
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpellcheckScope {
+    /// Only the [`FocusedWidget`]
+    #[default]
+    Focused,
+    All,
+}
+
+/// Underlines misspelled words, see [`SpellcheckSuggestions`] for corrections.
+///
+/// Requires a [`SpellcheckPlugin`]. Widgets with a [`Password`] aren't checked.
+///
+/// ```no_run
+/// # use bevy::prelude::*;
+/// # use bevy_cosmic_edit::prelude::*;
+/// use bevy_cosmic_edit::spellcheck::{Spellcheck, SpellcheckPlugin};
+///
+/// # fn main() {
+/// # let mut app = App::new();
+/// app.add_plugins(SpellcheckPlugin).add_systems(Startup, setup);
+/// # }
+///
+/// fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+///     let dictionary = asset_server.load("dictionaries/en.words");
+///     commands.insert_resource(Spellcheck::dictionary(dictionary));
+/// }
+/// ```
+#[derive(Resource, Clone)]
+pub struct Spellcheck {
+    pub source: SpellcheckSource,
+    pub scope: SpellcheckScope,
+    pub color: Color,
+    /// How long to wait after the last edit before checking again
+    pub debounce: Duration,
+}
+
+impl Spellcheck {
+    pub fn new(source: SpellcheckSource) -> Self {
+        Self {
+            source,
+            scope: SpellcheckScope::Focused,
+            color: Color::srgb(0.9, 0.1, 0.1),
+            debounce: Duration::from_millis(300),
+        }
+    }
+
+    pub fn dictionary(dictionary: Handle<Dictionary>) -> Self {
+        Self::new(SpellcheckSource::Dictionary(dictionary))
+    }
+
+    pub fn custom(checker: impl SpellChecker) -> Self {
+        Self::new(SpellcheckSource::Custom(Arc::new(checker)))
+    }
+
+    pub fn with_scope(mut self, scope: SpellcheckScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    fn checker<'a>(&'a self, dictionaries: &'a Assets<Dictionary>) -> Option<&'a dyn SpellChecker> {
+        match &self.source {
+            SpellcheckSource::Dictionary(handle) => dictionaries
+                .get(handle)
+                .map(|dictionary| dictionary as &dyn SpellChecker),
+            SpellcheckSource::Custom(checker) => Some(checker.as_ref()),
+        }
+    }
+}
+
+/// The misspelled words of a spellchecked widget, added and updated by [`Spellcheck`]
+#[derive(Component, Debug, Clone)]
+pub struct Misspellings {
+    highlights: TextHighlights,
+    /// When the text was edited since it was last checked
+    stale_since: Option<Duration>,
+}
+
+impl Default for Misspellings {
+    fn default() -> Self {
+        Self {
+            highlights: TextHighlights::default(),
+            stale_since: Some(Duration::ZERO),
+        }
+    }
+}
+
+impl Misspellings {
+    /// The underlined words
+    pub fn highlights(&self) -> &[Highlight] {
+        &self.highlights.0
+    }
+}
+
+/// Bounds of the words in `buffer` that `checker` doesn't accept
+fn find_misspellings(buffer: &Buffer, checker: &dyn SpellChecker) -> Vec<(Cursor, Cursor)> {
+    let mut misspellings = Vec::new();
+    for (line_i, line) in buffer.lines.iter().enumerate() {
+        for (index, word) in line.text().split_word_bound_indices() {
+            let is_word = word.chars().next().is_some_and(char::is_alphabetic)
+                && word
+                    .chars()
+                    .all(|c| c.is_alphabetic() || c == '\'' || c == '’');
+            if is_word && !checker.is_correct(word) {
+                misspellings.push((
+                    Cursor::new(line_i, index),
+                    Cursor::new(line_i, index + word.len()),
+                ));
+            }
+        }
+    }
+    misspellings
+}
+
+fn mark_misspellings_stale(
+    trigger: Trigger<CosmicTextEdited>,
+    mut misspellings: Query<&mut Misspellings>,
+    time: Res<Time>,
+) {
+    let Ok(mut misspellings) = misspellings.get_mut(trigger.entity()) else {
+        return;
+    };
+    for delta in trigger.event().deltas.iter() {
+        misspellings.highlights.anchor(delta);
+    }
+    misspellings.stale_since = Some(time.elapsed());
+}
+
+#[allow(clippy::type_complexity)]
+fn check_spelling(
+    mut commands: Commands,
+    spellcheck: Res<Spellcheck>,
+    dictionaries: Res<Assets<Dictionary>>,
+    mut evr_dictionaries: EventReader<AssetEvent<Dictionary>>,
+    focused: Res<FocusedWidget>,
+    time: Res<Time>,
+    mut buffers: Query<
+        (
+            Entity,
+            Option<&CosmicEditor>,
+            &CosmicEditBuffer,
+            Option<&mut Misspellings>,
+            Option<&Placeholder>,
+        ),
+        Without<Password>,
+    >,
+) {
+    let recheck_all = spellcheck.is_changed() || evr_dictionaries.read().count() > 0;
+    let checker = spellcheck.checker(&dictionaries);
+    for (entity, editor, buffer, misspellings, placeholder) in buffers.iter_mut() {
+        let in_scope = match spellcheck.scope {
+            SpellcheckScope::Focused => focused.0 == Some(entity),
+            SpellcheckScope::All => true,
+        };
+        let Some(mut misspellings) = misspellings else {
+            if in_scope {
+                commands.entity(entity).insert(Misspellings::default());
+            }
+            continue;
+        };
+        if !in_scope {
+            commands.entity(entity).remove::<Misspellings>();
+            continue;
+        }
+
+        if recheck_all {
+            misspellings.stale_since = Some(Duration::ZERO);
+        }
+        let Some(stale_since) = misspellings.stale_since else {
+            continue;
+        };
+        let Some(checker) = checker else {
+            continue;
+        };
+        if time.elapsed().saturating_sub(stale_since) < spellcheck.debounce {
+            continue;
+        }
+
+        let found = match placeholder.is_some_and(Placeholder::is_active) {
+            true => Vec::new(),
+            false => match editor {
+                Some(editor) => editor.with_buffer(|buffer| find_misspellings(buffer, checker)),
+                None => find_misspellings(buffer.inner(), checker),
+            },
+        };
+        misspellings.highlights.0 = found
+            .into_iter()
+            .map(|(start, end)| {
+                Highlight::new(start, end).underline(UnderlineStyle::Squiggly, spellcheck.color)
+            })
+            .collect();
+        misspellings.stale_since = None;
+    }
+}
+
+fn remove_misspellings(mut commands: Commands, misspellings: Query<Entity, With<Misspellings>>) {
+    for entity in misspellings.iter() {
+        commands.entity(entity).remove::<Misspellings>();
+    }
+}
+
+/// A misspelled word with corrections for it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Misspelling {
+    pub start: Cursor,
+    pub end: Cursor,
+    pub word: String,
+    pub suggestions: Vec<String>,
+}
+
+/// Looks up corrections for misspelled words, e.g. to show a replace menu.
+///
+/// Replace the word with [`EditorBufferItem::replace_range`] on the same buffer.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_cosmic_edit::prelude::*;
+/// use bevy_cosmic_edit::spellcheck::SpellcheckSuggestions;
+///
+/// fn apply_first_suggestion(
+///     suggestions: SpellcheckSuggestions,
+///     mut buffers: Query<EditorBuffer>,
+///     focused: Res<FocusedWidget>,
+///     keys: Res<ButtonInput<KeyCode>>,
+/// ) {
+///     let Some(entity) = focused.0.filter(|_| keys.just_pressed(KeyCode::F7)) else {
+///         return;
+///     };
+///     let Ok(mut buffer) = buffers.get_mut(entity) else {
+///         return;
+///     };
+///     if let Some(misspelling) = suggestions.at_cursor(entity, &buffer, 1) {
+///         if let Some(correction) = misspelling.suggestions.first() {
+///             buffer.replace_range(misspelling.start, misspelling.end, correction);
+///         }
+///     }
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct SpellcheckSuggestions<'w, 's> {
+    spellcheck: Option<Res<'w, Spellcheck>>,
+    dictionaries: Res<'w, Assets<Dictionary>>,
+    misspellings: Query<'w, 's, &'static Misspellings>,
+}
+
+impl SpellcheckSuggestions<'_, '_> {
+    /// The misspelled word at the cursor of `entity`'s `buffer`, with up to `max` suggestions
+    pub fn at_cursor(
+        &self,
+        entity: Entity,
+        buffer: &EditorBufferItem,
+        max: usize,
+    ) -> Option<Misspelling> {
+        let checker = self.spellcheck.as_ref()?.checker(&self.dictionaries)?;
+        let misspellings = self.misspellings.get(entity).ok()?;
+        let cursor = buffer.cursor()?;
+        let highlight = misspellings.highlights().iter().find(|highlight| {
+            highlight.start.line == cursor.line
+                && (highlight.start.index..=highlight.end.index).contains(&cursor.index)
+        })?;
+        let word = buffer.with_buffer(|buffer| {
+            buffer.lines[cursor.line]
+                .text()
+                .get(highlight.start.index..highlight.end.index)
+                .map(str::to_owned)
+        })?;
+        Some(Misspelling {
+            start: highlight.start,
+            end: highlight.end,
+            suggestions: checker.suggest(&word, max),
+            word,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_misspellings_and_suggestions() {
+        let mut font_system = test_font_system();
+        let mut buffer = Buffer::new(&mut font_system, cosmic_text::Metrics::new(20., 20.));
+        buffer.set_text(
+            &mut font_system,
+            "The quick brwn fox,\ndon't jmup 42 times",
+            cosmic_text::Attrs::new(),
+            cosmic_text::Shaping::Advanced,
+        );
+        let dictionary = Dictionary::from_words(
+            "the\nquick\nbrown\nfox\ndon't\njump\ntimes\nbrain\nbrew".lines(),
+        );
+
+        assert_eq!(
+            find_misspellings(&buffer, &dictionary),
+            [
+                (Cursor::new(0, 10), Cursor::new(0, 14)),
+                (Cursor::new(1, 6), Cursor::new(1, 10)),
+            ]
+        );
+        assert_eq!(dictionary.suggest("brwn", 2), ["brown", "brain"]);
+        assert_eq!(dictionary.suggest("Jmup", 5), ["Jump"]);
+    }
+}
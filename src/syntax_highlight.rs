@@ -0,0 +1,540 @@
+//! Syntax highlighting for code editors, see [`SyntaxHighlight`]
+
+use std::{ops::Range, sync::Arc};
+
+use bevy::utils::HashMap;
+use cosmic_text::{AttrsList, AttrsOwned, Color};
+
+use crate::{
+    input::delta::{CosmicTextEdited, DeltaKind},
+    placeholder::Placeholder,
+    prelude::*,
+    rich_text::AttrsChange,
+};
+
+pub(crate) struct SyntaxHighlightPlugin;
+
+impl Plugin for SyntaxHighlightPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(invalidate_edited_lines).add_systems(
+            PostUpdate,
+            highlight_lines
+                .after(crate::input::delta::send_programmatic_edits)
+                .before(crate::rich_text::send_attrs_changed),
+        );
+    }
+}
+
+/// What a token is, to choose its style from a [`HighlightTheme`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Keyword,
+    Type,
+    Function,
+    Macro,
+    String,
+    Number,
+    Constant,
+    Comment,
+    Operator,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    /// Byte range in the line
+    pub range: Range<usize>,
+    pub kind: TokenKind,
+}
+
+/// Carried from the end of one line to the start of the next, e.g. to continue a block
+/// comment. The default is the state at the start of the text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct HighlightState(pub u32);
+
+/// Splits lines into tokens to highlight
+pub trait Highlighter: Send + Sync + 'static {
+    /// Pushes the tokens of `line` to `tokens`, starting in the state the previous line
+    /// ended in, and returns the state at the end of the line
+    fn highlight_line(
+        &self,
+        line: &str,
+        state: HighlightState,
+        tokens: &mut Vec<Token>,
+    ) -> HighlightState;
+}
+
+/// Styles of each [`TokenKind`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct HighlightTheme(pub HashMap<TokenKind, AttrsChange>);
+
+impl Default for HighlightTheme {
+    fn default() -> Self {
+        let color = |r, g, b| AttrsChange::new().color(Color::rgb(r, g, b));
+        Self(HashMap::from_iter([
+            (TokenKind::Keyword, color(0xc6, 0x78, 0xdd)),
+            (TokenKind::Type, color(0xe5, 0xc0, 0x7b)),
+            (TokenKind::Function, color(0x61, 0xaf, 0xef)),
+            (TokenKind::Macro, color(0x56, 0xb6, 0xc2)),
+            (TokenKind::String, color(0x98, 0xc3, 0x79)),
+            (TokenKind::Number, color(0xd1, 0x9a, 0x66)),
+            (TokenKind::Constant, color(0xd1, 0x9a, 0x66)),
+            (
+                TokenKind::Comment,
+                color(0x7f, 0x84, 0x8e).style(cosmic_text::Style::Italic),
+            ),
+            (TokenKind::Operator, color(0x56, 0xb6, 0xc2)),
+        ]))
+    }
+}
+
+/// Highlights the text with a [`Highlighter`], through each line's attributes.
+///
+/// Only lines that were edited are highlighted again, along with the lines after them
+/// until they end in the same [`HighlightState`] as before.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_cosmic_edit::prelude::*;
+/// use bevy_cosmic_edit::syntax_highlight::{Grammar, SyntaxHighlight};
+///
+/// # fn setup(mut commands: Commands) {
+/// commands.spawn((TextEdit, SyntaxHighlight::new(Grammar::rust())));
+/// # }
+/// ```
+#[derive(Component)]
+pub struct SyntaxHighlight {
+    pub highlighter: Arc<dyn Highlighter>,
+    pub theme: HighlightTheme,
+    /// States at the start and end of each line when it was last highlighted,
+    /// `None` if it needs highlighting
+    lines: Vec<Option<(HighlightState, HighlightState)>>,
+    dirty: bool,
+}
+
+impl SyntaxHighlight {
+    pub fn new(highlighter: impl Highlighter) -> Self {
+        Self {
+            highlighter: Arc::new(highlighter),
+            theme: HighlightTheme::default(),
+            lines: Vec::new(),
+            dirty: true,
+        }
+    }
+
+    pub fn with_theme(mut self, theme: HighlightTheme) -> Self {
+        self.theme = theme;
+        self
+    }
+}
+
+/// Marks edited lines to be highlighted again
+fn invalidate_edited_lines(
+    trigger: Trigger<CosmicTextEdited>,
+    mut highlights: Query<&mut SyntaxHighlight>,
+) {
+    let Ok(mut highlight) = highlights.get_mut(trigger.entity()) else {
+        return;
+    };
+    let highlight = highlight.bypass_change_detection();
+    highlight.dirty = true;
+    for delta in trigger.event().deltas.iter() {
+        let (start, end) = (delta.start.line, delta.end.line);
+        if start >= highlight.lines.len() {
+            continue;
+        }
+        highlight.lines[start] = None;
+        match delta.kind {
+            DeltaKind::Inserted => {
+                let added = std::iter::repeat_n(None, end - start);
+                highlight.lines.splice(start + 1..start + 1, added);
+            }
+            DeltaKind::Deleted => {
+                let removed = start + 1..(end + 1).min(highlight.lines.len());
+                highlight.lines.drain(removed);
+            }
+        }
+    }
+}
+
+fn highlight_lines(
+    mut buffers: Query<(EditorBuffer, &mut SyntaxHighlight, Option<&Placeholder>)>,
+    mut tokens: Local<Vec<Token>>,
+) {
+    for (mut buffer, mut highlight, placeholder) in buffers.iter_mut() {
+        let highlight_changed = highlight.is_changed();
+        let highlight = highlight.bypass_change_detection();
+        if !highlight_changed && !highlight.dirty {
+            continue;
+        }
+        if placeholder.is_some_and(Placeholder::is_active) {
+            continue;
+        }
+        highlight.dirty = false;
+
+        let restyled = buffer.with_buffer_mut(|buffer| {
+            if highlight_changed || highlight.lines.len() != buffer.lines.len() {
+                highlight.lines = vec![None; buffer.lines.len()];
+            }
+
+            let mut restyled = false;
+            let mut state = HighlightState::default();
+            for (line, cached) in buffer.lines.iter_mut().zip(highlight.lines.iter_mut()) {
+                if let Some((start, end)) = *cached {
+                    if start == state {
+                        state = end;
+                        continue;
+                    }
+                }
+
+                tokens.clear();
+                let end = highlight
+                    .highlighter
+                    .highlight_line(line.text(), state, &mut tokens);
+                let defaults = AttrsOwned::new(line.attrs_list().defaults());
+                let mut attrs_list = AttrsList::new(defaults.as_attrs());
+                for token in tokens.iter() {
+                    if let Some(change) = highlight.theme.0.get(&token.kind) {
+                        attrs_list.add_span(token.range.clone(), change.apply(defaults.as_attrs()));
+                    }
+                }
+                restyled |= line.set_attrs_list(attrs_list);
+                *cached = Some((state, end));
+                state = end;
+            }
+            if restyled {
+                buffer.set_redraw(true);
+            }
+            restyled
+        });
+        if restyled {
+            buffer.mark_restyled();
+        }
+    }
+}
+
+/// Delimiters of a kind of string
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StringDelimiters {
+    pub open: &'static str,
+    pub close: &'static str,
+    /// Whether the string can continue onto the next line
+    pub multiline: bool,
+}
+
+impl StringDelimiters {
+    pub const fn new(open: &'static str, close: &'static str, multiline: bool) -> Self {
+        Self {
+            open,
+            close,
+            multiline,
+        }
+    }
+}
+
+/// A simple [`Highlighter`] for languages described by their keywords, comments and strings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grammar {
+    pub keywords: &'static [&'static str],
+    pub types: &'static [&'static str],
+    pub constants: &'static [&'static str],
+    /// Identifiers starting with an uppercase letter are types
+    pub capitalized_types: bool,
+    /// Identifiers followed by `!` are macros
+    pub macros: bool,
+    pub line_comments: &'static [&'static str],
+    pub block_comment: Option<(&'static str, &'static str)>,
+    /// Checked in order, so put longer delimiters first
+    pub strings: &'static [StringDelimiters],
+}
+
+/// States of a [`Grammar`], after which come the strings
+const IN_BLOCK_COMMENT: u32 = 1;
+const IN_STRING: u32 = 2;
+
+impl Grammar {
+    pub fn rust() -> Self {
+        Self {
+            keywords: &[
+                "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else",
+                "enum", "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+                "move", "mut", "pub", "ref", "return", "self", "static", "struct", "super",
+                "trait", "type", "unsafe", "use", "where", "while",
+            ],
+            types: &[
+                "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16",
+                "i32", "i64", "i128", "isize", "f32", "f64",
+            ],
+            constants: &["true", "false"],
+            capitalized_types: true,
+            macros: true,
+            line_comments: &["//"],
+            block_comment: Some(("/*", "*/")),
+            strings: const { &[StringDelimiters::new("\"", "\"", true)] },
+        }
+    }
+
+    pub fn python() -> Self {
+        Self {
+            keywords: &[
+                "and", "as", "assert", "async", "await", "break", "class", "continue", "def",
+                "del", "elif", "else", "except", "finally", "for", "from", "global", "if",
+                "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return",
+                "try", "while", "with", "yield",
+            ],
+            types: &[
+                "bool", "bytes", "dict", "float", "int", "list", "object", "set", "str", "tuple",
+            ],
+            constants: &["True", "False", "None"],
+            capitalized_types: true,
+            macros: false,
+            line_comments: &["#"],
+            block_comment: None,
+            strings: const {
+                &[
+                    StringDelimiters::new("\"\"\"", "\"\"\"", true),
+                    StringDelimiters::new("'''", "'''", true),
+                    StringDelimiters::new("\"", "\"", false),
+                    StringDelimiters::new("'", "'", false),
+                ]
+            },
+        }
+    }
+
+    pub fn lua() -> Self {
+        Self {
+            keywords: &[
+                "and", "break", "do", "else", "elseif", "end", "for", "function", "goto", "if",
+                "in", "local", "not", "or", "repeat", "return", "then", "until", "while",
+            ],
+            types: &[],
+            constants: &["true", "false", "nil"],
+            capitalized_types: false,
+            macros: false,
+            line_comments: &["--"],
+            block_comment: Some(("--[[", "]]")),
+            strings: const {
+                &[
+                    StringDelimiters::new("[[", "]]", true),
+                    StringDelimiters::new("\"", "\"", false),
+                    StringDelimiters::new("'", "'", false),
+                ]
+            },
+        }
+    }
+
+    pub fn json() -> Self {
+        Self {
+            keywords: &[],
+            types: &[],
+            constants: &["true", "false", "null"],
+            capitalized_types: false,
+            macros: false,
+            line_comments: &[],
+            block_comment: None,
+            strings: const { &[StringDelimiters::new("\"", "\"", false)] },
+        }
+    }
+
+    fn classify(&self, word: &str, next: Option<char>) -> Option<TokenKind> {
+        if self.keywords.contains(&word) {
+            Some(TokenKind::Keyword)
+        } else if self.constants.contains(&word) {
+            Some(TokenKind::Constant)
+        } else if self.types.contains(&word)
+            || (self.capitalized_types && word.starts_with(|c: char| c.is_uppercase()))
+        {
+            Some(TokenKind::Type)
+        } else if self.macros && next == Some('!') {
+            Some(TokenKind::Macro)
+        } else if next == Some('(') {
+            Some(TokenKind::Function)
+        } else {
+            None
+        }
+    }
+}
+
+/// Byte offset just after `close` in `text`, skipping escaped characters
+fn find_close(text: &str, close: &str, escapes: bool) -> Option<usize> {
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if text[i..].starts_with(close) {
+            return Some(i + close.len());
+        }
+        if escapes && c == '\\' {
+            chars.next();
+        }
+    }
+    None
+}
+
+impl Highlighter for Grammar {
+    fn highlight_line(
+        &self,
+        line: &str,
+        mut state: HighlightState,
+        tokens: &mut Vec<Token>,
+    ) -> HighlightState {
+        let mut push = |range: Range<usize>, kind| tokens.push(Token { range, kind });
+        let mut i = 0;
+        while i < line.len() {
+            let rest = &line[i..];
+
+            // continue a comment or string from before
+            let continued = match state.0 {
+                IN_BLOCK_COMMENT => self
+                    .block_comment
+                    .map(|(_, close)| (close, TokenKind::Comment, false)),
+                string if string >= IN_STRING => self
+                    .strings
+                    .get((string - IN_STRING) as usize)
+                    .map(|delimiters| (delimiters.close, TokenKind::String, true)),
+                _ => None,
+            };
+            if let Some((close, kind, escapes)) = continued {
+                match find_close(rest, close, escapes) {
+                    Some(end) => {
+                        push(i..i + end, kind);
+                        i += end;
+                        state = HighlightState::default();
+                    }
+                    None => {
+                        push(i..line.len(), kind);
+                        i = line.len();
+                    }
+                }
+                continue;
+            }
+            state = HighlightState::default();
+
+            if let Some((open, _)) = self
+                .block_comment
+                .filter(|(open, _)| rest.starts_with(open))
+            {
+                push(i..i + open.len(), TokenKind::Comment);
+                i += open.len();
+                state = HighlightState(IN_BLOCK_COMMENT);
+                continue;
+            }
+            if self
+                .line_comments
+                .iter()
+                .any(|start| rest.starts_with(start))
+            {
+                push(i..line.len(), TokenKind::Comment);
+                break;
+            }
+            if let Some(index) = self.strings.iter().position(|s| rest.starts_with(s.open)) {
+                let open = self.strings[index].open;
+                push(i..i + open.len(), TokenKind::String);
+                i += open.len();
+                state = HighlightState(IN_STRING + index as u32);
+                continue;
+            }
+
+            let c = rest.chars().next().unwrap();
+            if c.is_ascii_digit() {
+                let number_len = rest
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+                    .unwrap_or(rest.len());
+                push(i..i + number_len, TokenKind::Number);
+                i += number_len;
+            } else if c.is_alphabetic() || c == '_' {
+                let word_len = rest
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                let next = rest[word_len..].trim_start().chars().next();
+                if let Some(kind) = self.classify(&rest[..word_len], next) {
+                    push(i..i + word_len, kind);
+                }
+                i += word_len;
+            } else {
+                if "+-*/%=<>!&|^~?:".contains(c) {
+                    push(i..i + c.len_utf8(), TokenKind::Operator);
+                }
+                i += c.len_utf8();
+            }
+        }
+
+        let in_line_string = state.0 >= IN_STRING
+            && self
+                .strings
+                .get((state.0 - IN_STRING) as usize)
+                .is_some_and(|delimiters| !delimiters.multiline);
+        if in_line_string {
+            state = HighlightState::default();
+        }
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    fn kinds<'a>(grammar: &Grammar, line: &'a str) -> Vec<(&'a str, TokenKind)> {
+        let mut tokens = Vec::new();
+        grammar.highlight_line(line, HighlightState::default(), &mut tokens);
+        tokens
+            .into_iter()
+            .map(|token| (&line[token.range], token.kind))
+            .collect()
+    }
+
+    #[test]
+    fn tokenizes_rust() {
+        assert_eq!(
+            kinds(&Grammar::rust(), r#"let s: String = f("a\"b"); // done"#),
+            [
+                ("let", TokenKind::Keyword),
+                (":", TokenKind::Operator),
+                ("String", TokenKind::Type),
+                ("=", TokenKind::Operator),
+                ("f", TokenKind::Function),
+                ("\"", TokenKind::String),
+                ("a\\\"b\"", TokenKind::String),
+                ("// done", TokenKind::Comment),
+            ]
+        );
+    }
+
+    #[test]
+    fn rehighlights_following_lines() {
+        let mut font_system = test_font_system();
+        let buffer = test_buffer(&mut font_system, "fn\nfn");
+
+        let mut world = World::new();
+        world.init_resource::<Assets<Image>>();
+        world.insert_resource(CosmicFontSystem(font_system));
+        world.add_observer(invalidate_edited_lines);
+        let entity = world
+            .spawn((buffer, SyntaxHighlight::new(Grammar::rust())))
+            .id();
+        let line_color = |world: &World, line: usize| {
+            let buffer = world.get::<CosmicEditBuffer>(entity).unwrap().inner();
+            buffer.lines[line].attrs_list().get_span(0).color_opt
+        };
+        let theme = HighlightTheme::default();
+        let color_of = |kind| theme.0[&kind].color.unwrap();
+
+        world.run_system_once(highlight_lines).unwrap();
+        assert_eq!(line_color(&world, 1), color_of(TokenKind::Keyword));
+
+        world
+            .run_system_once(
+                |mut buffers: Query<EditorBuffer>, mut font_system: ResMut<CosmicFontSystem>| {
+                    let mut buffer = buffers.single_mut();
+                    buffer.set_text(&mut font_system, "/*\nfn", cosmic_text::Attrs::new());
+                },
+            )
+            .unwrap();
+        world.init_resource::<Events<crate::input::CosmicTextChanged>>();
+        world
+            .run_system_once(crate::input::delta::send_programmatic_edits)
+            .unwrap();
+        world.run_system_once(highlight_lines).unwrap();
+        assert_eq!(line_color(&world, 1), color_of(TokenKind::Comment));
+    }
+}
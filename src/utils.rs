@@ -99,3 +99,28 @@ pub(crate) fn get_timestamp() -> f64 {
     let duration = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
     duration.as_millis() as f64
 }
+
+/// The bundled font only, so tests don't depend on the system fonts
+#[cfg(test)]
+pub(crate) fn test_font_db() -> cosmic_text::fontdb::Database {
+    let mut db = cosmic_text::fontdb::Database::new();
+    db.load_font_data(include_bytes!("./font/FiraMono-Regular-subset.ttf").to_vec());
+    db
+}
+
+#[cfg(test)]
+pub(crate) fn test_font_system() -> cosmic_text::FontSystem {
+    cosmic_text::FontSystem::new_with_locale_and_db("en-US".into(), test_font_db())
+}
+
+#[cfg(test)]
+pub(crate) fn test_buffer(
+    font_system: &mut cosmic_text::FontSystem,
+    text: &str,
+) -> CosmicEditBuffer {
+    CosmicEditBuffer::new(font_system, cosmic_text::Metrics::new(20., 20.)).with_text(
+        font_system,
+        text,
+        cosmic_text::Attrs::new(),
+    )
+}